@@ -0,0 +1,277 @@
+use crate::build_worker::WorkerRequest;
+use crate::deadline::Deadline;
+use crate::host::Host;
+use crate::host_fs::HostFs;
+use crate::wasm_module::WasmModule;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// how long we wait for the worker's report before letting zellij try to load the wasm
+const REPORT_TIMEOUT_SECS: f64 = 5.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArtifactVerdict {
+    Compatible,
+    Incompatible(Vec<String>), // why zellij can't load it
+    Unchecked(String),         // we couldn't read or parse it, so we let zellij try
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactReport {
+    pub check_id: u32,
+    pub verdicts: Vec<(PathBuf, ArtifactVerdict)>,
+}
+
+// checks the imports and exports of the wasm each successful build produced, and holds back the
+// reload when zellij couldn't load it anyway, the worker reads and parses the files
+#[derive(Debug, Default)]
+pub struct ArtifactCheck {
+    disabled: bool,
+    check_id: u32,
+    checking: Vec<PathBuf>, // the files we're waiting for the report on
+    verdicts: Vec<(PathBuf, ArtifactVerdict)>, // of the last check
+    reload_allowed: Option<bool>, // once the build's wasm was checked
+    report_timeout: Deadline,
+}
+
+impl ArtifactCheck {
+    pub fn update_config(&mut self, configuration: &BTreeMap<String, String>) {
+        self.disabled = configuration.get("check_artifact").map(|c| c.as_str()) == Some("false");
+    }
+    // whether the check can keep a build from being reloaded
+    pub fn is_enabled(&self) -> bool {
+        !self.disabled
+    }
+    pub fn build_started(&mut self) {
+        self.check_id += 1;
+        self.checking.clear();
+        self.reload_allowed = None;
+        self.report_timeout.cancel();
+    }
+    // returns the request to post to the worker, if there's wasm to check
+    pub fn build_finished(
+        &mut self,
+        host: &mut impl Host,
+        plugin_paths: Vec<PathBuf>,
+        host_fs: &HostFs,
+    ) -> Option<WorkerRequest> {
+        if plugin_paths.is_empty() {
+            self.reload_allowed = Some(true);
+            return None;
+        }
+        self.checking = plugin_paths.clone();
+        self.report_timeout.schedule(host, REPORT_TIMEOUT_SECS);
+        Some(WorkerRequest::CheckArtifacts {
+            check_id: self.check_id,
+            plugin_paths,
+            host_fs: host_fs.clone(),
+        })
+    }
+    // returns true if we gave up on the worker's report, so the reload can be released
+    pub fn timer_elapsed(&mut self) -> bool {
+        if self.report_timeout.elapsed() && !self.checking.is_empty() {
+            self.stop_waiting();
+            return true;
+        }
+        false
+    }
+    pub fn result_received(&mut self, payload: &str) {
+        let report: ArtifactReport = match serde_json::from_str(payload) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("Failed to parse the artifact report: {}", e);
+                return;
+            }
+        };
+        // a check of a build that was restarted since
+        if report.check_id != self.check_id {
+            return;
+        }
+        self.checking.clear();
+        self.report_timeout.cancel();
+        self.verdicts = report.verdicts;
+        self.reload_allowed = Some(!self.is_blocking());
+    }
+    pub fn get_reload_allowed(&self) -> Option<bool> {
+        self.reload_allowed
+    }
+    pub fn is_blocking(&self) -> bool {
+        self.verdicts
+            .iter()
+            .any(|(_, verdict)| matches!(verdict, ArtifactVerdict::Incompatible(_)))
+    }
+    // eg. "reload blocked: my_plugin.wasm: it imports env::sqlite3_open, which zellij doesn't
+    // provide (is a dependency native only?)"
+    pub fn summary(&self) -> Option<String> {
+        if !self.checking.is_empty() {
+            return Some("checking...".to_owned());
+        }
+        if self.verdicts.is_empty() {
+            return None;
+        }
+        let incompatible: Vec<String> = self
+            .verdicts
+            .iter()
+            .filter_map(|(path, verdict)| match verdict {
+                ArtifactVerdict::Incompatible(problems) => {
+                    Some(format!("{}: {}", file_name(path), problems.join("; ")))
+                }
+                _ => None,
+            })
+            .collect();
+        if !incompatible.is_empty() {
+            return Some(format!("reload blocked: {}", incompatible.join(" - ")));
+        }
+        let unchecked: Vec<String> = self
+            .verdicts
+            .iter()
+            .filter_map(|(path, verdict)| match verdict {
+                ArtifactVerdict::Unchecked(e) => Some(format!("{}: {}", file_name(path), e)),
+                _ => None,
+            })
+            .collect();
+        if !unchecked.is_empty() {
+            return Some(format!("couldn't check {}", unchecked.join(" - ")));
+        }
+        Some("exports and imports match what zellij expects".to_owned())
+    }
+    // like for a file we couldn't read, zellij gets to try
+    fn stop_waiting(&mut self) {
+        self.verdicts = self
+            .checking
+            .drain(..)
+            .map(|plugin_path| {
+                let verdict =
+                    ArtifactVerdict::Unchecked("the build worker didn't report on it".to_owned());
+                (plugin_path, verdict)
+            })
+            .collect();
+        self.reload_allowed = Some(true);
+    }
+}
+
+// runs in the worker
+pub fn check_artifacts(
+    plugin_paths: &[PathBuf],
+    host_fs: &HostFs,
+) -> Vec<(PathBuf, ArtifactVerdict)> {
+    plugin_paths
+        .iter()
+        .map(|plugin_path| {
+            let verdict = match host_fs
+                .read_appended(plugin_path, 0)
+                .and_then(|(_, bytes)| WasmModule::parse(&bytes))
+            {
+                Ok(module) => {
+                    let problems = module.compatibility_problems();
+                    if problems.is_empty() {
+                        ArtifactVerdict::Compatible
+                    } else {
+                        ArtifactVerdict::Incompatible(problems)
+                    }
+                }
+                Err(e) => ArtifactVerdict::Unchecked(e),
+            };
+            (plugin_path.clone(), verdict)
+        })
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|f| f.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostEffect, RecordingHost};
+
+    fn plugin_path() -> PathBuf {
+        PathBuf::from("/project/target/wasm32-wasip1/debug/p.wasm")
+    }
+
+    fn report(check_id: u32, verdict: ArtifactVerdict) -> String {
+        serde_json::to_string(&ArtifactReport {
+            check_id,
+            verdicts: vec![(plugin_path(), verdict)],
+        })
+        .unwrap()
+    }
+
+    fn checking() -> ArtifactCheck {
+        let mut artifact_check = ArtifactCheck::default();
+        artifact_check.build_started();
+        let mut host = RecordingHost::default();
+        let request =
+            artifact_check.build_finished(&mut host, vec![plugin_path()], &HostFs::default());
+        assert!(matches!(
+            request,
+            Some(WorkerRequest::CheckArtifacts { check_id: 1, .. })
+        ));
+        assert_eq!(
+            host.take_effects(),
+            vec![HostEffect::SetTimeout(REPORT_TIMEOUT_SECS)]
+        );
+        artifact_check
+    }
+
+    #[test]
+    fn an_incompatible_artifact_blocks_the_reload() {
+        let mut artifact_check = checking();
+        assert_eq!(artifact_check.summary().as_deref(), Some("checking..."));
+        assert_eq!(artifact_check.get_reload_allowed(), None);
+        let incompatible = ArtifactVerdict::Incompatible(vec!["it doesn't export load".to_owned()]);
+        artifact_check.result_received(&report(1, incompatible));
+        assert_eq!(artifact_check.get_reload_allowed(), Some(false));
+        assert_eq!(
+            artifact_check.summary().as_deref(),
+            Some("reload blocked: p.wasm: it doesn't export load")
+        );
+    }
+
+    #[test]
+    fn reports_on_earlier_builds_are_ignored() {
+        let mut artifact_check = checking();
+        artifact_check.result_received(&report(0, ArtifactVerdict::Compatible));
+        assert_eq!(artifact_check.get_reload_allowed(), None);
+        artifact_check.result_received(&report(1, ArtifactVerdict::Compatible));
+        assert_eq!(artifact_check.get_reload_allowed(), Some(true));
+    }
+
+    #[test]
+    fn unchecked_or_unreported_artifacts_are_reloaded() {
+        let mut artifact_check = checking();
+        let unchecked = ArtifactVerdict::Unchecked("it's truncated".to_owned());
+        artifact_check.result_received(&report(1, unchecked));
+        assert_eq!(artifact_check.get_reload_allowed(), Some(true));
+        assert!(!artifact_check.is_blocking());
+        let mut artifact_check = checking();
+        assert!(!artifact_check.timer_elapsed());
+        artifact_check.stop_waiting();
+        assert_eq!(artifact_check.get_reload_allowed(), Some(true));
+        assert_eq!(
+            artifact_check.summary().as_deref(),
+            Some("couldn't check p.wasm: the build worker didn't report on it")
+        );
+    }
+
+    #[test]
+    fn a_build_without_artifacts_isnt_checked() {
+        let mut artifact_check = ArtifactCheck::default();
+        artifact_check.build_started();
+        assert_eq!(
+            artifact_check.build_finished(
+                &mut RecordingHost::default(),
+                vec![],
+                &HostFs::default()
+            ),
+            None
+        );
+        assert_eq!(artifact_check.get_reload_allowed(), Some(true));
+        assert_eq!(artifact_check.summary(), None);
+    }
+}
@@ -0,0 +1,115 @@
+use zellij_tile::prelude::*;
+
+use crate::host::Host;
+use crate::host_fs::HostFs;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// the most we pass to a pane on the command line when we can't write the output to a file
+const MAX_INLINE_OUTPUT_BYTES: usize = 64 * 1024;
+
+// the output of the last quiet build, which isn't shown anywhere unless it fails or we ask for it
+#[derive(Debug, Default)]
+pub struct BuildOutput {
+    output: Option<String>,
+    log_path: Option<PathBuf>, // where we wrote it on the host, if we could
+}
+
+impl BuildOutput {
+    pub fn build_finished(
+        &mut self,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        target_directory: Option<PathBuf>,
+        host_fs: &HostFs,
+    ) {
+        // cargo reports its progress and diagnostics on stderr
+        let mut output = String::from_utf8_lossy(&stderr).to_string();
+        output.push_str(&String::from_utf8_lossy(&stdout));
+        self.log_path = target_directory
+            .map(|t| t.join("zellij-dev").join("build.log"))
+            .and_then(|log_path| match host_fs.write(&log_path, &output) {
+                Ok(()) => Some(log_path),
+                Err(e) => {
+                    eprintln!("Failed to keep the build output: {}", e);
+                    None
+                }
+            });
+        self.output = Some(output);
+    }
+    pub fn has_output(&self) -> bool {
+        self.output.is_some()
+    }
+    pub fn open_pane(&self, host: &mut impl Host, cwd: Option<PathBuf>) {
+        let output = match &self.output {
+            Some(output) => output,
+            None => return,
+        };
+        let args = match &self.log_path {
+            Some(log_path) => vec!["cat".to_owned(), log_path.display().to_string()],
+            None => vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                "printf '%s' \"$1\"".to_owned(),
+                "sh".to_owned(),
+                tail(output, MAX_INLINE_OUTPUT_BYTES).to_owned(),
+            ],
+        };
+        let mut command = CommandToRun::new_with_args(&args[0], args[1..].to_vec());
+        command.cwd = cwd;
+        let mut context = BTreeMap::new();
+        context.insert("source".to_owned(), "build_output".to_owned());
+        host.open_command_pane_floating(command, context);
+    }
+}
+
+// the last max_bytes of the output, where the errors usually are
+fn tail(output: &str, max_bytes: usize) -> &str {
+    let mut start = output.len().saturating_sub(max_bytes);
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    &output[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostEffect, RecordingHost};
+
+    #[test]
+    fn the_output_is_kept_even_when_it_cant_be_written_to_the_host() {
+        let mut build_output = BuildOutput::default();
+        assert!(!build_output.has_output());
+        let mut host_fs = HostFs::default();
+        host_fs.update_root(PathBuf::from("/projects"));
+        build_output.build_finished(
+            b"stdout\n".to_vec(),
+            b"error: oops\n".to_vec(),
+            Some(PathBuf::from("/elsewhere/target")),
+            &host_fs,
+        );
+        assert!(build_output.has_output());
+        assert_eq!(
+            build_output.output.as_deref(),
+            Some("error: oops\nstdout\n")
+        );
+        assert_eq!(build_output.log_path, None);
+        let mut host = RecordingHost::default();
+        build_output.open_pane(&mut host, None);
+        assert!(matches!(
+            host.take_effects().as_slice(),
+            [HostEffect::OpenCommandPaneFloating { command, args, .. }]
+                if command == "sh" && args.last().map(|a| a.as_str()) == Some("error: oops\nstdout\n")
+        ));
+    }
+
+    #[test]
+    fn tail_keeps_the_end_on_a_char_boundary() {
+        assert_eq!(tail("abcdef", 3), "def");
+        assert_eq!(tail("ab", 3), "ab");
+        assert_eq!(tail("aé", 1), "");
+        assert_eq!(tail("aé", 2), "é");
+    }
+}
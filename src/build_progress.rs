@@ -0,0 +1,309 @@
+use crate::build_worker::{BuildUpdate, WorkerRequest};
+use crate::deadline::Deadline;
+use crate::host::Host;
+use crate::host_fs::HostFs;
+use crate::project_data::ProjectData;
+use crate::reload_timing::format_duration;
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL_SECS: f64 = 0.5;
+
+// cargo writes its json messages to a file in the target directory while it builds (see
+// RunAndReload::get_progress_file), which the build worker reads as it grows and tells us about
+#[derive(Debug, Default)]
+pub struct BuildProgress {
+    build_id: u32,
+    following: bool,
+    project_folder: Option<PathBuf>,
+    started: Option<Instant>,
+    elapsed: Option<Duration>, // once the build finished
+    units_compiled: usize,
+    expected_units: Option<usize>, // as many as the last successful build had
+    last_unit: Option<String>,
+    error_count: usize,
+    warning_count: usize,
+    next_poll: Deadline,
+}
+
+impl BuildProgress {
+    // returns true if we follow this build
+    pub fn build_started(
+        &mut self,
+        host: &mut impl Host,
+        project_data: &ProjectData,
+        progress_file: Option<PathBuf>,
+        project_folder: Option<PathBuf>,
+        host_fs: &HostFs,
+    ) -> bool {
+        let progress_file = progress_file.filter(|progress_file| {
+            if let Err(e) = host_fs.mounted_path(progress_file) {
+                eprintln!("Can't follow the build's progress: {}", e);
+                return false;
+            }
+            true
+        });
+        let build_id = self.build_id.wrapping_add(1);
+        self.next_poll.cancel();
+        *self = BuildProgress {
+            build_id,
+            following: progress_file.is_some(),
+            expected_units: project_folder
+                .as_ref()
+                .and_then(|p| project_data.read(p, "build_progress", "txt"))
+                .and_then(|units| units.trim().parse().ok()),
+            project_folder,
+            started: Some(Instant::now()),
+            ..Default::default()
+        };
+        if let Some(progress_file) = progress_file {
+            // so that we don't read the last build's messages before cargo truncates the file,
+            // it's fine if there's none
+            let _ = host_fs.remove_file(&progress_file);
+            WorkerRequest::FollowBuild {
+                build_id,
+                progress_file,
+                host_fs: host_fs.clone(),
+            }
+            .post(host);
+            self.next_poll.schedule(host, POLL_INTERVAL_SECS);
+        }
+        self.following
+    }
+    pub fn build_finished(&mut self, host: &mut impl Host) {
+        if self.is_building() {
+            // whatever cargo wrote last still arrives after this
+            WorkerRequest::PollBuild {
+                build_id: self.build_id,
+            }
+            .post(host);
+            self.elapsed = self.started.map(|s| s.elapsed());
+        }
+        self.next_poll.cancel();
+    }
+    // returns true if we should render
+    pub fn timer_elapsed(&mut self, host: &mut impl Host) -> bool {
+        if self.next_poll.elapsed() && self.is_building() {
+            WorkerRequest::PollBuild {
+                build_id: self.build_id,
+            }
+            .post(host);
+            self.next_poll.schedule(host, POLL_INTERVAL_SECS);
+            // for the elapsed time
+            return true;
+        }
+        false
+    }
+    // returns the update if it's about the build we follow, for its diagnostics
+    pub fn update_received(
+        &mut self,
+        project_data: &ProjectData,
+        payload: &str,
+    ) -> Option<BuildUpdate> {
+        match serde_json::from_str::<BuildUpdate>(payload) {
+            Ok(update) if update.build_id == self.build_id && self.following => {
+                self.apply(project_data, &update);
+                Some(update)
+            }
+            Ok(_) => None, // an update about an earlier build
+            Err(e) => {
+                eprintln!("Failed to parse the build update: {}", e);
+                None
+            }
+        }
+    }
+    // eg. "37/120 crates, compiled serde_json, 14.2s, 1 error"
+    pub fn summary(&self) -> Option<String> {
+        if !self.following {
+            return None;
+        }
+        let mut summary = match (self.elapsed, self.expected_units) {
+            (Some(elapsed), _) => format!(
+                "{} crates in {}",
+                self.units_compiled,
+                format_duration(elapsed)
+            ),
+            (None, expected_units) => {
+                let units = match expected_units {
+                    // the dependencies might have changed since
+                    Some(expected_units) if expected_units >= self.units_compiled => {
+                        format!("{}/{} crates", self.units_compiled, expected_units)
+                    }
+                    _ => format!("{} crates", self.units_compiled),
+                };
+                let last_unit = self
+                    .last_unit
+                    .as_ref()
+                    .map(|l| format!(", compiled {}", l))
+                    .unwrap_or_default();
+                let elapsed = self.started.map(|s| s.elapsed()).unwrap_or_default();
+                format!("{}{}, {}", units, last_unit, format_duration(elapsed))
+            }
+        };
+        if self.error_count > 0 {
+            summary.push_str(&format!(", {}", plural(self.error_count, "error")));
+        }
+        if self.warning_count > 0 {
+            summary.push_str(&format!(", {}", plural(self.warning_count, "warning")));
+        }
+        Some(summary)
+    }
+    fn is_building(&self) -> bool {
+        self.following && self.elapsed.is_none()
+    }
+    fn apply(&mut self, project_data: &ProjectData, update: &BuildUpdate) {
+        if update.restarted {
+            self.units_compiled = 0;
+            self.last_unit = None;
+            self.error_count = 0;
+            self.warning_count = 0;
+        }
+        self.units_compiled += update.units_compiled;
+        if update.last_unit.is_some() {
+            self.last_unit = update.last_unit.clone();
+        }
+        let error_count = update.diagnostics.iter().filter(|d| d.is_error()).count();
+        self.error_count += error_count;
+        self.warning_count += update.diagnostics.len() - error_count;
+        // every unit has an artifact, even the fresh ones, so a successful build tells us how
+        // many the next one will have
+        if update.finished == Some(true) {
+            self.expected_units = Some(self.units_compiled);
+            if let Some(project_folder) = &self.project_folder {
+                let units = self.units_compiled.to_string();
+                project_data.write(project_folder, "build_progress", "txt", &units);
+            }
+        }
+    }
+}
+
+// eg. "1 error" or "2 errors"
+pub fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo_messages::Diagnostic;
+
+    fn following() -> BuildProgress {
+        BuildProgress {
+            build_id: 2,
+            following: true,
+            expected_units: Some(3),
+            ..Default::default()
+        }
+    }
+
+    fn diagnostic(level: &str) -> Diagnostic {
+        serde_json::from_str(&format!(
+            r#"{{"message":"mismatched types","level":"{}","spans":[]}}"#,
+            level
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn updates_of_the_current_build_add_up() {
+        let mut build_progress = following();
+        let update = BuildUpdate {
+            build_id: 2,
+            units_compiled: 2,
+            last_unit: Some("serde_json".to_owned()),
+            diagnostics: vec![diagnostic("error"), diagnostic("warning")],
+            ..Default::default()
+        };
+        assert_eq!(
+            build_progress.update_received(
+                &ProjectData::default(),
+                &serde_json::to_string(&update).unwrap()
+            ),
+            Some(update)
+        );
+        let summary = build_progress.summary().unwrap();
+        assert!(
+            summary.starts_with("2/3 crates, compiled serde_json, "),
+            "{}",
+            summary
+        );
+        assert!(summary.ends_with(", 1 error, 1 warning"), "{}", summary);
+    }
+
+    #[test]
+    fn updates_of_an_earlier_build_or_that_dont_parse_are_ignored() {
+        let mut build_progress = following();
+        let stale_update = BuildUpdate {
+            build_id: 1,
+            units_compiled: 40,
+            ..Default::default()
+        };
+        assert_eq!(
+            build_progress.update_received(
+                &ProjectData::default(),
+                &serde_json::to_string(&stale_update).unwrap()
+            ),
+            None
+        );
+        assert_eq!(
+            build_progress.update_received(&ProjectData::default(), "not json"),
+            None
+        );
+        assert_eq!(build_progress.units_compiled, 0);
+    }
+
+    #[test]
+    fn a_restarted_build_counts_from_zero() {
+        let mut build_progress = following();
+        build_progress.apply(
+            &ProjectData::default(),
+            &BuildUpdate {
+                build_id: 2,
+                units_compiled: 2,
+                diagnostics: vec![diagnostic("error")],
+                ..Default::default()
+            },
+        );
+        build_progress.apply(
+            &ProjectData::default(),
+            &BuildUpdate {
+                build_id: 2,
+                units_compiled: 1,
+                restarted: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(build_progress.units_compiled, 1);
+        assert_eq!(build_progress.error_count, 0);
+    }
+
+    #[test]
+    fn a_successful_build_sets_how_many_crates_the_next_one_compiles() {
+        let mut build_progress = following();
+        build_progress.apply(
+            &ProjectData::default(),
+            &BuildUpdate {
+                build_id: 2,
+                units_compiled: 4,
+                finished: Some(true),
+                ..Default::default()
+            },
+        );
+        assert_eq!(build_progress.expected_units, Some(4));
+        build_progress.elapsed = Some(Duration::from_millis(1500));
+        assert_eq!(
+            build_progress.summary().as_deref(),
+            Some("4 crates in 1.5s")
+        );
+    }
+
+    #[test]
+    fn an_unfollowed_build_has_no_summary() {
+        assert_eq!(BuildProgress::default().summary(), None);
+    }
+}
@@ -0,0 +1,337 @@
+use zellij_tile::prelude::*;
+
+use crate::artifact_check::{check_artifacts, ArtifactReport};
+use crate::cargo_messages::{parse_cargo_message, CargoMessage, Diagnostic};
+use crate::host::{Host, ZellijHost};
+use crate::host_fs::HostFs;
+use crate::reports::export_reports;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+// registered as build_worker, zellij drops the _worker suffix
+pub const WORKER_NAME: &str = "build";
+// the names of the messages the worker sends back
+pub const BUILD_UPDATE: &str = "build_update";
+pub const CHECK_UPDATE: &str = "check_update";
+pub const EXPORT_RESULT: &str = "export_result";
+pub const ARTIFACT_CHECK: &str = "artifact_check";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorkerRequest {
+    FollowBuild {
+        build_id: u32,
+        progress_file: PathBuf,
+        host_fs: HostFs,
+    },
+    PollBuild {
+        build_id: u32,
+    },
+    // cargo check's output arrives all at once, the worker sends back a single update for it
+    ParseCheck {
+        check_id: u32,
+        output: String,
+    },
+    ExportReports {
+        dev_directory: PathBuf,
+        workspace_root: PathBuf,
+        build_messages_kept: bool, // otherwise the build's messages are from an earlier build
+        host_fs: HostFs,
+    },
+    CheckArtifacts {
+        check_id: u32,
+        plugin_paths: Vec<PathBuf>,
+        host_fs: HostFs,
+    },
+}
+
+impl WorkerRequest {
+    pub fn post(&self, host: &mut impl Host) {
+        match serde_json::to_string(self) {
+            Ok(payload) => host.post_message_to_worker(WORKER_NAME, "request", payload),
+            Err(e) => eprintln!("Failed to serialize the worker request: {}", e),
+        }
+    }
+}
+
+// what cargo reported since the last update (or in a whole check)
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildUpdate {
+    pub build_id: u32,
+    pub restarted: bool, // the progress file was truncated, so everything we had is stale
+    pub units_compiled: usize,
+    pub last_unit: Option<String>,
+    pub diagnostics: Vec<Diagnostic>, // errors and warnings, without duplicates
+    pub finished: Option<bool>,       // whether the build succeeded, once cargo says it's done
+}
+
+// reads and parses cargo's json messages off the main thread, so that a build with thousands of
+// warnings doesn't stall the plugin
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildWorker<H: Host = ZellijHost> {
+    build_id: u32,
+    progress_file: Option<PathBuf>,
+    host_fs: HostFs,
+    read_offset: u64,
+    parser: MessageParser,
+    #[serde(skip)]
+    host: H,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MessageParser {
+    partial_line: Vec<u8>,
+    // cargo reports a warning once for every target it's in (eg. a lib and its tests)
+    seen_diagnostics: BTreeSet<String>,
+}
+
+impl<'de, H: Host + Default> ZellijWorker<'de> for BuildWorker<H> {
+    fn on_message(&mut self, _message: String, payload: String) {
+        match serde_json::from_str(&payload) {
+            Ok(WorkerRequest::FollowBuild {
+                build_id,
+                progress_file,
+                host_fs,
+            }) => {
+                *self = BuildWorker {
+                    build_id,
+                    progress_file: Some(progress_file),
+                    host_fs,
+                    host: std::mem::take(&mut self.host),
+                    ..Default::default()
+                };
+            }
+            Ok(WorkerRequest::PollBuild { build_id }) if build_id == self.build_id => {
+                if let Some(update) = self.poll() {
+                    send_update(&mut self.host, BUILD_UPDATE, &update);
+                }
+            }
+            Ok(WorkerRequest::PollBuild { .. }) => {}
+            Ok(WorkerRequest::ParseCheck { check_id, output }) => {
+                let mut update = parse_messages(&output);
+                update.build_id = check_id;
+                send_update(&mut self.host, CHECK_UPDATE, &update);
+            }
+            Ok(WorkerRequest::ExportReports {
+                dev_directory,
+                workspace_root,
+                build_messages_kept,
+                host_fs,
+            }) => {
+                let result = export_reports(
+                    &dev_directory,
+                    &workspace_root,
+                    build_messages_kept,
+                    &host_fs,
+                );
+                match serde_json::to_string(&result) {
+                    Ok(result) => self.host.post_message_to_plugin(EXPORT_RESULT, result),
+                    Err(e) => eprintln!("Failed to serialize the export result: {}", e),
+                }
+            }
+            Ok(WorkerRequest::CheckArtifacts {
+                check_id,
+                plugin_paths,
+                host_fs,
+            }) => {
+                let report = ArtifactReport {
+                    check_id,
+                    verdicts: check_artifacts(&plugin_paths, &host_fs),
+                };
+                match serde_json::to_string(&report) {
+                    Ok(report) => self.host.post_message_to_plugin(ARTIFACT_CHECK, report),
+                    Err(e) => eprintln!("Failed to serialize the artifact report: {}", e),
+                }
+            }
+            Err(e) => eprintln!("Failed to parse the worker request: {}", e),
+        }
+    }
+}
+
+impl<H: Host> BuildWorker<H> {
+    fn poll(&mut self) -> Option<BuildUpdate> {
+        let progress_file = self.progress_file.as_ref()?;
+        // until cargo starts, the file might not be there
+        let (offset, appended) = self
+            .host_fs
+            .read_appended(progress_file, self.read_offset)
+            .ok()?;
+        let restarted = offset != self.read_offset;
+        if restarted {
+            self.parser = MessageParser::default();
+        }
+        self.read_offset = offset + appended.len() as u64;
+        let mut update = self.parser.messages_received(&appended);
+        update.build_id = self.build_id;
+        update.restarted = restarted;
+        Some(update)
+    }
+}
+
+impl MessageParser {
+    fn messages_received(&mut self, appended: &[u8]) -> BuildUpdate {
+        let mut update = BuildUpdate::default();
+        self.partial_line.extend_from_slice(appended);
+        let complete_length = match self.partial_line.iter().rposition(|b| *b == b'\n') {
+            Some(last_newline) => last_newline + 1,
+            None => return update,
+        };
+        let complete_lines: Vec<u8> = self.partial_line.drain(..complete_length).collect();
+        for line in String::from_utf8_lossy(&complete_lines).lines() {
+            match parse_cargo_message(line) {
+                Some(CargoMessage::CompilerArtifact { target_name, .. }) => {
+                    update.units_compiled += 1;
+                    update.last_unit = Some(target_name);
+                }
+                Some(CargoMessage::CompilerMessage(diagnostic))
+                    if diagnostic.is_error() || diagnostic.is_warning() =>
+                {
+                    let key = diagnostic
+                        .rendered
+                        .clone()
+                        .unwrap_or_else(|| diagnostic.summary());
+                    if self.seen_diagnostics.insert(key) {
+                        update.diagnostics.push(diagnostic);
+                    }
+                }
+                Some(CargoMessage::BuildFinished { success }) => {
+                    update.finished = Some(success);
+                }
+                _ => {}
+            }
+        }
+        update
+    }
+}
+
+// all of cargo's messages at once, eg. a check's output
+pub fn parse_messages(output: &str) -> BuildUpdate {
+    MessageParser::default().messages_received(output.as_bytes())
+}
+
+fn send_update(host: &mut impl Host, name: &str, update: &BuildUpdate) {
+    match serde_json::to_string(update) {
+        Ok(update) => host.post_message_to_plugin(name, update),
+        Err(e) => eprintln!("Failed to serialize the build update: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostEffect, RecordingHost};
+
+    const WARNING: &str = r#"{"reason":"compiler-message","message":{"message":"unused variable","level":"warning","spans":[],"rendered":"warning: unused variable"}}"#;
+
+    fn artifact(name: &str) -> String {
+        format!(
+            "{{\"reason\":\"compiler-artifact\",\"target\":{{\"name\":\"{}\"}},\"fresh\":false}}\n",
+            name
+        )
+    }
+
+    #[test]
+    fn a_message_split_mid_line_is_parsed_once_complete() {
+        let mut parser = MessageParser::default();
+        let serde = artifact("serde");
+        let (first_half, second_half) = serde.split_at(20);
+        assert_eq!(
+            parser.messages_received(first_half.as_bytes()),
+            BuildUpdate::default()
+        );
+        let update = parser.messages_received(second_half.as_bytes());
+        assert_eq!(update.units_compiled, 1);
+        assert_eq!(update.last_unit, Some("serde".to_owned()));
+    }
+
+    #[test]
+    fn counts_the_compiled_units_and_names_the_last_one() {
+        let mut parser = MessageParser::default();
+        let update = parser.messages_received(
+            format!("{}{}", artifact("serde"), artifact("serde_json")).as_bytes(),
+        );
+        assert_eq!(update.units_compiled, 2);
+        assert_eq!(update.last_unit, Some("serde_json".to_owned()));
+    }
+
+    #[test]
+    fn a_diagnostic_reported_for_several_targets_is_kept_once() {
+        let mut parser = MessageParser::default();
+        let update = parser.messages_received(format!("{}\n{}\n", WARNING, WARNING).as_bytes());
+        assert_eq!(update.diagnostics.len(), 1);
+        let update = parser.messages_received(format!("{}\n", WARNING).as_bytes());
+        assert!(update.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn reports_whether_the_build_succeeded_once_it_finished() {
+        let mut parser = MessageParser::default();
+        assert_eq!(
+            parser
+                .messages_received(artifact("serde").as_bytes())
+                .finished,
+            None
+        );
+        let update =
+            parser.messages_received(b"{\"reason\":\"build-finished\",\"success\":true}\n");
+        assert_eq!(update.finished, Some(true));
+    }
+
+    #[test]
+    fn lines_that_arent_cargo_messages_are_skipped() {
+        let update = parse_messages(&format!(
+            "   Compiling serde\n{{\"reason\":\n{}{}\n",
+            artifact("serde"),
+            WARNING
+        ));
+        assert_eq!(update.units_compiled, 1);
+        assert_eq!(update.diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn a_check_is_parsed_and_sent_back_to_the_plugin() {
+        let mut worker = BuildWorker::<RecordingHost>::default();
+        let request = WorkerRequest::ParseCheck {
+            check_id: 3,
+            output: format!("{}\n", WARNING),
+        };
+        worker.on_message(
+            "request".to_owned(),
+            serde_json::to_string(&request).unwrap(),
+        );
+        let effects = worker.host.take_effects();
+        let update = match effects.as_slice() {
+            [HostEffect::PostMessageToPlugin { name, payload }] if name == CHECK_UPDATE => {
+                serde_json::from_str::<BuildUpdate>(payload).unwrap()
+            }
+            _ => panic!("unexpected effects: {:?}", effects),
+        };
+        assert_eq!(update.build_id, 3);
+        assert_eq!(update.diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn only_the_followed_build_is_polled() {
+        let mut worker = BuildWorker::<RecordingHost>::default();
+        let follow = WorkerRequest::FollowBuild {
+            build_id: 2,
+            progress_file: PathBuf::from("/projects/p/target/zellij-dev/build.json"),
+            host_fs: HostFs::default(),
+        };
+        worker.on_message(
+            "request".to_owned(),
+            serde_json::to_string(&follow).unwrap(),
+        );
+        let stale_poll = WorkerRequest::PollBuild { build_id: 1 };
+        worker.on_message(
+            "request".to_owned(),
+            serde_json::to_string(&stale_poll).unwrap(),
+        );
+        assert_eq!(worker.build_id, 2);
+        // the progress file can't be read (the host folder is unknown), so there's nothing to send
+        let poll = WorkerRequest::PollBuild { build_id: 2 };
+        worker.on_message("request".to_owned(), serde_json::to_string(&poll).unwrap());
+        assert_eq!(worker.host.take_effects(), vec![]);
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+// the parts of cargo's --message-format=json output we use, one message per line
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CargoMessage {
+    CompilerArtifact { target_name: String, fresh: bool },
+    CompilerMessage(Diagnostic),
+    BuildFinished { success: bool },
+}
+
+// a rustc diagnostic, as rustc serializes it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub message: String,
+    #[serde(default)]
+    pub code: Option<DiagnosticCode>,
+    pub level: String, // error, warning, note, help, failure-note or error: internal compiler error
+    #[serde(default)]
+    pub spans: Vec<DiagnosticSpan>,
+    #[serde(default)]
+    pub children: Vec<Diagnostic>,
+    #[serde(default)]
+    pub rendered: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticCode {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticSpan {
+    pub file_name: String, // relative to the workspace root
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize, // 1-based, in characters
+    pub column_end: usize,
+    pub is_primary: bool,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub text: Vec<DiagnosticSpanLine>, // the source lines the span covers, as rustc saw them
+    #[serde(default)]
+    pub suggested_replacement: Option<String>,
+    #[serde(default)]
+    pub suggestion_applicability: Option<String>, // eg. MachineApplicable or MaybeIncorrect
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticSpanLine {
+    pub text: String,
+    pub highlight_start: usize, // 1-based, in characters
+    pub highlight_end: usize,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.level.starts_with("error")
+    }
+    pub fn is_warning(&self) -> bool {
+        self.level == "warning"
+    }
+    pub fn primary_span(&self) -> Option<&DiagnosticSpan> {
+        self.spans.iter().find(|s| s.is_primary)
+    }
+    // eg. "src/main.rs:12:5: cannot find value `x` in this scope"
+    pub fn summary(&self) -> String {
+        match self.primary_span() {
+            Some(span) => format!(
+                "{}:{}:{}: {}",
+                span.file_name, span.line_start, span.column_start, self.message
+            ),
+            None => self.message.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawMessage {
+    reason: String,
+    #[serde(default)]
+    target: Option<RawTarget>,
+    #[serde(default)]
+    fresh: bool,
+    #[serde(default)]
+    message: Option<Diagnostic>,
+    #[serde(default)]
+    success: bool,
+}
+
+#[derive(Deserialize)]
+struct RawTarget {
+    name: String,
+}
+
+// returns None for the messages we don't use and for lines that aren't cargo messages at all
+// (eg. a build script's output)
+pub fn parse_cargo_message(line: &str) -> Option<CargoMessage> {
+    let raw: RawMessage = serde_json::from_str(line).ok()?;
+    match raw.reason.as_str() {
+        "compiler-artifact" => Some(CargoMessage::CompilerArtifact {
+            target_name: raw.target?.name,
+            fresh: raw.fresh,
+        }),
+        "compiler-message" => raw.message.map(CargoMessage::CompilerMessage),
+        "build-finished" => Some(CargoMessage::BuildFinished {
+            success: raw.success,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_compiler_artifact() {
+        let artifact = r#"{"reason":"compiler-artifact","package_id":"serde 1.0.0","target":{"kind":["lib"],"name":"serde","src_path":"/src/lib.rs"},"filenames":[],"fresh":true}"#;
+        assert_eq!(
+            parse_cargo_message(artifact),
+            Some(CargoMessage::CompilerArtifact {
+                target_name: "serde".to_owned(),
+                fresh: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_a_compiler_message_with_its_code_and_location() {
+        let error = r#"{"reason":"compiler-message","package_id":"my-plugin 0.1.0","target":{"name":"my-plugin"},"message":{"message":"cannot find value `x` in this scope","code":{"code":"E0425","explanation":"..."},"level":"error","spans":[{"file_name":"src/main.rs","byte_start":10,"byte_end":11,"line_start":12,"line_end":12,"column_start":5,"column_end":6,"is_primary":true,"text":[],"label":"not found in this scope","suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[],"rendered":"error[E0425]: ..."}}"#;
+        match parse_cargo_message(error) {
+            Some(CargoMessage::CompilerMessage(diagnostic)) => {
+                assert!(diagnostic.is_error());
+                assert_eq!(
+                    diagnostic.code.as_ref().map(|c| c.code.as_str()),
+                    Some("E0425")
+                );
+                assert_eq!(
+                    diagnostic.summary(),
+                    "src/main.rs:12:5: cannot find value `x` in this scope"
+                );
+            }
+            message => panic!("unexpected message: {:?}", message),
+        }
+    }
+
+    #[test]
+    fn parses_the_end_of_the_build() {
+        assert_eq!(
+            parse_cargo_message(r#"{"reason":"build-finished","success":false}"#),
+            Some(CargoMessage::BuildFinished { success: false })
+        );
+    }
+
+    #[test]
+    fn skips_lines_that_arent_json_and_messages_we_dont_follow() {
+        assert_eq!(parse_cargo_message("   Compiling my-plugin"), None);
+        assert_eq!(
+            parse_cargo_message(r#"{"reason":"compiler-artifact","#),
+            None
+        );
+        assert_eq!(parse_cargo_message(""), None);
+        assert_eq!(
+            parse_cargo_message(r#"{"reason":"build-script-executed"}"#),
+            None
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use zellij_tile::prelude::*;
+
+use crate::host::Host;
+use crate::project_data::ProjectData;
+use crate::ui::{help_text, truncate};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// the project as cargo sees it, so that we can build members of a workspace with -p and find
+// their artifacts in the workspace's target directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CargoProject {
+    pub workspace_root: PathBuf,
+    pub target_directory: PathBuf,
+    pub is_workspace: bool,
+    pub plugins: Vec<PluginCrate>, // the members that depend on zellij-tile, sorted by name
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginCrate {
+    pub name: String,
+    pub artifact_name: String, // the wasm file's name, without the extension
+    pub manifest_dir: PathBuf,
+}
+
+impl CargoProject {
+    // the plugin in the chosen folder, or the only plugin in the workspace
+    pub fn default_selection(&self, cwd: &Path) -> Vec<String> {
+        if let Some(plugin) = self.plugins.iter().find(|p| p.manifest_dir == cwd) {
+            vec![plugin.name.clone()]
+        } else if self.plugins.len() == 1 {
+            vec![self.plugins[0].name.clone()]
+        } else {
+            vec![]
+        }
+    }
+    // keeps only the names of plugins in this project, in the project's order
+    pub fn normalize_selection(&self, selection: &[String]) -> Vec<String> {
+        self.plugins
+            .iter()
+            .filter(|p| selection.contains(&p.name))
+            .map(|p| p.name.clone())
+            .collect()
+    }
+}
+
+// the parts of `cargo metadata --format-version 1` we use
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<Target>,
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Dependency {
+    name: String,
+}
+
+pub fn parse_cargo_metadata(metadata: &str) -> Result<CargoProject, String> {
+    let metadata: Metadata = serde_json::from_str(metadata)
+        .map_err(|e| format!("Failed to parse the cargo metadata: {}", e))?;
+    let manifest_dir = |package: &Package| {
+        package
+            .manifest_path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default()
+    };
+    let is_workspace = metadata.packages.len() > 1
+        || metadata
+            .packages
+            .iter()
+            .any(|p| manifest_dir(p) != metadata.workspace_root);
+    let mut plugins: Vec<PluginCrate> = metadata
+        .packages
+        .iter()
+        .filter(|p| p.dependencies.iter().any(|d| d.name == "zellij-tile"))
+        .filter_map(|package| {
+            // plugins are usually binaries, but can also be built as a cdylib, whose file name
+            // has underscores instead of dashes
+            let artifact_name = package
+                .targets
+                .iter()
+                .find(|t| t.kind.iter().any(|k| k == "bin"))
+                .map(|t| t.name.clone())
+                .or_else(|| {
+                    package
+                        .targets
+                        .iter()
+                        .find(|t| t.kind.iter().any(|k| k == "cdylib"))
+                        .map(|t| t.name.replace('-', "_"))
+                })?;
+            Some(PluginCrate {
+                name: package.name.clone(),
+                artifact_name,
+                manifest_dir: manifest_dir(package),
+            })
+        })
+        .collect();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(CargoProject {
+        workspace_root: metadata.workspace_root,
+        target_directory: metadata.target_directory,
+        is_workspace,
+        plugins,
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct MemberPicker {
+    selected_index: usize,
+}
+
+impl MemberPicker {
+    pub fn request_metadata(&mut self, host: &mut impl Host, cwd: &Path) {
+        let mut context = BTreeMap::new();
+        context.insert("source".to_owned(), "cargo_metadata".to_owned());
+        context.insert("cwd".to_owned(), cwd.display().to_string());
+        host.run_command(
+            ["cargo", "metadata", "--format-version", "1", "--no-deps"]
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            BTreeMap::new(),
+            cwd.to_path_buf(),
+            context,
+        );
+        self.selected_index = 0;
+    }
+    // returns the project and the members to build, unless the folder changed in the meantime
+    pub fn handle_metadata_result(
+        &mut self,
+        project_data: &ProjectData,
+        exit_code: Option<i32>,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        context: &BTreeMap<String, String>,
+        cwd: Option<PathBuf>,
+    ) -> Option<(CargoProject, Vec<String>)> {
+        let cwd = cwd?;
+        if context.get("cwd") != Some(&cwd.display().to_string()) {
+            return None;
+        }
+        if exit_code != Some(0) {
+            eprintln!(
+                "Failed to read the cargo metadata: {}",
+                String::from_utf8_lossy(&stderr)
+            );
+            return None;
+        }
+        match parse_cargo_metadata(&String::from_utf8_lossy(&stdout)) {
+            Ok(project) => {
+                let saved_selection = project_data
+                    .read(&cwd, "workspace_members", "json")
+                    .and_then(|s| serde_json::from_str::<Vec<String>>(&s).ok())
+                    .map(|s| project.normalize_selection(&s))
+                    .filter(|s| !s.is_empty());
+                let selection = saved_selection.unwrap_or_else(|| project.default_selection(&cwd));
+                Some((project, selection))
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
+        }
+    }
+    // returns the new selection if it changed
+    pub fn handle_key(
+        &mut self,
+        project_data: &ProjectData,
+        key: KeyWithModifier,
+        project: Option<&CargoProject>,
+        selection: &[String],
+        cwd: Option<PathBuf>,
+    ) -> (bool, Option<Vec<String>>) {
+        let plugins = match project {
+            Some(project) if project.is_workspace => &project.plugins,
+            _ => return (false, None),
+        };
+        let mut should_render = true;
+        let mut new_selection = None;
+        match key.bare_key {
+            BareKey::Down if key.has_no_modifiers() => {
+                if self.selected_index + 1 < plugins.len() {
+                    self.selected_index += 1;
+                }
+            }
+            BareKey::Up if key.has_no_modifiers() => {
+                self.selected_index = self.selected_index.saturating_sub(1);
+            }
+            BareKey::Char(' ') if key.has_no_modifiers() => {
+                if let Some(plugin) = plugins.get(self.selected_index) {
+                    let mut selection = selection.to_vec();
+                    if let Some(position) = selection.iter().position(|s| s == &plugin.name) {
+                        selection.remove(position);
+                    } else {
+                        selection.push(plugin.name.clone());
+                    }
+                    new_selection = project.map(|p| p.normalize_selection(&selection));
+                }
+            }
+            BareKey::Enter if key.has_no_modifiers() => {
+                // builds only the highlighted member
+                new_selection = plugins
+                    .get(self.selected_index)
+                    .map(|p| vec![p.name.clone()]);
+            }
+            _ => {
+                should_render = false;
+            }
+        }
+        if let (Some(new_selection), Some(cwd)) = (&new_selection, cwd) {
+            if let Ok(contents) = serde_json::to_string(new_selection) {
+                project_data.write(&cwd, "workspace_members", "json", &contents);
+            }
+        }
+        (should_render, new_selection)
+    }
+    pub fn render(
+        &self,
+        y: usize,
+        rows: usize,
+        cols: usize,
+        project: Option<&CargoProject>,
+        selection: &[String],
+    ) {
+        let title = match project {
+            Some(project) if project.is_workspace => {
+                format!("Workspace Members - {}", project.workspace_root.display())
+            }
+            Some(_) => "Workspace Members - the folder is a single crate".to_owned(),
+            None => "Workspace Members - <NO CARGO PROJECT FOUND>".to_owned(),
+        };
+        print_text_with_coordinates(
+            Text::new(truncate(&title, cols)).color_range(2, ..17),
+            0,
+            y,
+            None,
+            None,
+        );
+        let plugins = match project {
+            Some(project) if project.is_workspace => &project.plugins,
+            _ => return,
+        };
+        if plugins.is_empty() {
+            print_text_with_coordinates(
+                Text::new(truncate("<NO MEMBERS DEPEND ON ZELLIJ-TILE>", cols)),
+                0,
+                y + 2,
+                None,
+                None,
+            );
+        }
+        let available_rows = rows.saturating_sub(y + 3);
+        let first_visible = self
+            .selected_index
+            .saturating_sub(available_rows.saturating_sub(1));
+        for (i, plugin) in plugins
+            .iter()
+            .enumerate()
+            .skip(first_visible)
+            .take(available_rows)
+        {
+            let checkbox = if selection.contains(&plugin.name) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            let line = format!(
+                "{} {} ({}.wasm)",
+                checkbox, plugin.name, plugin.artifact_name
+            );
+            let mut line =
+                Text::new(truncate(&line, cols)).color_range(0, 4..4 + plugin.name.chars().count());
+            if i == self.selected_index {
+                line = line.selected();
+            }
+            print_text_with_coordinates(line, 0, y + 2 + i - first_visible, None, None);
+        }
+        let help = "<↓↑> move, <Space> build it too, <Enter> build only it, <Esc> back";
+        print_text_with_coordinates(help_text(help, cols), 0, rows.saturating_sub(1), None, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKSPACE_METADATA: &str = r#"{
+        "packages": [
+            {
+                "name": "status-bar",
+                "manifest_path": "/projects/plugins/status-bar/Cargo.toml",
+                "targets": [{"name": "status-bar", "kind": ["bin"], "crate_types": ["bin"]}],
+                "dependencies": [{"name": "zellij-tile", "req": "^0.41.1"}]
+            },
+            {
+                "name": "shared",
+                "manifest_path": "/projects/plugins/shared/Cargo.toml",
+                "targets": [{"name": "shared", "kind": ["lib"], "crate_types": ["lib"]}],
+                "dependencies": [{"name": "serde", "req": "^1.0"}]
+            },
+            {
+                "name": "file-picker",
+                "manifest_path": "/projects/plugins/file-picker/Cargo.toml",
+                "targets": [{"name": "file-picker", "kind": ["cdylib"], "crate_types": ["cdylib"]}],
+                "dependencies": [{"name": "zellij-tile", "req": "^0.41.1"}]
+            }
+        ],
+        "workspace_members": [],
+        "workspace_root": "/projects/plugins",
+        "target_directory": "/projects/plugins/target",
+        "version": 1
+    }"#;
+
+    #[test]
+    fn lists_the_members_that_are_plugins() {
+        let project = parse_cargo_metadata(WORKSPACE_METADATA).unwrap();
+        assert!(project.is_workspace);
+        assert_eq!(
+            project.target_directory,
+            PathBuf::from("/projects/plugins/target")
+        );
+        assert_eq!(
+            project.plugins,
+            vec![
+                PluginCrate {
+                    name: "file-picker".to_owned(),
+                    artifact_name: "file_picker".to_owned(),
+                    manifest_dir: PathBuf::from("/projects/plugins/file-picker"),
+                },
+                PluginCrate {
+                    name: "status-bar".to_owned(),
+                    artifact_name: "status-bar".to_owned(),
+                    manifest_dir: PathBuf::from("/projects/plugins/status-bar"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_single_crate_is_not_a_workspace() {
+        let metadata = r#"{
+            "packages": [{
+                "name": "my-plugin",
+                "manifest_path": "/projects/my-plugin/Cargo.toml",
+                "targets": [{"name": "my-plugin", "kind": ["bin"]}],
+                "dependencies": [{"name": "zellij-tile"}]
+            }],
+            "workspace_root": "/projects/my-plugin",
+            "target_directory": "/projects/my-plugin/target"
+        }"#;
+        let project = parse_cargo_metadata(metadata).unwrap();
+        assert!(!project.is_workspace);
+        assert_eq!(
+            project.default_selection(Path::new("/projects/my-plugin")),
+            vec!["my-plugin".to_owned()]
+        );
+    }
+
+    #[test]
+    fn selects_the_member_in_the_chosen_folder() {
+        let project = parse_cargo_metadata(WORKSPACE_METADATA).unwrap();
+        assert_eq!(
+            project.default_selection(Path::new("/projects/plugins/status-bar")),
+            vec!["status-bar".to_owned()]
+        );
+        assert!(project
+            .default_selection(Path::new("/projects/plugins"))
+            .is_empty());
+        assert_eq!(
+            project.normalize_selection(&[
+                "status-bar".to_owned(),
+                "shared".to_owned(),
+                "file-picker".to_owned()
+            ]),
+            vec!["file-picker".to_owned(), "status-bar".to_owned()]
+        );
+    }
+
+    #[test]
+    fn invalid_metadata_is_reported() {
+        let error = parse_cargo_metadata("error: could not find `Cargo.toml`").unwrap_err();
+        assert!(error.starts_with("Failed to parse the cargo metadata"));
+    }
+}
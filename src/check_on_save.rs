@@ -0,0 +1,231 @@
+use crate::build_progress::plural;
+use crate::build_worker::{BuildUpdate, WorkerRequest};
+use crate::deadline::Deadline;
+use crate::host::Host;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// editors often write a file several times when saving it
+const DEBOUNCE_SECS: f64 = 0.3;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    #[default]
+    NotRun,
+    Checking,
+    Clean {
+        warnings: usize,
+    },
+    Failed {
+        errors: usize,
+    },
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckStatus::NotRun => write!(f, "waiting for changes"),
+            CheckStatus::Checking => write!(f, "checking..."),
+            CheckStatus::Clean { warnings: 0 } => write!(f, "clean"),
+            CheckStatus::Clean { warnings } => write!(f, "clean, {}", plural(*warnings, "warning")),
+            CheckStatus::Failed { errors: 0 } => write!(f, "failed"),
+            CheckStatus::Failed { errors } => write!(f, "{}", plural(*errors, "error")),
+        }
+    }
+}
+
+// runs cargo check whenever a source file changes, and (if auto_reload is on) builds and reloads
+// the plugin once it compiles
+#[derive(Debug, Default)]
+pub struct CheckOnSave {
+    enabled: bool,
+    auto_reload: bool,
+    watching_filesystem: bool,
+    check_id: u32,
+    running: bool,
+    changed_while_running: bool,
+    status: CheckStatus,
+    next_check: Deadline,
+}
+
+impl CheckOnSave {
+    pub fn update_config(
+        &mut self,
+        host: &mut impl Host,
+        configuration: &BTreeMap<String, String>,
+    ) {
+        if configuration.get("check_on_save").map(|c| c.as_str()) == Some("true") {
+            self.toggle(host);
+        }
+        self.auto_reload = configuration.get("auto_reload").map(|a| a.as_str()) == Some("true");
+    }
+    pub fn toggle(&mut self, host: &mut impl Host) {
+        self.enabled = !self.enabled;
+        if self.enabled && !self.watching_filesystem {
+            host.watch_filesystem();
+            self.watching_filesystem = true;
+        }
+        if !self.enabled {
+            self.next_check.cancel();
+            self.changed_while_running = false;
+        }
+    }
+    pub fn toggle_auto_reload(&mut self) {
+        self.auto_reload = !self.auto_reload;
+    }
+    // eg. "on save, auto reload - clean"
+    pub fn summary(&self) -> String {
+        match (self.enabled, self.auto_reload) {
+            (false, _) => "off (<c> to check on save)".to_owned(),
+            (true, false) => format!("on save - {}", self.status),
+            (true, true) => format!("on save, reloading when clean - {}", self.status),
+        }
+    }
+    // paths are relative to the folder zellij was started in
+    pub fn files_changed(
+        &mut self,
+        host: &mut impl Host,
+        paths: &[PathBuf],
+        host_root: Option<&Path>,
+        project_folder: Option<&Path>,
+        target_directory: Option<&Path>,
+    ) {
+        let (host_root, project_folder) = match (self.enabled, host_root, project_folder) {
+            (true, Some(host_root), Some(project_folder)) => (host_root, project_folder),
+            _ => return,
+        };
+        if paths
+            .iter()
+            .any(|path| is_source_change(&host_root.join(path), project_folder, target_directory))
+        {
+            self.next_check.schedule(host, DEBOUNCE_SECS);
+        }
+    }
+    // returns true if it's time to check
+    pub fn timer_elapsed(&mut self) -> bool {
+        if !self.next_check.elapsed() || !self.enabled {
+            return false;
+        }
+        if self.running {
+            self.changed_while_running = true;
+            return false;
+        }
+        true
+    }
+    pub fn run(&mut self, host: &mut impl Host, cwd: Option<PathBuf>, cargo_args: Vec<String>) {
+        let cwd = match cwd {
+            Some(cwd) => cwd,
+            None => return,
+        };
+        self.check_id = self.check_id.wrapping_add(1);
+        self.running = true;
+        self.status = CheckStatus::Checking;
+        let mut command = vec!["cargo".to_owned()];
+        command.extend(cargo_args);
+        command.push("--message-format=json".to_owned());
+        let mut context = BTreeMap::new();
+        context.insert("source".to_owned(), "cargo_check".to_owned());
+        context.insert("check_id".to_owned(), self.check_id.to_string());
+        host.run_command(command, BTreeMap::new(), cwd, context);
+    }
+    // hands cargo's output to the build worker, returns true if the files changed again while
+    // checking so we should check once more
+    pub fn check_finished(
+        &mut self,
+        host: &mut impl Host,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        context: &BTreeMap<String, String>,
+    ) -> bool {
+        if context.get("check_id") != Some(&self.check_id.to_string()) {
+            return false;
+        }
+        self.running = false;
+        let output = String::from_utf8_lossy(&stdout).to_string();
+        if output.trim().is_empty() {
+            // eg. a broken Cargo.toml, which cargo reports on stderr only
+            eprintln!("cargo check: {}", String::from_utf8_lossy(&stderr));
+        }
+        WorkerRequest::ParseCheck {
+            check_id: self.check_id,
+            output,
+        }
+        .post(host);
+        std::mem::take(&mut self.changed_while_running) && self.enabled
+    }
+    // returns the update if it's about the last check, and whether to build and reload now
+    pub fn update_received(&mut self, payload: &str) -> Option<(BuildUpdate, bool)> {
+        let update: BuildUpdate = match serde_json::from_str(payload) {
+            Ok(update) => update,
+            Err(e) => {
+                eprintln!("Failed to parse the check results: {}", e);
+                return None;
+            }
+        };
+        if update.build_id != self.check_id {
+            return None;
+        }
+        let errors = update.diagnostics.iter().filter(|d| d.is_error()).count();
+        self.status = if update.finished == Some(true) {
+            CheckStatus::Clean {
+                warnings: update.diagnostics.len() - errors,
+            }
+        } else {
+            CheckStatus::Failed { errors }
+        };
+        let should_reload =
+            self.auto_reload && !self.running && matches!(self.status, CheckStatus::Clean { .. });
+        Some((update, should_reload))
+    }
+}
+
+fn is_source_change(path: &Path, project_folder: &Path, target_directory: Option<&Path>) -> bool {
+    if !path.starts_with(project_folder)
+        || target_directory.map(|t| path.starts_with(t)) == Some(true)
+    {
+        return false;
+    }
+    path.extension().map(|e| e == "rs") == Some(true)
+        || path.file_name().map(|f| f == "Cargo.toml") == Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_sources_of_the_project_trigger_a_check() {
+        let project_folder = Path::new("/projects/my-plugin");
+        let target_directory = Some(Path::new("/projects/my-plugin/target"));
+        let changed =
+            |path: &str| is_source_change(Path::new(path), project_folder, target_directory);
+        assert!(changed("/projects/my-plugin/src/main.rs"));
+        assert!(changed("/projects/my-plugin/Cargo.toml"));
+        assert!(!changed("/projects/my-plugin/README.md"));
+        assert!(!changed("/projects/my-plugin/target/debug/build/out.rs"));
+        assert!(!changed("/projects/other-plugin/src/main.rs"));
+    }
+
+    #[test]
+    fn clean_checks_reload_only_with_auto_reload() {
+        let mut check_on_save = CheckOnSave {
+            check_id: 3,
+            ..Default::default()
+        };
+        let clean = r#"{"build_id":3,"restarted":false,"units_compiled":1,"last_unit":null,"diagnostics":[],"finished":true}"#;
+        let (_, should_reload) = check_on_save.update_received(clean).unwrap();
+        assert!(!should_reload);
+        assert_eq!(check_on_save.status, CheckStatus::Clean { warnings: 0 });
+        check_on_save.toggle_auto_reload();
+        let (_, should_reload) = check_on_save.update_received(clean).unwrap();
+        assert!(should_reload);
+        let failed = r#"{"build_id":3,"restarted":false,"units_compiled":0,"last_unit":null,"diagnostics":[{"message":"mismatched types","level":"error"}],"finished":false}"#;
+        let (_, should_reload) = check_on_save.update_received(failed).unwrap();
+        assert!(!should_reload);
+        assert_eq!(check_on_save.status.to_string(), "1 error");
+        let stale = clean.replace("\"build_id\":3", "\"build_id\":2");
+        assert!(check_on_save.update_received(&stale).is_none());
+    }
+}
@@ -0,0 +1,341 @@
+use zellij_tile::prelude::*;
+
+use crate::host::Host;
+use crate::ui::{help_text, truncate};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// every action the dev plugin has, so that the palette and the dashboard's keys agree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DevCommand {
+    BuildAndReload,
+    Reload,
+    RunTests,
+    RunClippy,
+    BuildRelease,
+    ToggleQuietBuilds,
+    ShowBuildOutput,
+    ToggleCheckOnSave,
+    ToggleAutoReload,
+    OpenDiagnostics,
+    AcceptWarnings,
+    ExportReports,
+    ChangeFolder,
+    ChooseMembers,
+    OpenPipeWorkbench,
+    OpenLogs,
+    OpenReloadTiming,
+    OpenSnapshots,
+    OpenDoctor,
+    ToggleSizeMatrix,
+    JumpToPanic,
+    EditConfig,
+}
+
+pub const COMMANDS: [DevCommand; 22] = [
+    DevCommand::BuildAndReload,
+    DevCommand::Reload,
+    DevCommand::RunTests,
+    DevCommand::RunClippy,
+    DevCommand::BuildRelease,
+    DevCommand::ToggleQuietBuilds,
+    DevCommand::ShowBuildOutput,
+    DevCommand::ToggleCheckOnSave,
+    DevCommand::ToggleAutoReload,
+    DevCommand::OpenDiagnostics,
+    DevCommand::AcceptWarnings,
+    DevCommand::ExportReports,
+    DevCommand::ChangeFolder,
+    DevCommand::ChooseMembers,
+    DevCommand::OpenPipeWorkbench,
+    DevCommand::OpenLogs,
+    DevCommand::OpenReloadTiming,
+    DevCommand::OpenSnapshots,
+    DevCommand::OpenDoctor,
+    DevCommand::ToggleSizeMatrix,
+    DevCommand::JumpToPanic,
+    DevCommand::EditConfig,
+];
+
+impl DevCommand {
+    pub fn description(&self) -> &'static str {
+        match self {
+            DevCommand::BuildAndReload => "Build and reload the plugin",
+            DevCommand::Reload => "Reload the plugin without building",
+            DevCommand::RunTests => "Run the tests (cargo test)",
+            DevCommand::RunClippy => "Lint the code (cargo clippy)",
+            DevCommand::BuildRelease => "Package a release build (cargo build --release)",
+            DevCommand::ToggleQuietBuilds => "Toggle quiet builds (in the background)",
+            DevCommand::ShowBuildOutput => "Show the last quiet build's output",
+            DevCommand::ToggleCheckOnSave => "Toggle checking on save (cargo check)",
+            DevCommand::ToggleAutoReload => "Toggle reloading when a check on save is clean",
+            DevCommand::OpenDiagnostics => "Browse the errors and warnings",
+            DevCommand::AcceptWarnings => "Accept the last build's warnings as the baseline",
+            DevCommand::ExportReports => "Export the results as SARIF and JUnit reports",
+            DevCommand::ChangeFolder => "Switch to another project folder",
+            DevCommand::ChooseMembers => "Choose the workspace members to build",
+            DevCommand::OpenPipeWorkbench => "Send test pipe messages",
+            DevCommand::OpenLogs => "Open the plugin's log lines",
+            DevCommand::OpenReloadTiming => "Show build and reload timing",
+            DevCommand::OpenSnapshots => "Record and compare snapshots",
+            DevCommand::OpenDoctor => "Check the toolchain (doctor)",
+            DevCommand::ToggleSizeMatrix => "Start or stop cycling through preset pane sizes",
+            DevCommand::JumpToPanic => "Jump to the source of the last panic",
+            DevCommand::EditConfig => "Edit the zellij configuration",
+        }
+    }
+    // the key that runs the command from the dashboard, building and reloading is bound
+    // globally to the reload shortcut instead
+    pub fn key(&self) -> Option<KeyWithModifier> {
+        let key = match self {
+            DevCommand::ChangeFolder => {
+                return Some(KeyWithModifier::new(BareKey::Char('f')).with_ctrl_modifier())
+            }
+            DevCommand::ChooseMembers => 'w',
+            DevCommand::ShowBuildOutput => 'o',
+            DevCommand::ToggleCheckOnSave => 'c',
+            DevCommand::OpenDiagnostics => 'd',
+            DevCommand::ExportReports => 'e',
+            DevCommand::OpenPipeWorkbench => 'p',
+            DevCommand::OpenLogs => 'l',
+            DevCommand::OpenReloadTiming => 't',
+            DevCommand::OpenSnapshots => 's',
+            DevCommand::OpenDoctor => 'h',
+            DevCommand::ToggleSizeMatrix => 'm',
+            DevCommand::JumpToPanic => 'j',
+            _ => return None,
+        };
+        Some(KeyWithModifier::new(BareKey::Char(key)))
+    }
+    pub fn for_key(key: &KeyWithModifier) -> Option<DevCommand> {
+        COMMANDS
+            .iter()
+            .find(|command| command.key().as_ref() == Some(key))
+            .copied()
+    }
+    fn binding(&self, reload_shortcut: &KeyWithModifier) -> Option<String> {
+        match self {
+            DevCommand::BuildAndReload => Some(format!("<{}>", reload_shortcut)),
+            _ => self.key().map(|key| format!("<{}>", key)),
+        }
+    }
+}
+
+// runs a one-off command (eg. the tests) in a floating pane of its own
+pub fn open_command_pane(
+    host: &mut impl Host,
+    cwd: Option<PathBuf>,
+    command: &str,
+    args: Vec<String>,
+) {
+    let mut command = CommandToRun::new_with_args(command, args);
+    command.cwd = cwd;
+    let mut context = BTreeMap::new();
+    context.insert("source".to_owned(), "command_palette".to_owned());
+    host.open_command_pane_floating(command, context);
+}
+
+// matches the query's characters in order, ignoring case, and returns a score (higher is better)
+// and the indices of the matched characters
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(usize, Vec<usize>)> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    let text: Vec<char> = text.chars().collect();
+    let first_char = match query.first() {
+        Some(first_char) => first_char,
+        None => return Some((0, vec![])),
+    };
+    // the characters are matched greedily, so we try every place the match can start from
+    (0..text.len())
+        .filter(|start| chars_match(text[*start], *first_char))
+        .filter_map(|start| fuzzy_match_from(&query, &text, start))
+        .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)))
+}
+
+fn fuzzy_match_from(query: &[char], text: &[char], start: usize) -> Option<(usize, Vec<usize>)> {
+    let mut score = 0;
+    let mut matched_indices: Vec<usize> = vec![];
+    let mut next_index = start;
+    for query_char in query {
+        let i = (next_index..text.len()).find(|i| chars_match(text[*i], *query_char))?;
+        score += 1;
+        if matched_indices.last().map(|l| l + 1) == Some(i) {
+            score += 3;
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 2;
+        }
+        matched_indices.push(i);
+        next_index = i + 1;
+    }
+    Some((score, matched_indices))
+}
+
+fn chars_match(text_char: char, query_char: char) -> bool {
+    text_char.to_lowercase().eq(query_char.to_lowercase())
+}
+
+#[derive(Debug, Default)]
+pub struct CommandPalette {
+    query: String,
+    selected_index: usize,
+}
+
+impl CommandPalette {
+    pub fn open(&mut self) {
+        self.query.clear();
+        self.selected_index = 0;
+    }
+    // the commands matching the query, best match first
+    fn matching_commands(&self) -> Vec<(DevCommand, Vec<usize>)> {
+        let mut matching_commands: Vec<(usize, DevCommand, Vec<usize>)> = COMMANDS
+            .iter()
+            .filter_map(|command| {
+                fuzzy_match(&self.query, command.description())
+                    .map(|(score, matched_indices)| (score, *command, matched_indices))
+            })
+            .collect();
+        // the sort is stable, so equally good matches stay in the commands' order
+        matching_commands.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+        matching_commands
+            .into_iter()
+            .map(|(_, command, matched_indices)| (command, matched_indices))
+            .collect()
+    }
+    // returns whether to render and the command to run, if one was chosen
+    pub fn handle_key(&mut self, key: KeyWithModifier) -> (bool, Option<DevCommand>) {
+        let mut should_render = true;
+        let mut chosen_command = None;
+        match key.bare_key {
+            BareKey::Down if key.has_no_modifiers() => {
+                if self.selected_index + 1 < self.matching_commands().len() {
+                    self.selected_index += 1;
+                }
+            }
+            BareKey::Up if key.has_no_modifiers() => {
+                self.selected_index = self.selected_index.saturating_sub(1);
+            }
+            BareKey::Enter if key.has_no_modifiers() => {
+                chosen_command = self
+                    .matching_commands()
+                    .get(self.selected_index)
+                    .map(|(command, _)| *command);
+            }
+            BareKey::Backspace if key.has_no_modifiers() => {
+                self.query.pop();
+                self.selected_index = 0;
+            }
+            BareKey::Char(character) if key.has_no_modifiers() => {
+                self.query.push(character);
+                self.selected_index = 0;
+            }
+            _ => {
+                should_render = false;
+            }
+        }
+        (should_render, chosen_command)
+    }
+    pub fn render(&self, y: usize, rows: usize, cols: usize, reload_shortcut: &KeyWithModifier) {
+        print_text_with_coordinates(
+            Text::new(truncate("Command Palette", cols)).color_range(2, ..),
+            0,
+            y,
+            None,
+            None,
+        );
+        let prompt = truncate(&format!(": {}_", self.query), cols);
+        print_text_with_coordinates(Text::new(prompt).color_range(3, ..1), 0, y + 1, None, None);
+        let matching_commands = self.matching_commands();
+        if matching_commands.is_empty() {
+            print_text_with_coordinates(
+                Text::new(truncate("<NO MATCHING COMMANDS>", cols)),
+                0,
+                y + 3,
+                None,
+                None,
+            );
+        }
+        let available_rows = rows.saturating_sub(y + 4);
+        let first_visible = self
+            .selected_index
+            .saturating_sub(available_rows.saturating_sub(1));
+        let description_width = COMMANDS
+            .iter()
+            .map(|c| c.description().chars().count())
+            .max()
+            .unwrap_or(0);
+        for (i, (command, matched_indices)) in matching_commands
+            .iter()
+            .enumerate()
+            .skip(first_visible)
+            .take(available_rows)
+        {
+            let binding = command.binding(reload_shortcut).unwrap_or_default();
+            let line = format!(
+                "{:width$}  {}",
+                command.description(),
+                binding,
+                width = description_width
+            );
+            let line = truncate(&line, cols);
+            let line_length = line.chars().count();
+            let matched_indices = matched_indices
+                .iter()
+                .copied()
+                .filter(|i| *i < line_length)
+                .collect();
+            let mut line = Text::new(&line)
+                .color_range(3, std::cmp::min(description_width + 2, line_length)..)
+                .color_indices(0, matched_indices);
+            if i == self.selected_index {
+                line = line.selected();
+            }
+            print_text_with_coordinates(line, 0, y + 3 + i - first_visible, None, None);
+        }
+        let help = "<↓↑> move, <Enter> run, <Esc> back";
+        print_text_with_coordinates(help_text(help, cols), 0, rows.saturating_sub(1), None, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches_characters_in_order() {
+        assert!(fuzzy_match("bld", "Build and reload the plugin").is_some());
+        assert!(fuzzy_match("dlb", "Build and reload the plugin").is_none());
+        assert_eq!(
+            fuzzy_match("LOG", "Open the plugin's log lines").map(|(_, indices)| indices),
+            Some(vec![18, 19, 20])
+        );
+        assert_eq!(fuzzy_match("", "anything"), Some((0, vec![])));
+    }
+
+    #[test]
+    fn prefers_consecutive_and_word_start_matches() {
+        let mut palette = CommandPalette::default();
+        for character in "test".chars() {
+            palette.handle_key(KeyWithModifier::new(BareKey::Char(character)));
+        }
+        assert_eq!(palette.matching_commands()[0].0, DevCommand::RunTests);
+        let (_, chosen_command) = palette.handle_key(KeyWithModifier::new(BareKey::Enter));
+        assert_eq!(chosen_command, Some(DevCommand::RunTests));
+    }
+
+    #[test]
+    fn dashboard_keys_map_to_commands() {
+        assert_eq!(
+            DevCommand::for_key(&KeyWithModifier::new(BareKey::Char('l'))),
+            Some(DevCommand::OpenLogs)
+        );
+        assert_eq!(
+            DevCommand::for_key(&KeyWithModifier::new(BareKey::Char('f')).with_ctrl_modifier()),
+            Some(DevCommand::ChangeFolder)
+        );
+        assert_eq!(
+            DevCommand::for_key(&KeyWithModifier::new(BareKey::Char('f'))),
+            None
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use zellij_tile::prelude::*;
+
+use crate::host::Host;
+use crate::log_viewer::LogLine;
+
+use std::path::{Path, PathBuf};
+
+// how many log polls (one per second) after a reload we consider a panic to be a crash on load
+const CRASH_WINDOW_POLLS: usize = 10;
+const MAX_PANIC_LINES: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginCrash {
+    pub message: String,
+    pub location: Option<SourceLocation>,
+}
+
+#[derive(Debug, Default)]
+pub struct CrashDetection {
+    awaiting_reload: bool,
+    polls_left_in_window: usize,
+    panic_lines: Option<Vec<String>>, // the panic we're currently reading from the log
+    crash: Option<PluginCrash>,
+}
+
+impl CrashDetection {
+    pub fn build_started(&mut self) {
+        self.crash = None;
+        self.panic_lines = None;
+        self.polls_left_in_window = 0;
+        self.awaiting_reload = true;
+    }
+    pub fn build_finished(&mut self, plugin_reloaded: bool) {
+        self.awaiting_reload = false;
+        if plugin_reloaded {
+            self.polls_left_in_window = CRASH_WINDOW_POLLS;
+        }
+    }
+    pub fn get_crash(&self) -> Option<&PluginCrash> {
+        self.crash.as_ref()
+    }
+    pub fn is_watching(&self) -> bool {
+        self.awaiting_reload || self.polls_left_in_window > 0 || self.panic_lines.is_some()
+    }
+    pub fn log_lines_received(&mut self, new_lines: &[LogLine], plugin_ids: &[u32]) {
+        let window_is_open = self.polls_left_in_window > 0;
+        self.polls_left_in_window = self.polls_left_in_window.saturating_sub(1);
+        for line in new_lines {
+            if let Some(panic_lines) = self.panic_lines.as_mut() {
+                // the panic report is logged as one multi-line message, its continuation lines
+                // don't carry a log level prefix
+                if !line.has_level_prefix() && panic_lines.len() < MAX_PANIC_LINES {
+                    panic_lines.push(line.text().to_owned());
+                    continue;
+                }
+                self.crash = Some(parse_panic(panic_lines));
+                self.panic_lines = None;
+            }
+            if window_is_open && is_panic_of(line, plugin_ids) {
+                self.panic_lines = Some(vec![line.text().to_owned()]);
+            }
+        }
+        if let Some(panic_lines) = &self.panic_lines {
+            self.crash = Some(parse_panic(panic_lines));
+        }
+    }
+    pub fn jump_to_source(&self, host: &mut impl Host, project_folder: Option<PathBuf>) {
+        if let Some(location) = self.crash.as_ref().and_then(|c| c.location.as_ref()) {
+            let file_path = match project_folder {
+                Some(project_folder) if Path::new(&location.file).is_relative() => {
+                    project_folder.join(&location.file)
+                }
+                _ => PathBuf::from(&location.file),
+            };
+            host.open_file_floating(
+                FileToOpen::new(file_path).with_line_number(location.line),
+                Default::default(),
+            );
+        }
+    }
+}
+
+fn is_panic_of(line: &LogLine, plugin_ids: &[u32]) -> bool {
+    // the host logs other plugins' panics (and errors of its own) too, so only lines carrying
+    // our plugin's id count (zellij doesn't mark a crashed plugin's pane as exited, so the log is
+    // all we have to go on)
+    line.belongs_to(plugin_ids) && line.text().to_lowercase().contains("panic")
+}
+
+// handles the default panic message formats ("panicked at src/main.rs:12:5:\nmessage" and
+// "panicked at 'message', src/main.rs:12:5") as well as the stringified PanicInfo reported by
+// zellij-tile's panic hook
+fn parse_panic(panic_lines: &[String]) -> PluginCrash {
+    let lines: Vec<&str> = panic_lines
+        .iter()
+        .map(|l| l.trim_matches(|c| c == '\r' || c == '\n'))
+        .collect();
+    let mut message = None;
+    let mut location = None;
+    let mut file = None;
+    let mut line_number = None;
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(panicked_at) = line.find("panicked at ") {
+            let rest = &line[panicked_at + "panicked at ".len()..];
+            if let Some(quoted) = rest.strip_prefix('\'') {
+                if let Some((panic_message, panic_location)) = quoted.rsplit_once("', ") {
+                    message = Some(panic_message.to_owned());
+                    location = parse_location(panic_location);
+                }
+            } else {
+                location = parse_location(rest.trim_end_matches(':'));
+                message = lines.get(i + 1).map(|m| m.trim().to_owned());
+            }
+        } else if let Some(file_name) = debug_field(line, "file") {
+            file = Some(file_name.trim_matches('"').to_owned());
+        } else if let Some(line_field) = debug_field(line, "line") {
+            line_number = line_field.parse().ok();
+        } else if let Some(column_field) = debug_field(line, "col") {
+            if let (Some(file), Some(line_number), Ok(column)) =
+                (file.take(), line_number.take(), column_field.parse())
+            {
+                location = location.or(Some(SourceLocation {
+                    file,
+                    line: line_number,
+                    column,
+                }));
+            }
+        } else if line.trim() == "message: Some(" && message.is_none() {
+            message = lines
+                .get(i + 1)
+                .map(|m| m.trim().trim_end_matches(',').to_owned());
+        }
+    }
+    let message = message
+        .filter(|m| !m.is_empty())
+        .or_else(|| {
+            // the first line of zellij-tile's report is the panic payload
+            lines.iter().find_map(|line| {
+                let payload = line
+                    .rsplit_once("PANIC IN PLUGIN!")
+                    .map(|(_, payload)| payload.trim())?;
+                if payload.is_empty() {
+                    None
+                } else {
+                    Some(payload.to_owned())
+                }
+            })
+        })
+        .or_else(|| lines.get(1).map(|l| l.trim().to_owned()))
+        .filter(|m| !m.is_empty() && m != "<NO PAYLOAD>")
+        .unwrap_or_else(|| "plugin panicked".to_owned());
+    PluginCrash { message, location }
+}
+
+fn parse_location(location: &str) -> Option<SourceLocation> {
+    let mut parts = location.trim().rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = parts.next()?.to_owned();
+    Some(SourceLocation { file, line, column })
+}
+
+// eg. `file: "src/main.rs",` in a pretty-printed Debug struct
+fn debug_field<'a>(line: &'a str, field_name: &str) -> Option<&'a str> {
+    let (name, value) = line.trim().split_once(": ")?;
+    if name == field_name {
+        Some(value.trim_end_matches(','))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLUGIN_PREFIX: &str =
+        "INFO   |file:/home/me/plugin.wasm      | 2024-11-07 17:26:47.123 [id: 3     ] ";
+
+    fn watching_reload() -> CrashDetection {
+        let mut crash_detection = CrashDetection::default();
+        crash_detection.build_started();
+        crash_detection.build_finished(true);
+        crash_detection
+    }
+
+    #[test]
+    fn a_panic_of_the_plugin_after_a_reload_is_a_crash() {
+        let mut crash_detection = watching_reload();
+        let lines = vec![
+            LogLine::new(format!(
+                "{}thread '<unnamed>' panicked at src/main.rs:12:5:",
+                PLUGIN_PREFIX
+            )),
+            LogLine::new("called `Option::unwrap()` on a `None` value".to_owned()),
+            LogLine::new(format!("{}plugin loaded", PLUGIN_PREFIX)),
+        ];
+        crash_detection.log_lines_received(&lines, &[3]);
+        assert_eq!(
+            crash_detection.get_crash(),
+            Some(&PluginCrash {
+                message: "called `Option::unwrap()` on a `None` value".to_owned(),
+                location: Some(SourceLocation {
+                    file: "src/main.rs".to_owned(),
+                    line: 12,
+                    column: 5,
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn errors_and_panics_of_other_plugins_arent_a_crash() {
+        let mut crash_detection = watching_reload();
+        let lines = vec![
+            LogLine::new(
+                "ERROR  |zellij_server::plugins| 2024-11-07 17:26:47.123 [id: 0     ] PANIC IN PLUGIN! oops"
+                    .to_owned(),
+            ),
+            LogLine::new(
+                "INFO   |file:/home/me/other.wasm       | 2024-11-07 17:26:47.123 [id: 7     ] thread '<unnamed>' panicked at src/lib.rs:1:1:"
+                    .to_owned(),
+            ),
+            LogLine::new("ERROR  |zellij_server::route| failed to route the action".to_owned()),
+        ];
+        crash_detection.log_lines_received(&lines, &[3]);
+        assert_eq!(crash_detection.get_crash(), None);
+    }
+
+    #[test]
+    fn parses_the_location_and_message_of_both_panic_formats() {
+        let old_format =
+            parse_panic(&["thread 'main' panicked at 'oops', src/lib.rs:3:9".to_owned()]);
+        assert_eq!(old_format.message, "oops");
+        assert_eq!(
+            old_format.location,
+            Some(SourceLocation {
+                file: "src/lib.rs".to_owned(),
+                line: 3,
+                column: 9,
+            })
+        );
+        assert_eq!(
+            parse_location("C:/plugin/src/main.rs:40:17"),
+            Some(SourceLocation {
+                file: "C:/plugin/src/main.rs".to_owned(),
+                line: 40,
+                column: 17,
+            })
+        );
+        assert_eq!(parse_location("src/main.rs"), None);
+    }
+}
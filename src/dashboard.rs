@@ -0,0 +1,411 @@
+use zellij_tile::prelude::*;
+
+use crate::cargo_messages::Diagnostic;
+use crate::cargo_workspace::CargoProject;
+use crate::command_palette::{DevCommand, COMMANDS};
+use crate::crash_detection::PluginCrash;
+use crate::hooks::{HookKind, HookStatus, Hooks};
+use crate::host::RecordingHost;
+use crate::run_and_reload::BuildStatus;
+use crate::ui::truncate;
+use crate::update_workspace::BindingState;
+
+use std::path::PathBuf;
+
+const LABEL_WIDTH: usize = 12;
+const MAX_ERRORS: usize = 5;
+
+// everything the dashboard shows, gathered from the other parts of the plugin on every render
+pub struct Dashboard<'a> {
+    pub project_folder: Option<PathBuf>,
+    pub plugin_name: Option<String>,
+    pub cargo_project: Option<&'a CargoProject>,
+    pub selected_members: &'a [String],
+    pub reload_shortcut: String,
+    pub binding_state: BindingState,
+    pub build_status: BuildStatus,
+    pub quiet_builds: bool,
+    pub has_build_output: bool,
+    pub timing_summary: Option<String>,
+    pub plugin_instances: usize,
+    pub close_with_plugin: bool,
+    pub crash: Option<&'a PluginCrash>,
+    pub watching_log: bool,
+    pub size_matrix_status: Option<&'a str>,
+    pub transcript_path: Option<&'a PathBuf>,
+    pub hooks: &'a Hooks<RecordingHost>,
+    pub build_progress: Option<String>,
+    pub check: String,
+    pub warnings: Option<String>, // compared to the baseline
+    pub reload_blocked: bool,
+    pub artifact: Option<String>, // whether zellij can load the built wasm
+    pub artifact_blocked: bool,
+    pub reports: Option<&'a Result<String, String>>, // how the last export went
+    pub errors: Vec<&'a Diagnostic>,                 // of the last check or build
+}
+
+impl<'a> Dashboard<'a> {
+    pub fn render(&self, y: usize, rows: usize, cols: usize) {
+        let mut y = y;
+        if rows <= y {
+            return;
+        }
+        self.render_header(y, cols);
+        y += 2;
+        let status_rows = self.status_rows(cols);
+        // the table has a title row
+        let table_height = std::cmp::min(status_rows.len() + 1, rows.saturating_sub(y));
+        let mut table = Table::new().add_row(vec!["Status", ""]);
+        for (label, value, color) in status_rows.into_iter().take(table_height.saturating_sub(1)) {
+            let mut value = Text::new(value);
+            if let Some(color) = color {
+                value = value.color_range(color, ..);
+            }
+            table = table.add_styled_row(vec![Text::new(label), value]);
+        }
+        print_table_with_coordinates(table, 0, y, Some(cols), Some(table_height));
+        y += table_height + 1;
+        if y < rows && !self.errors.is_empty() {
+            let errors = self.errors(cols);
+            let list_height = std::cmp::min(errors.len(), rows - y);
+            let errors = errors.into_iter().take(list_height).collect();
+            print_nested_list_with_coordinates(errors, 0, y, Some(cols), Some(list_height));
+            y += list_height + 1;
+        }
+        if y < rows {
+            let shortcuts = self.shortcuts(cols);
+            let list_height = std::cmp::min(shortcuts.len(), rows - y);
+            let shortcuts = shortcuts.into_iter().take(list_height).collect();
+            print_nested_list_with_coordinates(shortcuts, 0, y, Some(cols), Some(list_height));
+        }
+    }
+    fn render_header(&self, y: usize, cols: usize) {
+        let plugin_name = self
+            .plugin_name
+            .clone()
+            .unwrap_or_else(|| "<NO FOLDER SELECTED>".to_owned());
+        let header = match &self.project_folder {
+            Some(project_folder) => format!("{} - {}", plugin_name, project_folder.display()),
+            None => plugin_name.clone(),
+        };
+        let header = truncate(&header, cols);
+        let name_length = std::cmp::min(plugin_name.chars().count(), header.chars().count());
+        print_text_with_coordinates(
+            Text::new(header).color_range(2, ..name_length),
+            0,
+            y,
+            None,
+            None,
+        );
+    }
+    // (label, value, color of the value)
+    fn status_rows(&self, cols: usize) -> Vec<(String, String, Option<usize>)> {
+        let value_width = cols.saturating_sub(LABEL_WIDTH + 1);
+        let mut rows = vec![];
+        let (build, build_color) = match self.build_status {
+            BuildStatus::NotStarted => (
+                format!("not built yet, press {} to build", self.reload_shortcut),
+                None,
+            ),
+            BuildStatus::Building => ("building...".to_owned(), Some(0)),
+            BuildStatus::Succeeded => ("succeeded".to_owned(), Some(2)),
+            BuildStatus::Failed(Some(exit_code)) => {
+                (format!("failed (exit code {})", exit_code), Some(3))
+            }
+            BuildStatus::Failed(None) => ("failed".to_owned(), Some(3)),
+        };
+        let build = if self.quiet_builds {
+            format!("{} (quiet)", build)
+        } else {
+            build
+        };
+        rows.push(("Build", build, build_color));
+        if let Some(build_progress) = &self.build_progress {
+            rows.push(("Progress", build_progress.clone(), None));
+        }
+        rows.push(("Check", self.check.clone(), None));
+        if let Some(warnings) = &self.warnings {
+            let warnings_color = if self.reload_blocked { Some(3) } else { None };
+            rows.push(("Warnings", warnings.clone(), warnings_color));
+        }
+        if let Some(artifact) = &self.artifact {
+            let artifact_color = if self.artifact_blocked { Some(3) } else { None };
+            rows.push(("Artifact", artifact.clone(), artifact_color));
+        }
+        match self.reports {
+            Some(Ok(exported)) => rows.push(("Reports", exported.clone(), None)),
+            Some(Err(e)) => rows.push(("Reports", e.clone(), Some(3))),
+            None => {}
+        }
+        if let Some(cargo_project) = self.cargo_project.filter(|c| c.is_workspace) {
+            let (members, members_color) = if self.selected_members.is_empty() {
+                (
+                    format!(
+                        "{} plugins, none selected (<w> to choose)",
+                        cargo_project.plugins.len()
+                    ),
+                    Some(3),
+                )
+            } else {
+                (
+                    format!(
+                        "{} plugins, building {}",
+                        cargo_project.plugins.len(),
+                        self.selected_members.join(", ")
+                    ),
+                    None,
+                )
+            };
+            rows.push(("Workspace", members, members_color));
+        }
+        let hook_config = self.hooks.get_config();
+        if let Some(pre_build_command) = &hook_config.pre_build_command {
+            let status = self.hooks.get_status(HookKind::PreBuild);
+            rows.push((
+                "Pre-build",
+                format!("{} - {}", pre_build_command, status),
+                hook_status_color(status),
+            ));
+        }
+        let mut post_reload = vec![];
+        if let Some(post_reload_command) = &hook_config.post_reload_command {
+            post_reload.push(format!(
+                "{} - {}",
+                post_reload_command,
+                self.hooks.get_status(HookKind::PostReload)
+            ));
+        }
+        if let Some(post_reload_pipe) = &hook_config.post_reload_pipe {
+            post_reload.push(format!(
+                "pipe \"{}\" sent {} times",
+                post_reload_pipe,
+                self.hooks.get_post_reload_pipes_sent()
+            ));
+        }
+        if !post_reload.is_empty() {
+            rows.push((
+                "Post-reload",
+                post_reload.join(", "),
+                hook_status_color(self.hooks.get_status(HookKind::PostReload)),
+            ));
+        }
+        if let Some(timing_summary) = &self.timing_summary {
+            rows.push(("Last build", timing_summary.clone(), None));
+        }
+        rows.push((
+            "Shortcut",
+            format!("{} - {}", self.reload_shortcut, self.binding_state),
+            None,
+        ));
+        let plugin = match self.plugin_instances {
+            0 => "not running".to_owned(),
+            1 => "running".to_owned(),
+            instances => format!("running in {} panes", instances),
+        };
+        let plugin = if self.close_with_plugin {
+            format!("{}, closing it closes this pane", plugin)
+        } else {
+            plugin
+        };
+        rows.push(("Plugin", plugin, None));
+        let (health, health_color) = match self.crash {
+            Some(crash) => {
+                let location = crash
+                    .location
+                    .as_ref()
+                    .map(|l| format!(" ({}:{})", l.file, l.line))
+                    .unwrap_or_default();
+                (
+                    format!("crashed on load: {}{}", crash.message, location),
+                    Some(3),
+                )
+            }
+            None => ("no panics since the last reload".to_owned(), None),
+        };
+        rows.push(("Crashes", health, health_color));
+        let watch = if self.watching_log {
+            "watching the zellij log for panics"
+        } else {
+            "idle"
+        };
+        rows.push(("Watch", watch.to_owned(), None));
+        if let Some(size_matrix_status) = self.size_matrix_status {
+            rows.push(("Size matrix", size_matrix_status.to_owned(), None));
+        }
+        if let Some(transcript_path) = self.transcript_path {
+            rows.push((
+                "Transcript",
+                format!("recording to {}", transcript_path.display()),
+                None,
+            ));
+        }
+        rows.into_iter()
+            .map(|(label, value, color)| (label.to_owned(), truncate(&value, value_width), color))
+            .collect()
+    }
+    // the first errors of the check or build, as soon as cargo reports them
+    fn errors(&self, cols: usize) -> Vec<NestedListItem> {
+        let mut errors: Vec<NestedListItem> = self
+            .errors
+            .iter()
+            .take(MAX_ERRORS)
+            .map(|error| {
+                let line = truncate(&error.summary(), cols.saturating_sub(2));
+                NestedListItem::new(&line).color_range(3, ..)
+            })
+            .collect();
+        if self.errors.len() > MAX_ERRORS {
+            let more = format!("...and {} more (<d>)", self.errors.len() - MAX_ERRORS);
+            errors.push(NestedListItem::new(truncate(&more, cols.saturating_sub(2))));
+        }
+        errors
+    }
+    fn shortcuts(&self, cols: usize) -> Vec<NestedListItem> {
+        let is_workspace = self.cargo_project.map(|c| c.is_workspace) == Some(true);
+        let has_panic_location = self.crash.and_then(|c| c.location.as_ref()).is_some();
+        let mut shortcuts = vec![(
+            format!("<{}>", self.reload_shortcut),
+            DevCommand::BuildAndReload.description(),
+        )];
+        shortcuts.extend(
+            COMMANDS
+                .iter()
+                .filter(|command| match command {
+                    DevCommand::ChooseMembers => is_workspace,
+                    DevCommand::JumpToPanic => has_panic_location,
+                    DevCommand::ShowBuildOutput => self.has_build_output,
+                    _ => true,
+                })
+                .filter_map(|command| {
+                    command
+                        .key()
+                        .map(|key| (format!("<{}>", key), command.description()))
+                }),
+        );
+        shortcuts.push(("<:>".to_owned(), "Search all commands"));
+        shortcuts
+            .into_iter()
+            .map(|(key, description)| {
+                let key_length = key.chars().count();
+                // nested list items are indented by a bullet
+                let line = truncate(&format!("{} {}", key, description), cols.saturating_sub(2));
+                NestedListItem::new(&line)
+                    .color_range(3, ..std::cmp::min(key_length, line.chars().count()))
+            })
+            .collect()
+    }
+}
+
+fn hook_status_color(status: HookStatus) -> Option<usize> {
+    match status {
+        HookStatus::Failed(_) => Some(3),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crash_detection::SourceLocation;
+
+    fn dashboard(hooks: &Hooks<RecordingHost>) -> Dashboard<'_> {
+        Dashboard {
+            project_folder: Some(PathBuf::from("/projects/my-plugin")),
+            plugin_name: Some("my-plugin".to_owned()),
+            cargo_project: None,
+            selected_members: &[],
+            reload_shortcut: "Ctrl Shift r".to_owned(),
+            binding_state: BindingState::Unbound,
+            build_status: BuildStatus::NotStarted,
+            quiet_builds: false,
+            has_build_output: false,
+            timing_summary: None,
+            plugin_instances: 0,
+            close_with_plugin: true,
+            crash: None,
+            watching_log: false,
+            size_matrix_status: None,
+            transcript_path: None,
+            hooks,
+            build_progress: None,
+            check: "not checked yet".to_owned(),
+            warnings: None,
+            reload_blocked: false,
+            artifact: None,
+            artifact_blocked: false,
+            reports: None,
+            errors: vec![],
+        }
+    }
+
+    fn row(dashboard: &Dashboard, label: &str) -> Option<(String, Option<usize>)> {
+        dashboard
+            .status_rows(200)
+            .into_iter()
+            .find(|(l, _, _)| l == label)
+            .map(|(_, value, color)| (value, color))
+    }
+
+    #[test]
+    fn a_crash_on_load_is_shown_in_red_with_its_location() {
+        let hooks = Hooks::default();
+        let mut dashboard = dashboard(&hooks);
+        assert_eq!(
+            row(&dashboard, "Crashes"),
+            Some(("no panics since the last reload".to_owned(), None))
+        );
+        let crash = PluginCrash {
+            message: "oops".to_owned(),
+            location: Some(SourceLocation {
+                file: "src/main.rs".to_owned(),
+                line: 12,
+                column: 5,
+            }),
+        };
+        dashboard.crash = Some(&crash);
+        assert_eq!(
+            row(&dashboard, "Crashes"),
+            Some(("crashed on load: oops (src/main.rs:12)".to_owned(), Some(3)))
+        );
+    }
+
+    #[test]
+    fn the_build_row_is_colored_by_its_status() {
+        let hooks = Hooks::default();
+        let mut dashboard = dashboard(&hooks);
+        assert_eq!(
+            row(&dashboard, "Build"),
+            Some((
+                "not built yet, press Ctrl Shift r to build".to_owned(),
+                None
+            ))
+        );
+        dashboard.build_status = BuildStatus::Succeeded;
+        assert_eq!(
+            row(&dashboard, "Build"),
+            Some(("succeeded".to_owned(), Some(2)))
+        );
+        dashboard.build_status = BuildStatus::Failed(Some(101));
+        dashboard.quiet_builds = true;
+        assert_eq!(
+            row(&dashboard, "Build"),
+            Some(("failed (exit code 101) (quiet)".to_owned(), Some(3)))
+        );
+        assert_eq!(row(&dashboard, "Progress"), None);
+    }
+
+    #[test]
+    fn the_check_row_shows_how_the_last_check_went() {
+        let hooks = Hooks::default();
+        let mut dashboard = dashboard(&hooks);
+        assert_eq!(
+            row(&dashboard, "Check"),
+            Some(("not checked yet".to_owned(), None))
+        );
+        dashboard.check = "2 errors".to_owned();
+        assert_eq!(
+            row(&dashboard, "Check"),
+            Some(("2 errors".to_owned(), None))
+        );
+    }
+}
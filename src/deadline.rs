@@ -0,0 +1,35 @@
+use crate::host::Host;
+
+use std::time::{Duration, Instant};
+
+// the host might deliver a timer a bit before our clock agrees it's due
+const TOLERANCE: Duration = Duration::from_millis(20);
+
+// Event::Timer doesn't tell us whose timeout elapsed, so every part of the plugin that uses
+// set_timeout keeps its own deadline and checks it whenever any timer fires
+#[derive(Debug, Default)]
+pub struct Deadline {
+    at: Option<Instant>,
+}
+
+impl Deadline {
+    pub fn schedule(&mut self, host: &mut impl Host, secs: f64) {
+        if self.at.is_none() {
+            host.set_timeout(secs);
+            self.at = Some(Instant::now() + Duration::from_secs_f64(secs));
+        }
+    }
+    pub fn cancel(&mut self) {
+        self.at = None;
+    }
+    // returns true (once) if the deadline has passed
+    pub fn elapsed(&mut self) -> bool {
+        match self.at {
+            Some(at) if Instant::now() + TOLERANCE >= at => {
+                self.at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
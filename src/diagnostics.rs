@@ -0,0 +1,477 @@
+use zellij_tile::prelude::*;
+
+use crate::build_progress::plural;
+use crate::cargo_messages::Diagnostic;
+use crate::host::Host;
+use crate::host_fs::HostFs;
+use crate::quick_fix::Suggestion;
+use crate::snapshots::DiffLine;
+use crate::source_context::{render_lines, source_context, ContextLine};
+use crate::ui::{help_text, truncate};
+use crate::warning_budget::WarningBudget;
+
+use std::path::{Path, PathBuf};
+
+const CONTEXT_LINES: usize = 2; // around the lines the diagnostic points at
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticsOrigin {
+    Check,
+    Build,
+}
+
+// the errors and warnings of the last check or build, whichever came last
+#[derive(Debug, Default)]
+pub struct DiagnosticsList {
+    origin: Option<DiagnosticsOrigin>,
+    diagnostics: Vec<Diagnostic>, // the errors first
+    selected_index: usize,
+    status: Option<String>, // how applying the last suggestion went
+    preview: Option<SelectionPreview>,
+}
+
+// the changed lines of every file a suggestion changes, or why we can't tell
+type FixPreview = Result<Vec<(String, Vec<DiffLine>)>, String>;
+
+// what's shown below the list about the selected diagnostic, so that we read its files once
+// rather than on every render
+#[derive(Debug)]
+struct SelectionPreview {
+    diagnostic: Diagnostic,
+    workspace_root: Option<PathBuf>,
+    source: Option<(String, Result<Vec<ContextLine>, String>)>, // eg. "--> src/main.rs:3:5"
+    fix: Option<(String, FixPreview)>, // the suggestion's message and its changes
+}
+
+impl SelectionPreview {
+    fn of(diagnostic: &Diagnostic, workspace_root: Option<&Path>, host_fs: &HostFs) -> Self {
+        let source = diagnostic
+            .primary_span()
+            .zip(workspace_root)
+            .map(|(span, workspace_root)| {
+                let location = format!(
+                    "--> {}:{}:{}",
+                    span.file_name, span.line_start, span.column_start
+                );
+                let context = host_fs
+                    .read_to_string(&workspace_root.join(&span.file_name))
+                    .map(|content| {
+                        source_context(&content, diagnostic, &span.file_name, CONTEXT_LINES)
+                    });
+                (location, context)
+            });
+        let fix = Suggestion::of(diagnostic).map(|suggestion| {
+            let preview = match workspace_root {
+                Some(workspace_root) => suggestion.preview(workspace_root, host_fs),
+                None => Err("No folder selected".to_owned()),
+            };
+            (suggestion.message, preview)
+        });
+        SelectionPreview {
+            diagnostic: diagnostic.clone(),
+            workspace_root: workspace_root.map(|w| w.to_path_buf()),
+            source,
+            fix,
+        }
+    }
+}
+
+impl DiagnosticsList {
+    pub fn start(&mut self, origin: DiagnosticsOrigin) {
+        self.origin = Some(origin);
+        self.diagnostics.clear();
+        self.selected_index = 0;
+        self.status = None;
+        // the files might have changed since
+        self.preview = None;
+    }
+    pub fn extend(&mut self, origin: DiagnosticsOrigin, diagnostics: Vec<Diagnostic>) {
+        if self.origin != Some(origin) {
+            return;
+        }
+        self.diagnostics.extend(diagnostics);
+        // the sort is stable, so they stay in the order cargo reported them otherwise
+        self.diagnostics.sort_by_key(|d| !d.is_error());
+    }
+    pub fn get_errors(&self) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.is_error()).collect()
+    }
+    pub fn get_warnings(&self) -> Vec<&Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.is_warning()).collect()
+    }
+    // returns whether to render and whether to build and reload now
+    pub fn handle_key(
+        &mut self,
+        host: &mut impl Host,
+        key: KeyWithModifier,
+        workspace_root: Option<PathBuf>,
+        host_fs: &HostFs,
+    ) -> (bool, bool) {
+        let mut should_render = true;
+        let mut rebuild = false;
+        match key.bare_key {
+            BareKey::Down if key.has_no_modifiers() => {
+                if self.selected_index + 1 < self.diagnostics.len() {
+                    self.selected_index += 1;
+                }
+            }
+            BareKey::Up if key.has_no_modifiers() => {
+                self.selected_index = self.selected_index.saturating_sub(1);
+            }
+            BareKey::Enter if key.has_no_modifiers() => {
+                self.open_selected(host, workspace_root);
+                should_render = false;
+            }
+            BareKey::Char('a') if key.has_no_modifiers() => {
+                self.apply_selected_suggestion(workspace_root, host_fs);
+            }
+            BareKey::Char('b') if key.has_no_modifiers() => {
+                rebuild = self.apply_selected_suggestion(workspace_root, host_fs);
+            }
+            _ => {
+                should_render = false;
+            }
+        }
+        (should_render, rebuild)
+    }
+    pub fn render(
+        &mut self,
+        y: usize,
+        rows: usize,
+        cols: usize,
+        workspace_root: Option<&Path>,
+        host_fs: &HostFs,
+        warning_budget: &WarningBudget,
+    ) {
+        self.update_preview(workspace_root, host_fs);
+        let error_count = self.get_errors().len();
+        let warning_count = self.diagnostics.len() - error_count;
+        let title = match self.origin {
+            Some(DiagnosticsOrigin::Check) => "Diagnostics - last check",
+            Some(DiagnosticsOrigin::Build) => "Diagnostics - last build",
+            None => "Diagnostics",
+        };
+        print_text_with_coordinates(
+            Text::new(truncate(title, cols)).color_range(2, ..),
+            0,
+            y,
+            None,
+            None,
+        );
+        let status = self.status.clone().unwrap_or_else(|| {
+            let new_warning_count = warning_budget.get_new_warning_count();
+            let new_warnings = if new_warning_count > 0 {
+                format!(" ({} new)", new_warning_count)
+            } else {
+                String::new()
+            };
+            format!(
+                "{}, {}{}",
+                plural(error_count, "error"),
+                plural(warning_count, "warning"),
+                new_warnings
+            )
+        });
+        print_text_with_coordinates(Text::new(truncate(&status, cols)), 0, y + 1, None, None);
+        if self.origin.is_none() {
+            print_text_with_coordinates(
+                Text::new(truncate("<NOT BUILT OR CHECKED YET>", cols)),
+                0,
+                y + 3,
+                None,
+                None,
+            );
+        }
+        let mut preview = self.source_lines(cols);
+        preview.extend(self.preview_lines(cols));
+        // the preview gets at most half of the screen, below the list
+        let preview_height = std::cmp::min(preview.len(), rows.saturating_sub(y + 4) / 2);
+        let available_rows = rows.saturating_sub(y + 4 + preview_height);
+        let first_visible = self
+            .selected_index
+            .saturating_sub(available_rows.saturating_sub(1));
+        for (i, diagnostic) in self
+            .diagnostics
+            .iter()
+            .enumerate()
+            .skip(first_visible)
+            .take(available_rows)
+        {
+            let level_color = if diagnostic.is_error() { 3 } else { 1 };
+            // warnings the baseline doesn't have stand out
+            let level = if warning_budget.is_new(diagnostic) {
+                format!("{} (new)", diagnostic.level)
+            } else {
+                diagnostic.level.clone()
+            };
+            let line = truncate(&format!("{}: {}", level, diagnostic.summary()), cols);
+            let level_length =
+                std::cmp::min(diagnostic.level.chars().count(), line.chars().count());
+            let label_length = std::cmp::min(level.chars().count(), line.chars().count());
+            let mut line = Text::new(&line)
+                .color_range(level_color, ..level_length)
+                .color_range(3, level_length..label_length);
+            if i == self.selected_index {
+                line = line.selected();
+            }
+            print_text_with_coordinates(line, 0, y + 3 + i - first_visible, None, None);
+        }
+        let preview_y = rows.saturating_sub(1 + preview_height);
+        for (i, line) in preview.into_iter().take(preview_height).enumerate() {
+            print_text_with_coordinates(line, 0, preview_y + i, None, None);
+        }
+        let help =
+            "<↓↑> move, <Enter> open in editor, <a> apply fix, <b> apply fix and build, <Esc> back";
+        print_text_with_coordinates(help_text(help, cols), 0, rows.saturating_sub(1), None, None);
+    }
+    // reads the files again only when another diagnostic is selected, or after a new build, check
+    // or applied fix
+    fn update_preview(&mut self, workspace_root: Option<&Path>, host_fs: &HostFs) {
+        let diagnostic = match self.diagnostics.get(self.selected_index) {
+            Some(diagnostic) => diagnostic,
+            None => {
+                self.preview = None;
+                return;
+            }
+        };
+        let is_up_to_date = self.preview.as_ref().is_some_and(|preview| {
+            &preview.diagnostic == diagnostic && preview.workspace_root.as_deref() == workspace_root
+        });
+        if !is_up_to_date {
+            self.preview = Some(SelectionPreview::of(diagnostic, workspace_root, host_fs));
+        }
+    }
+    // the code the selected diagnostic points at, read from the host so there's no need for an editor
+    fn source_lines(&self, cols: usize) -> Vec<Text> {
+        let (location, context) = match self.preview.as_ref().and_then(|p| p.source.as_ref()) {
+            Some(source) => source,
+            None => return vec![],
+        };
+        let mut lines = vec![Text::new(truncate(location, cols)).color_range(0, ..3)];
+        match context {
+            Ok(context) => lines.extend(render_lines(context, cols)),
+            Err(e) => lines.push(Text::new(truncate(e, cols)).color_range(3, ..)),
+        }
+        lines
+    }
+    // the selected diagnostic's suggestion and the changes it makes
+    fn preview_lines(&self, cols: usize) -> Vec<Text> {
+        let (message, preview) = match self.preview.as_ref().and_then(|p| p.fix.as_ref()) {
+            Some(fix) => fix,
+            None => return vec![],
+        };
+        let title = format!("Fix: {}", message);
+        let mut lines = vec![Text::new(truncate(&title, cols)).color_range(2, ..4)];
+        match preview {
+            Ok(files) => {
+                for (file_name, diff) in files {
+                    lines.push(Text::new(truncate(file_name, cols)).color_range(0, ..));
+                    for line in diff {
+                        let (prefix, text, color) = match &line {
+                            DiffLine::Removed(text) => ("-", text, 3),
+                            DiffLine::Added(text) => ("+", text, 2),
+                            DiffLine::Unchanged(text) => (" ", text, 0),
+                        };
+                        let line = truncate(&format!("{} {}", prefix, text), cols);
+                        lines.push(Text::new(line).color_range(color, ..1));
+                    }
+                }
+            }
+            Err(e) => lines.push(Text::new(truncate(e, cols)).color_range(3, ..)),
+        }
+        lines
+    }
+    // returns true if the suggestion was applied
+    fn apply_selected_suggestion(
+        &mut self,
+        workspace_root: Option<PathBuf>,
+        host_fs: &HostFs,
+    ) -> bool {
+        let suggestion = match self
+            .diagnostics
+            .get(self.selected_index)
+            .and_then(Suggestion::of)
+        {
+            Some(suggestion) => suggestion,
+            None => {
+                self.status = Some("The compiler has no fix for this one".to_owned());
+                return false;
+            }
+        };
+        let result = match workspace_root {
+            Some(workspace_root) => suggestion.apply(&workspace_root, host_fs),
+            None => Err("No folder selected".to_owned()),
+        };
+        match result {
+            Ok(()) => {
+                self.status = Some(format!("Applied: {}", suggestion.message));
+                self.diagnostics.remove(self.selected_index);
+                self.preview = None;
+                if self.selected_index >= self.diagnostics.len() {
+                    self.selected_index = self.diagnostics.len().saturating_sub(1);
+                }
+                true
+            }
+            Err(e) => {
+                self.status = Some(e);
+                false
+            }
+        }
+    }
+    fn open_selected(&self, host: &mut impl Host, workspace_root: Option<PathBuf>) {
+        let span = match self
+            .diagnostics
+            .get(self.selected_index)
+            .and_then(|d| d.primary_span())
+        {
+            Some(span) => span,
+            None => return,
+        };
+        // rustc reports the files of the workspace relative to its root
+        let file_path = match workspace_root {
+            Some(workspace_root) if Path::new(&span.file_name).is_relative() => {
+                workspace_root.join(&span.file_name)
+            }
+            _ => PathBuf::from(&span.file_name),
+        };
+        host.open_file_floating(
+            FileToOpen::new(file_path).with_line_number(span.line_start),
+            Default::default(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::RecordingHost;
+
+    fn diagnostic(level: &str, message: &str) -> Diagnostic {
+        serde_json::from_str(&format!(
+            "{{\"message\":\"{}\",\"level\":\"{}\",\"spans\":[]}}",
+            message, level
+        ))
+        .unwrap()
+    }
+
+    fn pointing_at(message: &str, file_name: &str) -> Diagnostic {
+        serde_json::from_str(&format!(
+            r#"{{"message":"{}","level":"error","spans":[{{"file_name":"{}","line_start":3,"line_end":3,"column_start":5,"column_end":6,"is_primary":true}}]}}"#,
+            message, file_name
+        ))
+        .unwrap()
+    }
+
+    fn previewed_source(
+        diagnostics: &DiagnosticsList,
+    ) -> Option<&Result<Vec<ContextLine>, String>> {
+        diagnostics
+            .preview
+            .as_ref()
+            .and_then(|p| p.source.as_ref())
+            .map(|(_, context)| context)
+    }
+
+    fn messages(diagnostics: &DiagnosticsList) -> Vec<&str> {
+        diagnostics
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn errors_come_first_in_the_order_cargo_reported_them() {
+        let mut diagnostics = DiagnosticsList::default();
+        diagnostics.start(DiagnosticsOrigin::Build);
+        diagnostics.extend(
+            DiagnosticsOrigin::Build,
+            vec![diagnostic("warning", "unused"), diagnostic("error", "a")],
+        );
+        diagnostics.extend(DiagnosticsOrigin::Build, vec![diagnostic("error", "b")]);
+        assert_eq!(messages(&diagnostics), vec!["a", "b", "unused"]);
+        assert_eq!(diagnostics.get_errors().len(), 2);
+        assert_eq!(diagnostics.get_warnings().len(), 1);
+    }
+
+    #[test]
+    fn diagnostics_of_the_other_origin_are_ignored() {
+        let mut diagnostics = DiagnosticsList::default();
+        diagnostics.extend(DiagnosticsOrigin::Build, vec![diagnostic("error", "a")]);
+        assert!(messages(&diagnostics).is_empty());
+        diagnostics.start(DiagnosticsOrigin::Check);
+        diagnostics.extend(DiagnosticsOrigin::Build, vec![diagnostic("error", "b")]);
+        diagnostics.extend(DiagnosticsOrigin::Check, vec![diagnostic("error", "c")]);
+        assert_eq!(messages(&diagnostics), vec!["c"]);
+    }
+
+    #[test]
+    fn the_selected_source_is_read_once_per_selection_and_build() {
+        let workspace_root = Some(Path::new("/projects/my-plugin"));
+        let mut diagnostics = DiagnosticsList::default();
+        diagnostics.start(DiagnosticsOrigin::Build);
+        diagnostics.extend(
+            DiagnosticsOrigin::Build,
+            vec![
+                pointing_at("a", "src/main.rs"),
+                pointing_at("b", "src/lib.rs"),
+            ],
+        );
+        diagnostics.update_preview(workspace_root, &HostFs::default());
+        let unknown_host_folder = Err("the host folder is unknown".to_owned());
+        assert_eq!(previewed_source(&diagnostics), Some(&unknown_host_folder));
+        // the files are only read again once something else is selected or built
+        let mut host_fs = HostFs::default();
+        host_fs.update_root(PathBuf::from("/elsewhere"));
+        diagnostics.update_preview(workspace_root, &host_fs);
+        assert_eq!(previewed_source(&diagnostics), Some(&unknown_host_folder));
+        diagnostics.handle_key(
+            &mut RecordingHost::default(),
+            KeyWithModifier::new(BareKey::Down),
+            None,
+            &host_fs,
+        );
+        diagnostics.update_preview(workspace_root, &host_fs);
+        assert!(matches!(
+            previewed_source(&diagnostics),
+            Some(Err(e)) if e.starts_with("/projects/my-plugin/src/lib.rs is outside")
+        ));
+        diagnostics.start(DiagnosticsOrigin::Check);
+        diagnostics.extend(
+            DiagnosticsOrigin::Check,
+            vec![pointing_at("a", "src/main.rs")],
+        );
+        diagnostics.update_preview(workspace_root, &host_fs);
+        assert!(matches!(
+            previewed_source(&diagnostics),
+            Some(Err(e)) if e.starts_with("/projects/my-plugin/src/main.rs is outside")
+        ));
+    }
+
+    #[test]
+    fn starting_over_clears_the_list_and_the_selection() {
+        let mut diagnostics = DiagnosticsList::default();
+        diagnostics.start(DiagnosticsOrigin::Check);
+        diagnostics.extend(
+            DiagnosticsOrigin::Check,
+            vec![diagnostic("error", "a"), diagnostic("error", "b")],
+        );
+        let down = KeyWithModifier::new(BareKey::Down);
+        diagnostics.handle_key(
+            &mut RecordingHost::default(),
+            down.clone(),
+            None,
+            &HostFs::default(),
+        );
+        diagnostics.handle_key(
+            &mut RecordingHost::default(),
+            down,
+            None,
+            &HostFs::default(),
+        );
+        assert_eq!(diagnostics.selected_index, 1);
+        diagnostics.start(DiagnosticsOrigin::Build);
+        assert!(messages(&diagnostics).is_empty());
+        assert_eq!(diagnostics.selected_index, 0);
+    }
+}
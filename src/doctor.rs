@@ -0,0 +1,459 @@
+use zellij_tile::prelude::*;
+
+use crate::host::Host;
+use crate::host_fs::HostFs;
+use crate::run_and_reload::PLUGIN_TARGET;
+use crate::ui::{help_text, truncate};
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const LABEL_WIDTH: usize = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Check {
+    Cargo,
+    InstalledTargets,
+    BuildTarget,
+    WasmOpt,
+    Artifact,
+}
+
+impl Check {
+    fn label(&self) -> &'static str {
+        match self {
+            Check::Cargo => "cargo",
+            Check::InstalledTargets => "rustup targets",
+            Check::BuildTarget => "build target",
+            Check::WasmOpt => "wasm-opt",
+            Check::Artifact => "artifact",
+        }
+    }
+    // the checks that run a command, and the command
+    fn command(&self) -> Option<&'static str> {
+        match self {
+            Check::Cargo => Some("cargo --version"),
+            Check::InstalledTargets => Some("rustup target list --installed"),
+            Check::WasmOpt => Some("wasm-opt --version"),
+            Check::BuildTarget | Check::Artifact => None,
+        }
+    }
+    fn from_label(label: &str) -> Option<Check> {
+        [Check::Cargo, Check::InstalledTargets, Check::WasmOpt]
+            .iter()
+            .copied()
+            .find(|c| c.label() == label)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    Checking,
+    Ok(String),
+    Warning { problem: String, remedy: String }, // the plugin still builds and loads
+    Failed { problem: String, remedy: String },
+}
+
+// checks what it takes to build the plugin and load it from where we look for it, since a
+// missing target or a target that doesn't match where we look for the wasm file only shows up
+// as a plugin that doesn't reload
+#[derive(Debug, Default)]
+pub struct Doctor {
+    run_id: u32,
+    findings: BTreeMap<Check, Finding>,
+    no_folder: bool,
+}
+
+impl Doctor {
+    pub fn run(
+        &mut self,
+        host: &mut impl Host,
+        project_folder: Option<PathBuf>,
+        target_directory: Option<PathBuf>,
+        plugin_paths: Vec<PathBuf>,
+        host_fs: &HostFs,
+    ) {
+        self.run_id = self.run_id.wrapping_add(1);
+        self.findings.clear();
+        let project_folder = match project_folder {
+            Some(project_folder) => project_folder,
+            None => {
+                self.no_folder = true;
+                return;
+            }
+        };
+        self.no_folder = false;
+        for check in [Check::Cargo, Check::InstalledTargets, Check::WasmOpt] {
+            if let Some(command) = check.command() {
+                let mut context = BTreeMap::new();
+                context.insert("source".to_owned(), "doctor".to_owned());
+                context.insert("check".to_owned(), check.label().to_owned());
+                context.insert("run_id".to_owned(), self.run_id.to_string());
+                // through the shell, so that a missing program is an exit code like any other
+                host.run_command(
+                    ["sh", "-c", command]
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect(),
+                    BTreeMap::new(),
+                    project_folder.clone(),
+                    context,
+                );
+                self.findings.insert(check, Finding::Checking);
+            }
+        }
+        let configured_target = find_configured_target(&project_folder, host_fs);
+        self.findings.insert(
+            Check::BuildTarget,
+            build_target_finding(configured_target.as_ref()),
+        );
+        self.findings.insert(
+            Check::Artifact,
+            artifact_finding(
+                &plugin_paths,
+                target_directory.as_deref(),
+                configured_target
+                    .as_ref()
+                    .map(|(target, _)| target.as_str()),
+                host_fs,
+            ),
+        );
+    }
+    pub fn command_finished(
+        &mut self,
+        exit_code: Option<i32>,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        context: &BTreeMap<String, String>,
+    ) {
+        let check = match context.get("check").and_then(|c| Check::from_label(c)) {
+            Some(check) if context.get("run_id") == Some(&self.run_id.to_string()) => check,
+            _ => return,
+        };
+        let stdout = String::from_utf8_lossy(&stdout).trim().to_owned();
+        let stderr = String::from_utf8_lossy(&stderr).trim().to_owned();
+        let finding = match (check, exit_code) {
+            (Check::Cargo, Some(0)) | (Check::WasmOpt, Some(0)) => Finding::Ok(stdout),
+            (Check::Cargo, _) => Finding::Failed {
+                problem: format!("cargo can't be run: {}", stderr),
+                remedy: "Install Rust with rustup (https://rustup.rs), then start zellij from a \
+                         shell where cargo works, so that the commands it runs find it"
+                    .to_owned(),
+            },
+            (Check::InstalledTargets, Some(0)) => {
+                if stdout.lines().any(|l| l.trim() == PLUGIN_TARGET) {
+                    Finding::Ok(format!("{} is installed", PLUGIN_TARGET))
+                } else {
+                    Finding::Failed {
+                        problem: format!(
+                            "{} isn't installed (installed: {})",
+                            PLUGIN_TARGET,
+                            stdout.lines().collect::<Vec<&str>>().join(", ")
+                        ),
+                        remedy: format!("Run: rustup target add {}", PLUGIN_TARGET),
+                    }
+                }
+            }
+            (Check::InstalledTargets, _) => Finding::Warning {
+                problem: format!("the installed targets are unknown: {}", stderr),
+                remedy: format!(
+                    "Without rustup, make sure your toolchain has the standard library for {}",
+                    PLUGIN_TARGET
+                ),
+            },
+            (Check::WasmOpt, _) => Finding::Warning {
+                problem: "wasm-opt (from binaryen) can't be run".to_owned(),
+                remedy: "Install binaryen with your package manager (or cargo install wasm-opt) \
+                         to shrink the plugin before publishing it"
+                    .to_owned(),
+            },
+            (Check::BuildTarget, _) | (Check::Artifact, _) => return,
+        };
+        self.findings.insert(check, finding);
+    }
+    pub fn render(&self, y: usize, rows: usize, cols: usize) {
+        print_text_with_coordinates(
+            Text::new(truncate("Toolchain doctor", cols)).color_range(2, ..),
+            0,
+            y,
+            None,
+            None,
+        );
+        let mut lines = vec![];
+        if self.no_folder {
+            lines.push(Text::new(truncate("<NO FOLDER SELECTED>", cols)));
+        }
+        for (check, finding) in &self.findings {
+            let (marker, color, text, remedy) = match finding {
+                Finding::Checking => ("…", 0, "checking...", None),
+                Finding::Ok(text) => ("✓", 2, text.as_str(), None),
+                Finding::Warning { problem, remedy } => ("!", 1, problem.as_str(), Some(remedy)),
+                Finding::Failed { problem, remedy } => ("✗", 3, problem.as_str(), Some(remedy)),
+            };
+            let line = truncate(
+                &format!(
+                    "{} {:width$}{}",
+                    marker,
+                    check.label(),
+                    text,
+                    width = LABEL_WIDTH
+                ),
+                cols,
+            );
+            let label_length = std::cmp::min(LABEL_WIDTH + 2, line.chars().count());
+            lines.push(
+                Text::new(&line)
+                    .color_range(color, ..1)
+                    .color_range(0, 2..label_length),
+            );
+            if let Some(remedy) = remedy {
+                let line = truncate(
+                    &format!("{:width$}→ {}", "", remedy, width = LABEL_WIDTH + 2),
+                    cols,
+                );
+                lines.push(Text::new(line));
+            }
+        }
+        let available_rows = rows.saturating_sub(y + 3);
+        for (i, line) in lines.into_iter().take(available_rows).enumerate() {
+            print_text_with_coordinates(line, 0, y + 2 + i, None, None);
+        }
+        print_text_with_coordinates(
+            help_text("<r> check again, <Esc> back", cols),
+            0,
+            rows.saturating_sub(1),
+            None,
+            None,
+        );
+    }
+}
+
+// cargo reads .cargo/config.toml (or .cargo/config) in the project folder and every folder above
+// it, the closest one wins, returns the target and the file it's set in
+fn find_configured_target(project_folder: &Path, host_fs: &HostFs) -> Option<(String, PathBuf)> {
+    project_folder
+        .ancestors()
+        .flat_map(|folder| {
+            ["config.toml", "config"]
+                .iter()
+                .map(move |file_name| folder.join(".cargo").join(file_name))
+        })
+        .find_map(|config_path| {
+            let config = host_fs.read_to_string(&config_path).ok()?;
+            configured_target(&config).map(|target| (target, config_path))
+        })
+}
+
+// build.target, either under [build] or as a dotted key
+fn configured_target(config: &str) -> Option<String> {
+    let mut table = String::new();
+    for line in config.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.starts_with('[') {
+            table = line
+                .trim_matches(|c| c == '[' || c == ']')
+                .trim()
+                .to_owned();
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        let key = match table.as_str() {
+            "" => key.to_owned(),
+            table => format!("{}.{}", table, key),
+        };
+        if key == "build.target" {
+            return Some(value.trim_matches(|c| c == '"' || c == '\'').to_owned());
+        }
+    }
+    None
+}
+
+fn build_target_finding(configured_target: Option<&(String, PathBuf)>) -> Finding {
+    let remedy = |config_path: &Path| {
+        format!(
+            "Set target = \"{}\" under [build] in {}",
+            PLUGIN_TARGET,
+            config_path.display()
+        )
+    };
+    match configured_target {
+        Some((target, config_path)) if target == PLUGIN_TARGET => {
+            Finding::Ok(format!("{} (from {})", target, config_path.display()))
+        }
+        Some((target, config_path)) if target == "wasm32-wasi" => Finding::Failed {
+            problem: format!(
+                "wasm32-wasi (from {}) was renamed {} and is gone since Rust 1.84, and the \
+                 plugin is loaded from target/{}",
+                config_path.display(),
+                PLUGIN_TARGET,
+                PLUGIN_TARGET
+            ),
+            remedy: remedy(config_path),
+        },
+        Some((target, config_path)) => Finding::Failed {
+            problem: format!(
+                "{} (from {}) isn't where the plugin is loaded from (target/{})",
+                target,
+                config_path.display(),
+                PLUGIN_TARGET
+            ),
+            remedy: remedy(config_path),
+        },
+        None => Finding::Failed {
+            problem: "no build target is configured, so cargo builds for this machine".to_owned(),
+            remedy: format!(
+                "Add [build] and target = \"{}\" to .cargo/config.toml in the project folder",
+                PLUGIN_TARGET
+            ),
+        },
+    }
+}
+
+fn artifact_finding(
+    plugin_paths: &[PathBuf],
+    target_directory: Option<&Path>,
+    configured_target: Option<&str>,
+    host_fs: &HostFs,
+) -> Finding {
+    if plugin_paths.is_empty() {
+        return Finding::Warning {
+            problem: "there's no plugin to build".to_owned(),
+            remedy: "Choose the workspace members to build with <w>".to_owned(),
+        };
+    }
+    for plugin_path in plugin_paths {
+        match host_fs.exists(plugin_path) {
+            Ok(true) => {}
+            Ok(false) => {
+                // eg. target/wasm32-wasi/debug/my-plugin.wasm
+                let built_path =
+                    match (target_directory, configured_target, plugin_path.file_name()) {
+                        (Some(target_directory), Some(target), Some(file_name))
+                            if target != PLUGIN_TARGET =>
+                        {
+                            Some(target_directory.join(target).join("debug").join(file_name))
+                        }
+                        _ => None,
+                    };
+                return match built_path.filter(|p| host_fs.exists(p) == Ok(true)) {
+                    Some(built_path) => Finding::Failed {
+                        problem: format!(
+                            "built to {}, but loaded from {}",
+                            built_path.display(),
+                            plugin_path.display()
+                        ),
+                        remedy: "Build for the target the plugin is loaded from (see the build \
+                                 target above)"
+                            .to_owned(),
+                    },
+                    None => Finding::Warning {
+                        problem: format!("{} isn't built yet", plugin_path.display()),
+                        remedy: "Build the plugin, or set artifact_path if it's built somewhere \
+                                 else"
+                            .to_owned(),
+                    },
+                };
+            }
+            Err(e) => {
+                return Finding::Failed {
+                    problem: e,
+                    remedy: "Start zellij in a folder that contains the project, so that the \
+                             plugin can see what's built"
+                        .to_owned(),
+                }
+            }
+        }
+    }
+    let plugin_paths: Vec<String> = plugin_paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect();
+    Finding::Ok(plugin_paths.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_path() -> PathBuf {
+        PathBuf::from("/projects/my-plugin/.cargo/config.toml")
+    }
+
+    fn context(check: &str, run_id: u32) -> BTreeMap<String, String> {
+        let mut context = BTreeMap::new();
+        context.insert("check".to_owned(), check.to_owned());
+        context.insert("run_id".to_owned(), run_id.to_string());
+        context
+    }
+
+    #[test]
+    fn finds_the_build_target_in_the_cargo_config() {
+        let config = "# built for zellij\n[build]\ntarget = \"wasm32-wasi\" # the old name\n\n[env]\ntarget = \"x\"\n";
+        assert_eq!(configured_target(config), Some("wasm32-wasi".to_owned()));
+        assert_eq!(
+            configured_target("build.target = 'wasm32-wasip1'\n"),
+            Some("wasm32-wasip1".to_owned())
+        );
+    }
+
+    #[test]
+    fn target_keys_of_other_tables_arent_the_build_target() {
+        assert_eq!(
+            configured_target("[target.wasm32-wasip1]\nrunner = \"x\"\n"),
+            None
+        );
+        assert_eq!(configured_target("[env]\ntarget = \"x\"\n"), None);
+    }
+
+    #[test]
+    fn the_old_wasi_target_fails_with_a_remedy() {
+        assert!(matches!(
+            build_target_finding(Some(&("wasm32-wasi".to_owned(), config_path()))),
+            Finding::Failed { remedy, .. } if remedy == "Set target = \"wasm32-wasip1\" under [build] in /projects/my-plugin/.cargo/config.toml"
+        ));
+        assert!(matches!(build_target_finding(None), Finding::Failed { .. }));
+        assert_eq!(
+            build_target_finding(Some(&("wasm32-wasip1".to_owned(), config_path()))),
+            Finding::Ok("wasm32-wasip1 (from /projects/my-plugin/.cargo/config.toml)".to_owned())
+        );
+    }
+
+    #[test]
+    fn a_missing_rustup_target_fails_and_earlier_runs_are_ignored() {
+        let mut doctor = Doctor {
+            run_id: 2,
+            ..Default::default()
+        };
+        let installed = b"x86_64-unknown-linux-gnu\nwasm32-wasip1\n".to_vec();
+        doctor.command_finished(Some(0), installed, vec![], &context("rustup targets", 1));
+        assert!(doctor.findings.is_empty());
+        let installed = b"x86_64-unknown-linux-gnu\n".to_vec();
+        doctor.command_finished(Some(0), installed, vec![], &context("rustup targets", 2));
+        assert_eq!(
+            doctor.findings.get(&Check::InstalledTargets),
+            Some(&Finding::Failed {
+                problem: "wasm32-wasip1 isn't installed (installed: x86_64-unknown-linux-gnu)"
+                    .to_owned(),
+                remedy: "Run: rustup target add wasm32-wasip1".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn an_artifact_outside_the_host_folder_cant_be_checked() {
+        let mut host_fs = HostFs::default();
+        host_fs.update_root(PathBuf::from("/projects"));
+        let plugin_path = PathBuf::from("/elsewhere/target/wasm32-wasip1/debug/p.wasm");
+        assert!(matches!(
+            artifact_finding(&[plugin_path], None, None, &host_fs),
+            Finding::Failed { problem, .. } if problem.contains("is outside of the folder zellij was started in")
+        ));
+        assert!(matches!(
+            artifact_finding(&[], None, None, &host_fs),
+            Finding::Warning { .. }
+        ));
+    }
+}
@@ -0,0 +1,351 @@
+use zellij_tile::prelude::*;
+
+use crate::host::{Host, ZellijHost};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+// commands run (with sh -c, in the project folder) before building and after reloading, and a
+// pipe message sent to the plugin after reloading
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookConfig {
+    pub pre_build_command: Option<String>,
+    pub post_reload_command: Option<String>,
+    pub post_reload_pipe: Option<String>,
+    pub post_reload_pipe_payload: Option<String>,
+}
+
+impl HookConfig {
+    pub fn from_configuration(configuration: &BTreeMap<String, String>) -> Self {
+        let get = |key: &str| {
+            configuration
+                .get(key)
+                .map(|v| v.trim().to_owned())
+                .filter(|v| !v.is_empty())
+        };
+        HookConfig {
+            pre_build_command: get("pre_build_command"),
+            post_reload_command: get("post_reload_command"),
+            post_reload_pipe: get("post_reload_pipe"),
+            post_reload_pipe_payload: configuration.get("post_reload_pipe_payload").cloned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HookKind {
+    PreBuild,
+    PostReload,
+}
+
+impl HookKind {
+    pub fn from_context(context: &BTreeMap<String, String>) -> Option<Self> {
+        match context.get("hook").map(|h| h.as_str()) {
+            Some("pre_build") => Some(HookKind::PreBuild),
+            Some("post_reload") => Some(HookKind::PostReload),
+            _ => None,
+        }
+    }
+    fn context_name(&self) -> &'static str {
+        match self {
+            HookKind::PreBuild => "pre_build",
+            HookKind::PostReload => "post_reload",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HookStatus {
+    #[default]
+    NotRun,
+    Running,
+    Succeeded,
+    Failed(Option<i32>), // the exit code, if the command wasn't interrupted
+}
+
+impl fmt::Display for HookStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HookStatus::NotRun => write!(f, "not run yet"),
+            HookStatus::Running => write!(f, "running..."),
+            HookStatus::Succeeded => write!(f, "succeeded"),
+            HookStatus::Failed(Some(exit_code)) => write!(f, "failed (exit code {})", exit_code),
+            HookStatus::Failed(None) => write!(f, "failed"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HookState {
+    pane_id: Option<u32>,
+    opened_with: Option<(String, Option<PathBuf>)>, // the command and cwd of the pane
+    status: HookStatus,
+    in_background: bool, // while a quiet build waits for it, we only show its pane if it fails
+}
+
+#[derive(Debug, Default)]
+pub struct Hooks<H: Host = ZellijHost> {
+    config: HookConfig,
+    pre_build: HookState,
+    post_reload: HookState,
+    post_reload_pipes_sent: usize,
+    host: H,
+}
+
+impl<H: Host> Hooks<H> {
+    pub fn update_config(&mut self, config: HookConfig) {
+        self.config = config;
+    }
+    pub fn get_config(&self) -> &HookConfig {
+        &self.config
+    }
+    pub fn host_mut(&mut self) -> &mut H {
+        &mut self.host
+    }
+    pub fn get_status(&self, hook: HookKind) -> HookStatus {
+        self.state(hook).status
+    }
+    pub fn get_post_reload_pipes_sent(&self) -> usize {
+        self.post_reload_pipes_sent
+    }
+    pub fn has_pre_build(&self) -> bool {
+        self.config.pre_build_command.is_some()
+    }
+    // before a quiet build, the hook runs in the background too
+    pub fn run_pre_build(&mut self, cwd: Option<PathBuf>, quiet: bool) {
+        if let Some(command) = self.config.pre_build_command.clone() {
+            self.run(HookKind::PreBuild, command, cwd, quiet);
+            if !quiet {
+                self.host.hide_self();
+            }
+        }
+    }
+    // plugin_urls are the plugins that were just reloaded
+    pub fn run_post_reload(&mut self, cwd: Option<PathBuf>, plugin_urls: &[String]) {
+        if let Some(command) = self.config.post_reload_command.clone() {
+            self.run(HookKind::PostReload, command, cwd, false);
+        }
+        if let Some(name) = self.config.post_reload_pipe.clone() {
+            for plugin_url in plugin_urls {
+                let mut message_to_plugin = MessageToPlugin::new(&name).with_plugin_url(plugin_url);
+                if let Some(payload) = &self.config.post_reload_pipe_payload {
+                    message_to_plugin = message_to_plugin.with_payload(payload);
+                }
+                self.host.pipe_message_to_plugin(message_to_plugin);
+                self.post_reload_pipes_sent += 1;
+            }
+        }
+    }
+    pub fn update_pane_id(&mut self, hook: HookKind, terminal_pane_id: u32) {
+        self.state_mut(hook).pane_id = Some(terminal_pane_id);
+    }
+    pub fn hook_of_pane(&self, terminal_pane_id: u32) -> Option<HookKind> {
+        [HookKind::PreBuild, HookKind::PostReload]
+            .iter()
+            .find(|hook| self.state(**hook).pane_id == Some(terminal_pane_id))
+            .copied()
+    }
+    // returns the hook and whether it succeeded, the pane of a failed hook is left open
+    pub fn pane_exited(
+        &mut self,
+        terminal_pane_id: u32,
+        exit_code: Option<i32>,
+    ) -> Option<(HookKind, bool)> {
+        let hook = self.hook_of_pane(terminal_pane_id)?;
+        let succeeded = exit_code == Some(0);
+        if succeeded {
+            self.state_mut(hook).status = HookStatus::Succeeded;
+            self.host
+                .hide_pane_with_id(PaneId::Terminal(terminal_pane_id));
+        } else {
+            self.state_mut(hook).status = HookStatus::Failed(exit_code);
+            if self.state(hook).in_background {
+                self.host
+                    .show_pane_with_id(PaneId::Terminal(terminal_pane_id), true);
+            }
+        }
+        Some((hook, succeeded))
+    }
+    pub fn pane_closed(&mut self, pane_id: PaneId) {
+        if let PaneId::Terminal(terminal_pane_id) = pane_id {
+            if let Some(hook) = self.hook_of_pane(terminal_pane_id) {
+                self.state_mut(hook).pane_id = None;
+            }
+        }
+    }
+    // like the compilation pane, each hook has a pane of its own that we rerun, as long as it
+    // would run the same command in the same folder
+    fn run(&mut self, hook: HookKind, command: String, cwd: Option<PathBuf>, in_background: bool) {
+        let opened_with = Some((command.clone(), cwd.clone()));
+        let state = self.state_mut(hook);
+        state.status = HookStatus::Running;
+        state.in_background = in_background;
+        if state.opened_with != opened_with {
+            state.opened_with = opened_with;
+            if let Some(pane_id) = state.pane_id.take() {
+                self.host.close_terminal_pane(pane_id);
+            }
+        }
+        if let Some(pane_id) = self.state(hook).pane_id {
+            if !in_background {
+                self.host.show_pane_with_id(PaneId::Terminal(pane_id), true);
+            }
+            self.host.rerun_command_pane(pane_id);
+        } else {
+            let mut command_to_run = CommandToRun::new_with_args("sh", vec!["-c", &command]);
+            command_to_run.cwd = cwd;
+            let mut context = BTreeMap::new();
+            context.insert("source".to_owned(), "hook".to_owned());
+            context.insert("hook".to_owned(), hook.context_name().to_owned());
+            if in_background {
+                self.host
+                    .open_command_pane_background(command_to_run, context);
+            } else {
+                self.host
+                    .open_command_pane_floating(command_to_run, context);
+            }
+        }
+    }
+    fn state(&self, hook: HookKind) -> &HookState {
+        match hook {
+            HookKind::PreBuild => &self.pre_build,
+            HookKind::PostReload => &self.post_reload,
+        }
+    }
+    fn state_mut(&mut self, hook: HookKind) -> &mut HookState {
+        match hook {
+            HookKind::PreBuild => &mut self.pre_build,
+            HookKind::PostReload => &mut self.post_reload,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostEffect, RecordingHost};
+
+    fn hooks() -> Hooks<RecordingHost> {
+        let mut hooks = Hooks::<RecordingHost>::default();
+        hooks.update_config(HookConfig {
+            pre_build_command: Some("buf generate".to_owned()),
+            post_reload_command: Some("./seed.sh".to_owned()),
+            post_reload_pipe: Some("init".to_owned()),
+            post_reload_pipe_payload: None,
+        });
+        hooks
+    }
+
+    #[test]
+    fn pre_build_hook_runs_in_its_own_pane() {
+        let mut hooks = hooks();
+        hooks.run_pre_build(Some(PathBuf::from("/projects/my-plugin")), false);
+        let mut context = BTreeMap::new();
+        context.insert("source".to_owned(), "hook".to_owned());
+        context.insert("hook".to_owned(), "pre_build".to_owned());
+        assert_eq!(
+            hooks.host.take_effects(),
+            vec![
+                HostEffect::OpenCommandPaneFloating {
+                    command: "sh".to_owned(),
+                    args: vec!["-c".to_owned(), "buf generate".to_owned()],
+                    cwd: Some(PathBuf::from("/projects/my-plugin")),
+                    context,
+                },
+                HostEffect::HideSelf,
+            ]
+        );
+        assert_eq!(hooks.get_status(HookKind::PreBuild), HookStatus::Running);
+        hooks.update_pane_id(HookKind::PreBuild, 4);
+        assert_eq!(
+            hooks.pane_exited(4, Some(1)),
+            Some((HookKind::PreBuild, false))
+        );
+        assert!(hooks.host.take_effects().is_empty());
+        assert_eq!(
+            hooks.get_status(HookKind::PreBuild),
+            HookStatus::Failed(Some(1))
+        );
+        hooks.run_pre_build(Some(PathBuf::from("/projects/my-plugin")), false);
+        assert_eq!(
+            hooks.host.take_effects(),
+            vec![
+                HostEffect::ShowPaneWithId(PaneId::Terminal(4), true),
+                HostEffect::RerunCommandPane(4),
+                HostEffect::HideSelf,
+            ]
+        );
+        assert_eq!(
+            hooks.pane_exited(4, Some(0)),
+            Some((HookKind::PreBuild, true))
+        );
+        assert_eq!(
+            hooks.host.take_effects(),
+            vec![HostEffect::HidePaneWithId(PaneId::Terminal(4))]
+        );
+    }
+
+    #[test]
+    fn a_hook_run_in_another_folder_gets_a_new_pane() {
+        let mut hooks = hooks();
+        hooks.run_pre_build(Some(PathBuf::from("/projects/my-plugin")), false);
+        hooks.update_pane_id(HookKind::PreBuild, 4);
+        hooks.host.take_effects();
+        hooks.run_pre_build(Some(PathBuf::from("/projects/other-plugin")), false);
+        let effects = hooks.host.take_effects();
+        assert_eq!(effects[0], HostEffect::CloseTerminalPane(4));
+        assert!(matches!(
+            &effects[1],
+            HostEffect::OpenCommandPaneFloating { cwd, .. }
+                if cwd.as_deref() == Some(std::path::Path::new("/projects/other-plugin"))
+        ));
+        assert_eq!(hooks.hook_of_pane(4), None);
+    }
+
+    #[test]
+    fn post_reload_hook_pipes_to_every_reloaded_plugin() {
+        let mut hooks = hooks();
+        hooks.run_post_reload(
+            None,
+            &["file:/a.wasm".to_owned(), "file:/b.wasm".to_owned()],
+        );
+        let effects = hooks.host.take_effects();
+        assert!(matches!(
+            effects[0],
+            HostEffect::OpenCommandPaneFloating { .. }
+        ));
+        assert_eq!(
+            effects[1..],
+            [
+                HostEffect::PipeMessageToPlugin {
+                    plugin_url: "file:/a.wasm".to_owned(),
+                    name: "init".to_owned(),
+                    payload: None,
+                    args: BTreeMap::new(),
+                    plugin_config: BTreeMap::new(),
+                    pane_title: None,
+                },
+                HostEffect::PipeMessageToPlugin {
+                    plugin_url: "file:/b.wasm".to_owned(),
+                    name: "init".to_owned(),
+                    payload: None,
+                    args: BTreeMap::new(),
+                    plugin_config: BTreeMap::new(),
+                    pane_title: None,
+                },
+            ]
+        );
+        assert_eq!(hooks.get_post_reload_pipes_sent(), 2);
+    }
+
+    #[test]
+    fn other_panes_are_not_hooks() {
+        let mut hooks = hooks();
+        hooks.update_pane_id(HookKind::PostReload, 7);
+        assert_eq!(hooks.pane_exited(3, Some(0)), None);
+        hooks.pane_closed(PaneId::Terminal(7));
+        assert_eq!(hooks.hook_of_pane(7), None);
+    }
+}
@@ -0,0 +1,495 @@
+use zellij_tile::prelude::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// the zellij host calls our workspace logic makes, so that it can be tested without zellij
+pub trait Host {
+    fn request_permission(&mut self, permissions: &[PermissionType]);
+    fn subscribe(&mut self, event_types: &[EventType]);
+    fn get_plugin_ids(&mut self) -> PluginIds;
+    fn open_command_pane(&mut self, command: CommandToRun, context: BTreeMap<String, String>);
+    fn open_command_pane_floating(
+        &mut self,
+        command: CommandToRun,
+        context: BTreeMap<String, String>,
+    );
+    fn open_command_pane_background(
+        &mut self,
+        command: CommandToRun,
+        context: BTreeMap<String, String>,
+    );
+    fn open_file_floating(&mut self, file_to_open: FileToOpen, context: BTreeMap<String, String>);
+    fn rerun_command_pane(&mut self, terminal_pane_id: u32);
+    fn close_terminal_pane(&mut self, terminal_pane_id: u32);
+    fn show_pane_with_id(&mut self, pane_id: PaneId, should_float_if_hidden: bool);
+    fn focus_pane_with_id(&mut self, pane_id: PaneId, should_float_if_hidden: bool);
+    fn toggle_pane_embed_or_eject_for_pane_id(&mut self, pane_id: PaneId);
+    fn resize_pane_with_id(&mut self, resize_strategy: ResizeStrategy, pane_id: PaneId);
+    fn hide_pane_with_id(&mut self, pane_id: PaneId);
+    fn hide_self(&mut self);
+    fn close_self(&mut self);
+    fn start_or_reload_plugin(&mut self, url: &str);
+    fn rename_pane_with_id(&mut self, pane_id: PaneId, new_name: String);
+    fn reconfigure(&mut self, new_config: String, save_configuration_file: bool);
+    fn pipe_message_to_plugin(&mut self, message_to_plugin: MessageToPlugin);
+    fn run_command(
+        &mut self,
+        command: Vec<String>,
+        env: BTreeMap<String, String>,
+        cwd: PathBuf,
+        context: BTreeMap<String, String>,
+    );
+    fn set_timeout(&mut self, secs: f64);
+    fn watch_filesystem(&mut self);
+    fn post_message_to_worker(&mut self, worker_name: &str, name: &str, payload: String);
+    fn post_message_to_plugin(&mut self, name: &str, payload: String);
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ZellijHost;
+
+impl Host for ZellijHost {
+    fn request_permission(&mut self, permissions: &[PermissionType]) {
+        request_permission(permissions);
+    }
+    fn subscribe(&mut self, event_types: &[EventType]) {
+        subscribe(event_types);
+    }
+    fn get_plugin_ids(&mut self) -> PluginIds {
+        get_plugin_ids()
+    }
+    fn open_command_pane(&mut self, command: CommandToRun, context: BTreeMap<String, String>) {
+        open_command_pane(command, context);
+    }
+    fn open_command_pane_floating(
+        &mut self,
+        command: CommandToRun,
+        context: BTreeMap<String, String>,
+    ) {
+        open_command_pane_floating(command, None, context);
+    }
+    fn open_command_pane_background(
+        &mut self,
+        command: CommandToRun,
+        context: BTreeMap<String, String>,
+    ) {
+        open_command_pane_background(command, context);
+    }
+    fn open_file_floating(&mut self, file_to_open: FileToOpen, context: BTreeMap<String, String>) {
+        open_file_floating(file_to_open, None, context);
+    }
+    fn rerun_command_pane(&mut self, terminal_pane_id: u32) {
+        rerun_command_pane(terminal_pane_id);
+    }
+    fn close_terminal_pane(&mut self, terminal_pane_id: u32) {
+        close_terminal_pane(terminal_pane_id);
+    }
+    fn show_pane_with_id(&mut self, pane_id: PaneId, should_float_if_hidden: bool) {
+        show_pane_with_id(pane_id, should_float_if_hidden);
+    }
+    fn focus_pane_with_id(&mut self, pane_id: PaneId, should_float_if_hidden: bool) {
+        focus_pane_with_id(pane_id, should_float_if_hidden);
+    }
+    fn toggle_pane_embed_or_eject_for_pane_id(&mut self, pane_id: PaneId) {
+        toggle_pane_embed_or_eject_for_pane_id(pane_id);
+    }
+    fn resize_pane_with_id(&mut self, resize_strategy: ResizeStrategy, pane_id: PaneId) {
+        resize_pane_with_id(resize_strategy, pane_id);
+    }
+    fn hide_pane_with_id(&mut self, pane_id: PaneId) {
+        hide_pane_with_id(pane_id);
+    }
+    fn hide_self(&mut self) {
+        hide_self();
+    }
+    fn close_self(&mut self) {
+        close_self();
+    }
+    fn start_or_reload_plugin(&mut self, url: &str) {
+        start_or_reload_plugin(url);
+    }
+    fn rename_pane_with_id(&mut self, pane_id: PaneId, new_name: String) {
+        rename_pane_with_id(pane_id, new_name);
+    }
+    fn reconfigure(&mut self, new_config: String, save_configuration_file: bool) {
+        reconfigure(new_config, save_configuration_file);
+    }
+    fn pipe_message_to_plugin(&mut self, message_to_plugin: MessageToPlugin) {
+        pipe_message_to_plugin(message_to_plugin);
+    }
+    fn run_command(
+        &mut self,
+        command: Vec<String>,
+        env: BTreeMap<String, String>,
+        cwd: PathBuf,
+        context: BTreeMap<String, String>,
+    ) {
+        let command: Vec<&str> = command.iter().map(|c| c.as_str()).collect();
+        run_command_with_env_variables_and_cwd(&command, env, cwd, context);
+    }
+    fn set_timeout(&mut self, secs: f64) {
+        set_timeout(secs);
+    }
+    fn watch_filesystem(&mut self) {
+        watch_filesystem();
+    }
+    fn post_message_to_worker(&mut self, worker_name: &str, name: &str, payload: String) {
+        post_message_to(PluginMessage::new_to_worker(worker_name, name, &payload));
+    }
+    fn post_message_to_plugin(&mut self, name: &str, payload: String) {
+        post_message_to_plugin(PluginMessage::new_to_plugin(name, &payload));
+    }
+}
+
+// a host call, as data, so that it can be returned from the workspace reducer, recorded in
+// transcripts and compared in tests
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HostEffect {
+    RequestPermission(Vec<PermissionType>),
+    Subscribe(Vec<EventType>),
+    OpenCommandPane {
+        command: String,
+        args: Vec<String>,
+        cwd: Option<PathBuf>,
+        context: BTreeMap<String, String>,
+    },
+    OpenCommandPaneFloating {
+        command: String,
+        args: Vec<String>,
+        cwd: Option<PathBuf>,
+        context: BTreeMap<String, String>,
+    },
+    OpenCommandPaneBackground {
+        command: String,
+        args: Vec<String>,
+        cwd: Option<PathBuf>,
+        context: BTreeMap<String, String>,
+    },
+    OpenFileFloating {
+        path: PathBuf,
+        line_number: Option<usize>,
+        cwd: Option<PathBuf>,
+        context: BTreeMap<String, String>,
+    },
+    RerunCommandPane(u32),
+    CloseTerminalPane(u32),
+    ShowPaneWithId(PaneId, bool),
+    FocusPaneWithId(PaneId, bool),
+    TogglePaneEmbedOrEjectForPaneId(PaneId),
+    ResizePaneWithId(ResizeStrategy, PaneId),
+    HidePaneWithId(PaneId),
+    HideSelf,
+    CloseSelf,
+    StartOrReloadPlugin(String),
+    RenamePaneWithId(PaneId, String),
+    Reconfigure(String, bool),
+    PipeMessageToPlugin {
+        plugin_url: String,
+        name: String,
+        payload: Option<String>,
+        #[serde(default)]
+        args: BTreeMap<String, String>,
+        // the configuration and pane title of the plugin if the message launches it
+        #[serde(default)]
+        plugin_config: BTreeMap<String, String>,
+        #[serde(default)]
+        pane_title: Option<String>,
+    },
+    RunCommand {
+        command: Vec<String>,
+        env: BTreeMap<String, String>,
+        cwd: PathBuf,
+        context: BTreeMap<String, String>,
+    },
+    SetTimeout(f64),
+    WatchFilesystem,
+    PostMessageToWorker {
+        worker_name: String,
+        name: String,
+        payload: String,
+    },
+    PostMessageToPlugin {
+        name: String,
+        payload: String,
+    },
+}
+
+impl HostEffect {
+    pub fn apply(self, host: &mut impl Host) {
+        match self {
+            HostEffect::RequestPermission(permissions) => host.request_permission(&permissions),
+            HostEffect::Subscribe(event_types) => host.subscribe(&event_types),
+            HostEffect::OpenCommandPane {
+                command,
+                args,
+                cwd,
+                context,
+            } => {
+                let mut command = CommandToRun::new_with_args(command, args);
+                command.cwd = cwd;
+                host.open_command_pane(command, context);
+            }
+            HostEffect::OpenCommandPaneFloating {
+                command,
+                args,
+                cwd,
+                context,
+            } => {
+                let mut command = CommandToRun::new_with_args(command, args);
+                command.cwd = cwd;
+                host.open_command_pane_floating(command, context);
+            }
+            HostEffect::OpenCommandPaneBackground {
+                command,
+                args,
+                cwd,
+                context,
+            } => {
+                let mut command = CommandToRun::new_with_args(command, args);
+                command.cwd = cwd;
+                host.open_command_pane_background(command, context);
+            }
+            HostEffect::OpenFileFloating {
+                path,
+                line_number,
+                cwd,
+                context,
+            } => host.open_file_floating(
+                FileToOpen {
+                    path,
+                    line_number,
+                    cwd,
+                },
+                context,
+            ),
+            HostEffect::RerunCommandPane(terminal_pane_id) => {
+                host.rerun_command_pane(terminal_pane_id)
+            }
+            HostEffect::CloseTerminalPane(terminal_pane_id) => {
+                host.close_terminal_pane(terminal_pane_id)
+            }
+            HostEffect::ShowPaneWithId(pane_id, should_float_if_hidden) => {
+                host.show_pane_with_id(pane_id, should_float_if_hidden)
+            }
+            HostEffect::FocusPaneWithId(pane_id, should_float_if_hidden) => {
+                host.focus_pane_with_id(pane_id, should_float_if_hidden)
+            }
+            HostEffect::TogglePaneEmbedOrEjectForPaneId(pane_id) => {
+                host.toggle_pane_embed_or_eject_for_pane_id(pane_id)
+            }
+            HostEffect::ResizePaneWithId(resize_strategy, pane_id) => {
+                host.resize_pane_with_id(resize_strategy, pane_id)
+            }
+            HostEffect::HidePaneWithId(pane_id) => host.hide_pane_with_id(pane_id),
+            HostEffect::HideSelf => host.hide_self(),
+            HostEffect::CloseSelf => host.close_self(),
+            HostEffect::StartOrReloadPlugin(url) => host.start_or_reload_plugin(&url),
+            HostEffect::RenamePaneWithId(pane_id, new_name) => {
+                host.rename_pane_with_id(pane_id, new_name)
+            }
+            HostEffect::Reconfigure(new_config, save_configuration_file) => {
+                host.reconfigure(new_config, save_configuration_file)
+            }
+            HostEffect::PipeMessageToPlugin {
+                plugin_url,
+                name,
+                payload,
+                args,
+                plugin_config,
+                pane_title,
+            } => {
+                let mut message_to_plugin = MessageToPlugin::new(name)
+                    .with_plugin_url(plugin_url)
+                    .with_args(args)
+                    .with_plugin_config(plugin_config);
+                if let Some(payload) = payload {
+                    message_to_plugin = message_to_plugin.with_payload(payload);
+                }
+                if let Some(pane_title) = pane_title {
+                    message_to_plugin =
+                        message_to_plugin.new_plugin_instance_should_have_pane_title(pane_title);
+                }
+                host.pipe_message_to_plugin(message_to_plugin);
+            }
+            HostEffect::RunCommand {
+                command,
+                env,
+                cwd,
+                context,
+            } => host.run_command(command, env, cwd, context),
+            HostEffect::SetTimeout(secs) => host.set_timeout(secs),
+            HostEffect::WatchFilesystem => host.watch_filesystem(),
+            HostEffect::PostMessageToWorker {
+                worker_name,
+                name,
+                payload,
+            } => host.post_message_to_worker(&worker_name, &name, payload),
+            HostEffect::PostMessageToPlugin { name, payload } => {
+                host.post_message_to_plugin(&name, payload)
+            }
+        }
+    }
+}
+
+// records the calls as effects instead of making them
+#[derive(Debug, Default)]
+pub struct RecordingHost {
+    effects: Vec<HostEffect>,
+    pub plugin_ids: Option<PluginIds>, // what get_plugin_ids returns
+}
+
+impl RecordingHost {
+    pub fn take_effects(&mut self) -> Vec<HostEffect> {
+        std::mem::take(&mut self.effects)
+    }
+}
+
+impl Host for RecordingHost {
+    fn request_permission(&mut self, permissions: &[PermissionType]) {
+        self.effects
+            .push(HostEffect::RequestPermission(permissions.to_vec()));
+    }
+    fn subscribe(&mut self, event_types: &[EventType]) {
+        self.effects
+            .push(HostEffect::Subscribe(event_types.to_vec()));
+    }
+    fn get_plugin_ids(&mut self) -> PluginIds {
+        self.plugin_ids.clone().unwrap_or(PluginIds {
+            plugin_id: 0,
+            zellij_pid: 0,
+            initial_cwd: PathBuf::new(),
+        })
+    }
+    fn open_command_pane(&mut self, command: CommandToRun, context: BTreeMap<String, String>) {
+        self.effects.push(HostEffect::OpenCommandPane {
+            command: command.path.display().to_string(),
+            args: command.args,
+            cwd: command.cwd,
+            context,
+        });
+    }
+    fn open_command_pane_floating(
+        &mut self,
+        command: CommandToRun,
+        context: BTreeMap<String, String>,
+    ) {
+        self.effects.push(HostEffect::OpenCommandPaneFloating {
+            command: command.path.display().to_string(),
+            args: command.args,
+            cwd: command.cwd,
+            context,
+        });
+    }
+    fn open_command_pane_background(
+        &mut self,
+        command: CommandToRun,
+        context: BTreeMap<String, String>,
+    ) {
+        self.effects.push(HostEffect::OpenCommandPaneBackground {
+            command: command.path.display().to_string(),
+            args: command.args,
+            cwd: command.cwd,
+            context,
+        });
+    }
+    fn open_file_floating(&mut self, file_to_open: FileToOpen, context: BTreeMap<String, String>) {
+        self.effects.push(HostEffect::OpenFileFloating {
+            path: file_to_open.path,
+            line_number: file_to_open.line_number,
+            cwd: file_to_open.cwd,
+            context,
+        });
+    }
+    fn rerun_command_pane(&mut self, terminal_pane_id: u32) {
+        self.effects
+            .push(HostEffect::RerunCommandPane(terminal_pane_id));
+    }
+    fn close_terminal_pane(&mut self, terminal_pane_id: u32) {
+        self.effects
+            .push(HostEffect::CloseTerminalPane(terminal_pane_id));
+    }
+    fn show_pane_with_id(&mut self, pane_id: PaneId, should_float_if_hidden: bool) {
+        self.effects
+            .push(HostEffect::ShowPaneWithId(pane_id, should_float_if_hidden));
+    }
+    fn focus_pane_with_id(&mut self, pane_id: PaneId, should_float_if_hidden: bool) {
+        self.effects
+            .push(HostEffect::FocusPaneWithId(pane_id, should_float_if_hidden));
+    }
+    fn toggle_pane_embed_or_eject_for_pane_id(&mut self, pane_id: PaneId) {
+        self.effects
+            .push(HostEffect::TogglePaneEmbedOrEjectForPaneId(pane_id));
+    }
+    fn resize_pane_with_id(&mut self, resize_strategy: ResizeStrategy, pane_id: PaneId) {
+        self.effects
+            .push(HostEffect::ResizePaneWithId(resize_strategy, pane_id));
+    }
+    fn hide_pane_with_id(&mut self, pane_id: PaneId) {
+        self.effects.push(HostEffect::HidePaneWithId(pane_id));
+    }
+    fn hide_self(&mut self) {
+        self.effects.push(HostEffect::HideSelf);
+    }
+    fn close_self(&mut self) {
+        self.effects.push(HostEffect::CloseSelf);
+    }
+    fn start_or_reload_plugin(&mut self, url: &str) {
+        self.effects
+            .push(HostEffect::StartOrReloadPlugin(url.to_owned()));
+    }
+    fn rename_pane_with_id(&mut self, pane_id: PaneId, new_name: String) {
+        self.effects
+            .push(HostEffect::RenamePaneWithId(pane_id, new_name));
+    }
+    fn reconfigure(&mut self, new_config: String, save_configuration_file: bool) {
+        self.effects
+            .push(HostEffect::Reconfigure(new_config, save_configuration_file));
+    }
+    fn pipe_message_to_plugin(&mut self, message_to_plugin: MessageToPlugin) {
+        self.effects.push(HostEffect::PipeMessageToPlugin {
+            plugin_url: message_to_plugin.plugin_url.unwrap_or_default(),
+            name: message_to_plugin.message_name,
+            payload: message_to_plugin.message_payload,
+            args: message_to_plugin.message_args,
+            plugin_config: message_to_plugin.plugin_config,
+            pane_title: message_to_plugin
+                .new_plugin_args
+                .and_then(|new_plugin_args| new_plugin_args.pane_title),
+        });
+    }
+    fn run_command(
+        &mut self,
+        command: Vec<String>,
+        env: BTreeMap<String, String>,
+        cwd: PathBuf,
+        context: BTreeMap<String, String>,
+    ) {
+        self.effects.push(HostEffect::RunCommand {
+            command,
+            env,
+            cwd,
+            context,
+        });
+    }
+    fn set_timeout(&mut self, secs: f64) {
+        self.effects.push(HostEffect::SetTimeout(secs));
+    }
+    fn watch_filesystem(&mut self) {
+        self.effects.push(HostEffect::WatchFilesystem);
+    }
+    fn post_message_to_worker(&mut self, worker_name: &str, name: &str, payload: String) {
+        self.effects.push(HostEffect::PostMessageToWorker {
+            worker_name: worker_name.to_owned(),
+            name: name.to_owned(),
+            payload,
+        });
+    }
+    fn post_message_to_plugin(&mut self, name: &str, payload: String) {
+        self.effects.push(HostEffect::PostMessageToPlugin {
+            name: name.to_owned(),
+            payload,
+        });
+    }
+}
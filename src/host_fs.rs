@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const HOST_FOLDER: &str = "/host";
+
+// zellij mounts the folder the plugin was started in at /host, so we can only read and write
+// files on the host if they're under that folder
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostFs {
+    root: Option<PathBuf>,
+}
+
+impl HostFs {
+    pub fn update_root(&mut self, root: PathBuf) {
+        self.root = Some(root);
+    }
+    pub fn get_root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+    pub fn mounted_path(&self, host_path: &Path) -> Result<PathBuf, String> {
+        let root = self
+            .root
+            .as_ref()
+            .ok_or_else(|| "the host folder is unknown".to_owned())?;
+        let relative_path = host_path.strip_prefix(root).map_err(|_| {
+            format!(
+                "{} is outside of the folder zellij was started in ({})",
+                host_path.display(),
+                root.display()
+            )
+        })?;
+        Ok(PathBuf::from(HOST_FOLDER).join(relative_path))
+    }
+    pub fn exists(&self, host_path: &Path) -> Result<bool, String> {
+        Ok(self.mounted_path(host_path)?.exists())
+    }
+    pub fn read_to_string(&self, host_path: &Path) -> Result<String, String> {
+        let mounted_path = self.mounted_path(host_path)?;
+        std::fs::read_to_string(&mounted_path)
+            .map_err(|e| format!("Failed to read {}: {}", host_path.display(), e))
+    }
+    // returns what was appended to a file since we read the first `offset` bytes of it, and where
+    // that starts, which is the beginning of the file if it was truncated in the meantime
+    pub fn read_appended(&self, host_path: &Path, offset: u64) -> Result<(u64, Vec<u8>), String> {
+        let mounted_path = self.mounted_path(host_path)?;
+        let read_error =
+            |e: std::io::Error| format!("Failed to read {}: {}", host_path.display(), e);
+        let mut file = File::open(&mounted_path).map_err(read_error)?;
+        let length = file.metadata().map_err(read_error)?.len();
+        let offset = if length < offset { 0 } else { offset };
+        file.seek(SeekFrom::Start(offset)).map_err(read_error)?;
+        let mut appended = vec![];
+        file.read_to_end(&mut appended).map_err(read_error)?;
+        Ok((offset, appended))
+    }
+    pub fn write(&self, host_path: &Path, contents: &str) -> Result<(), String> {
+        let mounted_path = self.mounted_path(host_path)?;
+        if let Some(parent) = mounted_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&mounted_path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", host_path.display(), e))
+    }
+    pub fn remove_file(&self, host_path: &Path) -> Result<(), String> {
+        let mounted_path = self.mounted_path(host_path)?;
+        std::fs::remove_file(&mounted_path)
+            .map_err(|e| format!("Failed to remove {}: {}", host_path.display(), e))
+    }
+}
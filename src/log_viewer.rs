@@ -0,0 +1,362 @@
+use zellij_tile::prelude::*;
+
+use crate::deadline::Deadline;
+use crate::host::Host;
+use crate::ui::{help_text, truncate};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const MAX_LINES: usize = 5000;
+const INITIAL_READ_BYTES: usize = 256 * 1024;
+const POLL_INTERVAL_SECS: f64 = 1.0;
+// this is where zellij keeps its log, see ZELLIJ_TMP_LOG_FILE in zellij-utils
+const DEFAULT_LOG_PATH: &str = "\"${TMPDIR:-/tmp}/zellij-$(id -u)/zellij-log/zellij.log\"";
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    text: String,
+    plugin_id: Option<u32>,
+}
+
+impl LogLine {
+    pub fn new(text: String) -> Self {
+        // plugin STDERR lines look like:
+        // INFO   |file:/path/to/plugin.wa| 2024-11-07 17:26:47.123 [id: 3     ] message
+        let plugin_id = text.find("[id: ").and_then(|start| {
+            text[start + 5..]
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse()
+                .ok()
+        });
+        LogLine { text, plugin_id }
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn has_level_prefix(&self) -> bool {
+        ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"]
+            .iter()
+            .any(|level| self.text.starts_with(level))
+    }
+    // a plugin's panic, the host's own errors aren't
+    pub fn is_panic(&self) -> bool {
+        self.plugin_id.is_some() && self.text.contains("panicked at")
+    }
+    pub fn message(&self) -> &str {
+        if self.plugin_id.is_some() {
+            if let Some(start) = self.text.find("[id: ") {
+                if let Some(end) = self.text[start..].find("] ") {
+                    return self.text[start + end + 2..].trim_end();
+                }
+            }
+        }
+        self.text.trim_end()
+    }
+    // by the plugin's id, zellij cuts the plugin's url in the prefix short, so a longer file name
+    // wouldn't be in it
+    pub fn belongs_to(&self, plugin_ids: &[u32]) -> bool {
+        self.plugin_id
+            .map(|plugin_id| plugin_ids.contains(&plugin_id))
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LogViewer {
+    log_path: Option<String>, // None means the default zellij log location
+    offset: Option<usize>,    // bytes of the log file we've already read
+    partial_line: String,
+    lines: Vec<LogLine>,
+    cleared_at_offset: Option<usize>,
+    scroll_from_bottom: usize,
+    show_all_lines: bool,
+    poll_in_flight: bool,
+    next_poll: Deadline,
+    active: bool,
+    keep_polling: bool, // eg. while we're watching for a crash after a reload
+}
+
+impl LogViewer {
+    pub fn update_log_path(&mut self, log_path: &str) {
+        self.log_path = Some(log_path.to_owned());
+    }
+    pub fn activate(&mut self, host: &mut impl Host) {
+        self.active = true;
+        self.poll(host);
+    }
+    pub fn deactivate(&mut self) {
+        self.active = false;
+    }
+    pub fn keep_polling(&mut self, host: &mut impl Host, keep_polling: bool) {
+        let started_polling = keep_polling && !self.keep_polling;
+        self.keep_polling = keep_polling;
+        if started_polling {
+            self.poll(host);
+        }
+    }
+    pub fn timer_elapsed(&mut self, host: &mut impl Host) {
+        if self.next_poll.elapsed() && (self.active || self.keep_polling) {
+            self.poll(host);
+        }
+    }
+    pub fn handle_key(
+        &mut self,
+        host: &mut impl Host,
+        key: KeyWithModifier,
+        plugin_ids: &[u32],
+    ) -> bool {
+        let mut should_render = true;
+        match key.bare_key {
+            BareKey::Up if key.has_no_modifiers() => {
+                self.scroll_from_bottom += 1;
+            }
+            BareKey::Down if key.has_no_modifiers() => {
+                self.scroll_from_bottom = self.scroll_from_bottom.saturating_sub(1);
+            }
+            BareKey::PageUp if key.has_no_modifiers() => {
+                self.scroll_from_bottom += 10;
+            }
+            BareKey::PageDown if key.has_no_modifiers() => {
+                self.scroll_from_bottom = self.scroll_from_bottom.saturating_sub(10);
+            }
+            BareKey::Char('c') if key.has_no_modifiers() => {
+                self.clear();
+            }
+            BareKey::Char('a') if key.has_no_modifiers() => {
+                self.show_all_lines = !self.show_all_lines;
+                self.scroll_from_bottom = 0;
+            }
+            BareKey::Char('o') if key.has_no_modifiers() => {
+                self.open_follow_pane(host, plugin_ids);
+                should_render = false;
+            }
+            _ => {
+                should_render = false;
+            }
+        }
+        should_render
+    }
+    // returns the lines that were logged since the previous poll
+    pub fn handle_poll_result(
+        &mut self,
+        exit_code: Option<i32>,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+    ) -> Vec<LogLine> {
+        self.poll_in_flight = false;
+        let mut new_lines = vec![];
+        if exit_code != Some(0) {
+            eprintln!(
+                "Failed to read the zellij log: {}",
+                String::from_utf8_lossy(&stderr)
+            );
+            return new_lines;
+        }
+        let stdout = String::from_utf8_lossy(&stdout);
+        let (header, contents) = stdout.split_once('\n').unwrap_or((&stdout, ""));
+        let mut header = header
+            .split_whitespace()
+            .filter_map(|n| n.parse::<usize>().ok());
+        let (size, read_from) = match (header.next(), header.next()) {
+            (Some(size), Some(read_from)) => (size, read_from),
+            _ => return new_lines,
+        };
+        let is_initial_read = self.offset.is_none();
+        let mut contents = contents;
+        let log_was_rotated = self
+            .offset
+            .map(|offset| read_from < offset)
+            .unwrap_or(false);
+        if log_was_rotated {
+            self.partial_line.clear();
+            self.cleared_at_offset = None;
+        } else if is_initial_read && read_from > 0 {
+            // we started reading from the middle of the log, so the first line is partial
+            contents = contents.split_once('\n').map(|(_, c)| c).unwrap_or("");
+        }
+        self.offset = Some(size);
+        self.partial_line.push_str(contents);
+        if let Some(last_newline) = self.partial_line.rfind('\n') {
+            let complete_lines: String = self.partial_line.drain(..=last_newline).collect();
+            for line in complete_lines.lines() {
+                new_lines.push(LogLine::new(line.to_owned()));
+            }
+            self.lines.extend(new_lines.iter().cloned());
+            if self.lines.len() > MAX_LINES {
+                self.lines.drain(..self.lines.len() - MAX_LINES);
+            }
+        }
+        if is_initial_read {
+            // these were logged before we started looking
+            new_lines.clear();
+        }
+        new_lines
+    }
+    pub fn render(
+        &self,
+        y: usize,
+        rows: usize,
+        cols: usize,
+        plugin_ids: &[u32],
+        plugin_file_name: Option<&str>,
+    ) {
+        let mut title = if self.show_all_lines {
+            "Zellij Log - all lines".to_owned()
+        } else {
+            match plugin_file_name {
+                Some(plugin_file_name) => format!("Zellij Log - lines from {}", plugin_file_name),
+                None => "Zellij Log - <NO FOLDER SELECTED>".to_owned(),
+            }
+        };
+        if let Some(cleared_at_offset) = self.cleared_at_offset {
+            title.push_str(&format!(" (cleared at byte {})", cleared_at_offset));
+        }
+        print_text_with_coordinates(
+            Text::new(truncate(&title, cols)).color_range(2, ..10),
+            0,
+            y,
+            None,
+            None,
+        );
+        let visible_lines: Vec<&LogLine> = self
+            .lines
+            .iter()
+            .filter(|l| self.show_all_lines || l.belongs_to(plugin_ids))
+            .collect();
+        let available_rows = rows.saturating_sub(y + 3);
+        let last_line = visible_lines.len().saturating_sub(self.scroll_from_bottom);
+        let first_line = last_line.saturating_sub(available_rows);
+        for (i, line) in visible_lines[first_line..last_line].iter().enumerate() {
+            let text = if self.show_all_lines {
+                line.text.trim_end()
+            } else {
+                line.message()
+            };
+            let mut text = Text::new(truncate(text, cols));
+            if line.is_panic() {
+                text = text.color_range(3, ..);
+            }
+            print_text_with_coordinates(text, 0, y + 1 + i, None, None);
+        }
+        if visible_lines.is_empty() {
+            print_text_with_coordinates(Text::new("<NO LOG LINES YET>"), 0, y + 1, None, None);
+        }
+        let help = "<↓↑> scroll, <c> clear, <a> toggle all lines, <o> follow in a pane, <Esc> back";
+        print_text_with_coordinates(help_text(help, cols), 0, rows.saturating_sub(1), None, None);
+    }
+    fn clear(&mut self) {
+        self.lines.clear();
+        self.scroll_from_bottom = 0;
+        self.cleared_at_offset = self.offset;
+    }
+    fn poll(&mut self, host: &mut impl Host) {
+        if !self.poll_in_flight {
+            let read_from = match self.offset {
+                Some(offset) => offset.to_string(),
+                None => "-1".to_owned(),
+            };
+            // we print the size of the log and the offset we read from on the first line, and then
+            // only the bytes up to that size so that we won't skip anything written meanwhile
+            let script = format!(
+                "f={log_path}
+                size=$(($(wc -c < \"$f\")))
+                offset={read_from}
+                if [ \"$offset\" -lt 0 ]; then offset=$((size > {initial} ? size - {initial} : 0)); fi
+                if [ \"$size\" -lt \"$offset\" ]; then offset=0; fi
+                echo \"$size $offset\"
+                tail -c +$((offset + 1)) \"$f\" | head -c $((size - offset))",
+                log_path = self.log_path_for_shell(),
+                read_from = read_from,
+                initial = INITIAL_READ_BYTES,
+            );
+            let mut context = BTreeMap::new();
+            context.insert("source".to_owned(), "log_viewer".to_owned());
+            host.run_command(
+                vec!["sh".to_owned(), "-c".to_owned(), script],
+                BTreeMap::new(),
+                PathBuf::from("."),
+                context,
+            );
+            self.poll_in_flight = true;
+        }
+        self.next_poll.schedule(host, POLL_INTERVAL_SECS);
+    }
+    fn open_follow_pane(&self, host: &mut impl Host, plugin_ids: &[u32]) {
+        let patterns: Vec<String> = plugin_ids
+            .iter()
+            .map(|plugin_id| format!("\\[id: {} *\\]", plugin_id))
+            .collect();
+        let script = if patterns.is_empty() || self.show_all_lines {
+            format!("tail -n 0 -F {}", self.log_path_for_shell())
+        } else {
+            format!(
+                "tail -n 0 -F {} | grep --line-buffered -E '{}'",
+                self.log_path_for_shell(),
+                patterns.join("|")
+            )
+        };
+        let command = CommandToRun::new_with_args("sh", vec!["-c", &script]);
+        host.open_command_pane_floating(command, Default::default());
+    }
+    fn log_path_for_shell(&self) -> String {
+        match &self.log_path {
+            Some(log_path) => format!("'{}'", log_path.replace('\'', "'\\''")),
+            None => DEFAULT_LOG_PATH.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // zellij cuts the url in the prefix to 25 characters
+    const LONG_NAME_LINE: &str =
+        "INFO   |file:/home/me/projects/my| 2024-11-07 17:26:47.123 [id: 3     ] loaded the config";
+
+    #[test]
+    fn lines_belong_to_a_plugin_by_its_id_even_when_its_file_name_is_cut_short() {
+        let line = LogLine::new(LONG_NAME_LINE.to_owned());
+        assert!(line.belongs_to(&[1, 3]));
+        assert!(!line.belongs_to(&[4]));
+        assert_eq!(line.message(), "loaded the config");
+        let host_line = LogLine::new("INFO   |zellij_server::screen| resized the tab".to_owned());
+        assert!(!host_line.belongs_to(&[3]));
+        assert_eq!(
+            host_line.message(),
+            "INFO   |zellij_server::screen| resized the tab"
+        );
+    }
+
+    #[test]
+    fn only_plugin_panics_are_panics() {
+        let panic = LogLine::new(
+            "INFO   |file:/p.wasm| 2024-11-07 17:26:47.123 [id: 3     ] thread '<unnamed>' panicked at src/main.rs:1:1:"
+                .to_owned(),
+        );
+        assert!(panic.is_panic());
+        let error = LogLine::new("ERROR  |zellij_server::route| failed to route".to_owned());
+        assert!(!error.is_panic());
+        assert!(error.has_level_prefix());
+    }
+
+    #[test]
+    fn polled_lines_are_new_once_complete_and_only_after_the_first_read() {
+        let mut log_viewer = LogViewer::default();
+        let initial =
+            log_viewer.handle_poll_result(Some(0), b"20 0\nlogged earlier\n".to_vec(), vec![]);
+        assert!(initial.is_empty());
+        let partial = log_viewer.handle_poll_result(Some(0), b"30 20\nfirst ha".to_vec(), vec![]);
+        assert!(partial.is_empty());
+        let completed =
+            log_viewer.handle_poll_result(Some(0), b"40 30\nlf\nsecond\n".to_vec(), vec![]);
+        assert_eq!(
+            completed.iter().map(|l| l.text()).collect::<Vec<&str>>(),
+            vec!["first half", "second"]
+        );
+        assert_eq!(log_viewer.lines.len(), 3);
+    }
+}
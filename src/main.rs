@@ -1,47 +1,129 @@
+mod artifact_check;
+mod build_output;
+mod build_progress;
+mod build_worker;
+mod cargo_messages;
+mod cargo_workspace;
+mod check_on_save;
+mod command_palette;
+mod crash_detection;
+mod dashboard;
+mod deadline;
+mod diagnostics;
+mod doctor;
+mod hooks;
+mod host;
+mod host_fs;
+mod log_viewer;
+mod pipe_workbench;
+mod project_data;
+mod quick_fix;
+mod reload_timing;
+mod reports;
 mod run_and_reload;
+mod size_matrix;
+mod snapshots;
+mod source_context;
+mod transcript;
+mod ui;
 mod update_workspace;
+mod warning_budget;
+mod wasm_module;
+mod workspace;
 
-use run_and_reload::RunAndReload;
-use update_workspace::UpdateWorkspace;
+use artifact_check::ArtifactCheck;
+use build_output::BuildOutput;
+use build_progress::BuildProgress;
+use build_worker::{ARTIFACT_CHECK, BUILD_UPDATE, CHECK_UPDATE, EXPORT_RESULT};
+use cargo_workspace::MemberPicker;
+use check_on_save::CheckOnSave;
+use command_palette::{open_command_pane, CommandPalette, DevCommand};
+use crash_detection::CrashDetection;
+use dashboard::Dashboard;
+use diagnostics::{DiagnosticsList, DiagnosticsOrigin};
+use doctor::Doctor;
+use hooks::{HookConfig, HookKind, HookStatus};
+use host::{Host, HostEffect, ZellijHost};
+use host_fs::HostFs;
+use log_viewer::LogViewer;
+use pipe_workbench::PipeWorkbench;
+use project_data::ProjectData;
+use reload_timing::ReloadTiming;
+use reports::ReportExport;
+use run_and_reload::{BuildConfig, BuildStatus};
+use size_matrix::SizeMatrix;
+use snapshots::Snapshots;
+use transcript::TranscriptRecorder;
+use ui::print_tabs;
+use update_workspace::recompile_bindings;
+use warning_budget::WarningBudget;
+use workspace::{DevEvent, Workspace};
 
 use zellij_tile::prelude::*;
 
 use std::collections::BTreeMap;
-use std::path::{Component, PathBuf};
+use std::path::PathBuf;
 use uuid::Uuid;
 
-struct State {
-    run_and_reload: RunAndReload,
-    update_workspace: UpdateWorkspace,
-    filepicker_request_ids: Vec<String>,
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    #[default]
+    Main,
+    PipeWorkbench,
+    Logs,
+    ReloadTiming,
+    Snapshots,
+    Members,
+    Diagnostics,
+    Doctor,
+    CommandPalette,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        let reload_shortcut = KeyWithModifier::new(BareKey::Char('r'))
-            .with_ctrl_modifier()
-            .with_shift_modifier();
-        State {
-            run_and_reload: Default::default(),
-            update_workspace: UpdateWorkspace::new(reload_shortcut),
-            filepicker_request_ids: Default::default(),
-        }
-    }
+#[derive(Default)]
+struct State<H: Host = ZellijHost> {
+    workspace: Workspace,
+    host: H,
+    transcript: TranscriptRecorder,
+    pipe_workbench: PipeWorkbench,
+    log_viewer: LogViewer,
+    crash_detection: CrashDetection,
+    reload_timing: ReloadTiming,
+    size_matrix: SizeMatrix,
+    snapshots: Snapshots,
+    member_picker: MemberPicker,
+    command_palette: CommandPalette,
+    build_output: BuildOutput,
+    build_progress: BuildProgress,
+    check_on_save: CheckOnSave,
+    diagnostics: DiagnosticsList,
+    warning_budget: WarningBudget,
+    artifact_check: ArtifactCheck,
+    report_export: ReportExport,
+    doctor: Doctor,
+    host_fs: HostFs,
+    project_data: ProjectData,
+    filepicker_request_ids: Vec<String>,
+    screen: Screen,
 }
 
+// the tests (which run natively, without zellij) drive the plugin through a RecordingHost
+#[cfg(not(test))]
 register_plugin!(State);
+#[cfg(not(test))]
+register_worker!(build_worker::BuildWorker, build_worker, BUILD_WORKER);
 
-impl ZellijPlugin for State {
+impl<H: Host + Default> ZellijPlugin for State<H> {
     fn load(&mut self, configuration: BTreeMap<String, String>) {
-        request_permission(&[
+        self.host.request_permission(&[
             PermissionType::ReadApplicationState,
             PermissionType::RunCommands,
+            PermissionType::OpenFiles,
             PermissionType::OpenTerminalsOrPlugins,
             PermissionType::Reconfigure,
             PermissionType::ChangeApplicationState,
             PermissionType::MessageAndLaunchOtherPlugins,
         ]);
-        subscribe(&[
+        self.host.subscribe(&[
             EventType::ModeUpdate,
             EventType::TabUpdate,
             EventType::Key,
@@ -50,19 +132,51 @@ impl ZellijPlugin for State {
             EventType::PaneUpdate,
             EventType::PaneClosed,
             EventType::PermissionRequestResult,
+            EventType::RunCommandResult,
+            EventType::Timer,
+            EventType::CustomMessage,
+            EventType::FileSystemCreate,
+            EventType::FileSystemUpdate,
+            EventType::FileSystemDelete,
         ]);
-        let plugin_ids = get_plugin_ids();
-        if let Some(reload_shortcut) = configuration.get("reload_shortcut") {
-            self.update_workspace.update_reload_shortcut(reload_shortcut);
+        let plugin_ids = self.host.get_plugin_ids();
+        if configuration.get("record_transcript").map(|r| r.as_str()) == Some("true") {
+            self.transcript.start();
+        }
+        if let Some(size_matrix) = configuration.get("size_matrix") {
+            self.size_matrix.update_sizes(size_matrix);
+        }
+        if let Some(size_matrix_pause) = configuration.get("size_matrix_pause") {
+            self.size_matrix.update_pause(size_matrix_pause);
         }
-        self.update_workspace
-            .update_own_plugin_id(plugin_ids.plugin_id);
-        self.run_and_reload.update_cwd(plugin_ids.initial_cwd);
+        if let Some(zellij_log_path) = configuration.get("zellij_log_path") {
+            self.log_viewer.update_log_path(zellij_log_path);
+        }
+        self.check_on_save
+            .update_config(&mut self.host, &configuration);
+        self.warning_budget.update_config(&configuration);
+        self.artifact_check.update_config(&configuration);
+        self.dispatch(DevEvent::Loaded {
+            own_plugin_id: plugin_ids.plugin_id,
+            reload_shortcut: configuration.get("reload_shortcut").cloned(),
+            bind_in_locked_mode: configuration.get("bind_in_locked_mode").map(|b| b.as_str())
+                == Some("true"),
+            close_with_plugin: configuration.get("close_with_plugin").map(|c| c.as_str())
+                != Some("false"),
+            build_config: BuildConfig {
+                hold_reload: self.warning_budget.is_enforced() || self.artifact_check.is_enabled(),
+                ..BuildConfig::from_configuration(&configuration)
+            },
+            hook_config: HookConfig::from_configuration(&configuration),
+        });
+        self.host_fs.update_root(plugin_ids.initial_cwd.clone());
+        self.update_cwd(plugin_ids.initial_cwd);
     }
     fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
         let mut should_render = false;
         if pipe_message.is_private && pipe_message.name == "recompile" {
-            self.run_and_reload.run_compilation();
+            self.recompile();
+            should_render = true;
         } else if pipe_message.name == "filepicker_result" {
             should_render = self.handle_filepicker_result(pipe_message);
         }
@@ -72,52 +186,262 @@ impl ZellijPlugin for State {
         let mut should_render = false;
         match event {
             Event::PermissionRequestResult(_) => {
-                if let Some(plugin_id) = self.update_workspace.get_own_plugin_id() {
-                    rename_pane_with_id(
-                        PaneId::Plugin(plugin_id),
-                        format!("Develop Zellij Plugin"),
-                    );
-                }
+                self.dispatch(DevEvent::PermissionsGranted);
             }
             Event::PaneUpdate(pane_manifest) => {
-                self.update_workspace.update_pane_manifest(pane_manifest);
-                self.update_workspace
-                    .bind_key_if_not_bound_and_tab_is_focused();
-                self.update_workspace
-                    .update_plugin_pane_id(self.plugin_url());
-                self.update_workspace
-                    .rename_plugin_pane_if_needed(self.plugin_name());
+                self.dispatch(DevEvent::PaneUpdate(pane_manifest));
+                let completed_iteration = self
+                    .reload_timing
+                    .plugin_pane_updated(self.workspace.update_workspace().get_plugin_pane_info());
+                let size_matrix_was_running = self.size_matrix.is_running();
+                self.size_matrix.plugin_pane_updated(
+                    &mut self.host,
+                    self.workspace.update_workspace().get_plugin_pane_info(),
+                );
+                should_render = completed_iteration || size_matrix_was_running;
             }
             Event::TabUpdate(tab_infos) => {
-                self.update_workspace.update_tab_infos(tab_infos);
-                self.update_workspace
-                    .bind_key_if_not_bound_and_tab_is_focused();
+                self.dispatch(DevEvent::TabUpdate(tab_infos));
+            }
+            Event::Key(key) => {
+                should_render = self.handle_key(key);
             }
-            Event::Key(key) => match key.bare_key {
-                BareKey::Char('f') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
-                    self.send_filepicker_request();
-                }
-                _ => {}
-            },
             Event::ModeUpdate(mode_info) => {
-                if let Some(base_mode) = mode_info.base_mode {
-                    self.update_workspace.update_base_mode(base_mode);
-                    self.update_workspace
-                        .bind_key_if_not_bound_and_tab_is_focused();
-                }
+                let recompile_bindings = if mode_info.keybinds.is_empty() {
+                    None
+                } else {
+                    Some(recompile_bindings(
+                        &mode_info.keybinds,
+                        self.workspace.update_workspace().get_own_plugin_id(),
+                    ))
+                };
+                self.dispatch(DevEvent::ModeUpdate {
+                    base_mode: mode_info.base_mode,
+                    session_name: mode_info.session_name,
+                    recompile_bindings,
+                });
             }
-            Event::CommandPaneOpened(terminal_pane_id, _context) => {
-                self.run_and_reload
-                    .update_compilation_pane_id(terminal_pane_id);
+            Event::CommandPaneOpened(terminal_pane_id, context)
+                if context_source(&context) == Some("compilation") =>
+            {
+                self.dispatch(DevEvent::CompilationPaneOpened(terminal_pane_id));
                 should_render = true;
             }
-            Event::CommandPaneExited(terminal_pane_id, exit_code, _context) => {
-                self.run_and_reload
-                    .command_pane_exited(exit_code, terminal_pane_id);
+            Event::CommandPaneExited(terminal_pane_id, exit_code, _context)
+                if self
+                    .workspace
+                    .run_and_reload()
+                    .is_compilation_pane(terminal_pane_id) =>
+            {
+                let effects = self.dispatch(DevEvent::CompilationPaneExited {
+                    terminal_pane_id,
+                    exit_code,
+                });
+                self.build_finished(&effects);
+                should_render = true;
+            }
+            Event::CommandPaneOpened(terminal_pane_id, context)
+                if context_source(&context) == Some("hook") =>
+            {
+                if let Some(hook) = HookKind::from_context(&context) {
+                    self.dispatch(DevEvent::HookPaneOpened {
+                        hook,
+                        terminal_pane_id,
+                    });
+                }
+            }
+            Event::CommandPaneExited(terminal_pane_id, exit_code, _context)
+                if self
+                    .workspace
+                    .hooks()
+                    .hook_of_pane(terminal_pane_id)
+                    .is_some() =>
+            {
+                let hook = self.workspace.hooks().hook_of_pane(terminal_pane_id);
+                let effects = self.dispatch(DevEvent::HookPaneExited {
+                    terminal_pane_id,
+                    exit_code,
+                });
+                let hook_status = self.workspace.hooks().get_status(HookKind::PreBuild);
+                if hook == Some(HookKind::PreBuild) && matches!(hook_status, HookStatus::Failed(_))
+                {
+                    // the build was aborted
+                    self.build_finished(&effects);
+                }
+                should_render = true;
             }
             Event::PaneClosed(pane_id) => {
-                self.run_and_reload.pane_closed(pane_id);
-                self.update_workspace.pane_closed(pane_id);
+                self.dispatch(DevEvent::PaneClosed(pane_id));
+            }
+            Event::RunCommandResult(exit_code, stdout, stderr, context)
+                if context_source(&context) == Some("pipe_workbench") =>
+            {
+                self.pipe_workbench
+                    .handle_cli_pipe_result(exit_code, stdout, stderr, context);
+                should_render = true;
+            }
+            Event::RunCommandResult(exit_code, stdout, stderr, context)
+                if context_source(&context) == Some("log_viewer") =>
+            {
+                let new_lines = self
+                    .log_viewer
+                    .handle_poll_result(exit_code, stdout, stderr);
+                let previous_crash = self.crash_detection.get_crash().cloned();
+                self.crash_detection
+                    .log_lines_received(&new_lines, &self.plugin_ids());
+                self.log_viewer
+                    .keep_polling(&mut self.host, self.crash_detection.is_watching());
+                should_render = self.screen == Screen::Logs
+                    || self.crash_detection.get_crash() != previous_crash.as_ref();
+            }
+            Event::Timer(_) => {
+                self.log_viewer.timer_elapsed(&mut self.host);
+                should_render = self.size_matrix.timer_elapsed(
+                    &mut self.host,
+                    self.workspace.update_workspace().get_plugin_pane_info(),
+                );
+                self.snapshots.timer_elapsed(
+                    &mut self.host,
+                    self.workspace.update_workspace().get_session_name(),
+                );
+                if self.build_progress.timer_elapsed(&mut self.host) && self.screen == Screen::Main
+                {
+                    should_render = true;
+                }
+                let warnings_timed_out = self.warning_budget.timer_elapsed();
+                let artifact_timed_out = self.artifact_check.timer_elapsed();
+                if warnings_timed_out || artifact_timed_out {
+                    self.release_held_reload();
+                    should_render = true;
+                }
+                if self.check_on_save.timer_elapsed() {
+                    self.run_check();
+                    should_render = true;
+                }
+            }
+            Event::RunCommandResult(exit_code, stdout, stderr, context)
+                if context_source(&context) == Some("quiet_build")
+                    && self.workspace.run_and_reload().is_quiet_build_running() =>
+            {
+                self.build_output.build_finished(
+                    stdout,
+                    stderr,
+                    self.workspace.run_and_reload().get_target_directory(),
+                    &self.host_fs,
+                );
+                let effects = self.dispatch(DevEvent::QuietBuildFinished { exit_code });
+                self.build_finished(&effects);
+                if exit_code != Some(0) {
+                    self.build_output
+                        .open_pane(&mut self.host, self.workspace.run_and_reload().get_cwd());
+                }
+                should_render = true;
+            }
+            Event::RunCommandResult(exit_code, stdout, stderr, context)
+                if context_source(&context) == Some("cargo_metadata") =>
+            {
+                if let Some((cargo_project, selected_members)) =
+                    self.member_picker.handle_metadata_result(
+                        &self.project_data,
+                        exit_code,
+                        stdout,
+                        stderr,
+                        &context,
+                        self.workspace.run_and_reload().get_cwd(),
+                    )
+                {
+                    self.dispatch(DevEvent::CargoProjectDetected {
+                        cargo_project,
+                        selected_members,
+                    });
+                    should_render = true;
+                }
+            }
+            Event::RunCommandResult(exit_code, _stdout, stderr, context)
+                if context_source(&context) == Some("snapshots") =>
+            {
+                self.snapshots.handle_dump_result(
+                    &mut self.host,
+                    exit_code,
+                    stderr,
+                    self.workspace.update_workspace().get_own_plugin_id(),
+                    &self.host_fs,
+                );
+                should_render = true;
+            }
+            Event::CustomMessage(message, payload) if message == BUILD_UPDATE => {
+                if let Some(update) = self
+                    .build_progress
+                    .update_received(&self.project_data, &payload)
+                {
+                    if update.restarted {
+                        self.diagnostics.start(DiagnosticsOrigin::Build);
+                    }
+                    self.diagnostics
+                        .extend(DiagnosticsOrigin::Build, update.diagnostics);
+                    if let Some(succeeded) = update.finished {
+                        self.warning_budget.build_finished(
+                            &self.project_data,
+                            succeeded,
+                            &self.diagnostics.get_warnings(),
+                        );
+                        self.release_held_reload();
+                    }
+                    should_render = true;
+                }
+            }
+            Event::CustomMessage(message, payload) if message == CHECK_UPDATE => {
+                if let Some((update, should_reload)) = self.check_on_save.update_received(&payload)
+                {
+                    self.diagnostics.start(DiagnosticsOrigin::Check);
+                    self.diagnostics
+                        .extend(DiagnosticsOrigin::Check, update.diagnostics);
+                    if should_reload && !self.is_building() {
+                        self.recompile();
+                    }
+                    should_render = true;
+                }
+            }
+            Event::RunCommandResult(exit_code, stdout, stderr, context)
+                if context_source(&context) == Some("doctor") =>
+            {
+                self.doctor
+                    .command_finished(exit_code, stdout, stderr, &context);
+                should_render = self.screen == Screen::Doctor;
+            }
+            Event::CustomMessage(message, payload) if message == ARTIFACT_CHECK => {
+                self.artifact_check.result_received(&payload);
+                self.release_held_reload();
+                should_render = true;
+            }
+            Event::CustomMessage(message, payload) if message == EXPORT_RESULT => {
+                self.report_export.result_received(&payload);
+                should_render = true;
+            }
+            Event::RunCommandResult(_exit_code, stdout, stderr, context)
+                if context_source(&context) == Some("cargo_check") =>
+            {
+                if self
+                    .check_on_save
+                    .check_finished(&mut self.host, stdout, stderr, &context)
+                {
+                    self.run_check();
+                }
+                should_render = true;
+            }
+            Event::FileSystemCreate(paths)
+            | Event::FileSystemUpdate(paths)
+            | Event::FileSystemDelete(paths) => {
+                let paths: Vec<PathBuf> = paths.into_iter().map(|(path, _)| path).collect();
+                let workspace_root = self.workspace_root();
+                let target_directory = self.workspace.run_and_reload().get_target_directory();
+                self.check_on_save.files_changed(
+                    &mut self.host,
+                    &paths,
+                    self.host_fs.get_root(),
+                    workspace_root.as_deref(),
+                    target_directory.as_deref(),
+                );
             }
             _ => {}
         }
@@ -125,81 +449,499 @@ impl ZellijPlugin for State {
     }
 
     fn render(&mut self, rows: usize, cols: usize) {
-        let reload_shortcut = format!("{}", self.update_workspace.get_reload_shortcut());
-        let current_folder = format!(
-            "{}",
-            self.run_and_reload
-                .get_cwd()
-                .map(|f| f.display().to_string())
-                .unwrap_or_else(|| "<NOT SET>".to_owned())
-        );
-        let title_text = "Develop Zellij Plugin";
-        let explanation_text_1 = "This plugin will help you develop a Zellij plugin in Rust.";
-        let explanation_text_2 = format!("Press <{}> to:", reload_shortcut);
-        let bulletin_1 = format!("1. Run cargo build");
-        let bulletin_2 = format!("2. Load or Reload the plugin");
-        let explanation_text_3 = format!("Closing the plugin window will close this plugin.");
-        let folder_text = format!("Current Folder: {} <Ctrl f> to change", current_folder);
-
-        let longest_line_length = std::cmp::max(
-            folder_text.chars().count(),
-            explanation_text_1.chars().count(),
-        );
-        let centered_x = cols.saturating_sub(longest_line_length) / 2;
-        let centered_y = rows.saturating_sub(10) / 2;
-
-        let title_text = Text::new(title_text).color_range(2, ..);
-        let explanation_text_1 = Text::new(explanation_text_1);
-        let explanation_text_2 =
-            Text::new(explanation_text_2).color_range(3, 6..=7 + reload_shortcut.chars().count());
-        let bulletin_1 = Text::new(bulletin_1).color_range(0, 7..=17);
-        let bulletin_2 = Text::new(bulletin_2);
-        let explanation_text_3 = Text::new(explanation_text_3);
-        let folder_text = Text::new(folder_text)
-            .color_range(0, 16..=16 + current_folder.chars().count())
-            .color_range(
-                3,
-                16 + current_folder.chars().count() + 1..=16 + current_folder.chars().count() + 9,
-            );
-
-        print_text_with_coordinates(title_text, centered_x, centered_y, None, None);
-        print_text_with_coordinates(explanation_text_1, centered_x, centered_y + 1, None, None);
-        print_text_with_coordinates(explanation_text_2, centered_x, centered_y + 3, None, None);
-        print_text_with_coordinates(bulletin_1, centered_x + 2, centered_y + 4, None, None);
-        print_text_with_coordinates(bulletin_2, centered_x + 2, centered_y + 5, None, None);
-        print_text_with_coordinates(explanation_text_3, centered_x, centered_y + 7, None, None);
-        print_text_with_coordinates(folder_text, centered_x, centered_y + 9, None, None);
+        let tabs = [
+            ("", "Dashboard"),
+            ("<p>", "Pipes"),
+            ("<l>", "Logs"),
+            ("<t>", "Timing"),
+            ("<s>", "Snapshots"),
+            ("<w>", "Members"),
+            ("<d>", "Diagnostics"),
+            ("<h>", "Doctor"),
+        ];
+        let selected_tab = match self.screen {
+            Screen::Main => 0,
+            Screen::PipeWorkbench => 1,
+            Screen::Logs => 2,
+            Screen::ReloadTiming => 3,
+            Screen::Snapshots => 4,
+            Screen::Members => 5,
+            Screen::Diagnostics => 6,
+            Screen::Doctor => 7,
+            Screen::CommandPalette => 0,
+        };
+        print_tabs(&tabs, selected_tab, 0, cols);
+        let y = 1;
+        match self.screen {
+            Screen::PipeWorkbench => {
+                self.pipe_workbench.render(y, rows, cols, self.plugin_url());
+            }
+            Screen::Logs => {
+                let plugin_file_name = self.plugin_file_name();
+                self.log_viewer.render(
+                    y,
+                    rows,
+                    cols,
+                    &self.plugin_ids(),
+                    plugin_file_name.as_deref(),
+                );
+            }
+            Screen::ReloadTiming => {
+                self.reload_timing.render(y, rows, cols);
+            }
+            Screen::Snapshots => {
+                self.snapshots.render(y, rows, cols);
+            }
+            Screen::Doctor => {
+                self.doctor.render(y, rows, cols);
+            }
+            Screen::CommandPalette => {
+                self.command_palette.render(
+                    y,
+                    rows,
+                    cols,
+                    self.workspace.update_workspace().get_reload_shortcut(),
+                );
+            }
+            Screen::Members => {
+                let run_and_reload = self.workspace.run_and_reload();
+                self.member_picker.render(
+                    y,
+                    rows,
+                    cols,
+                    run_and_reload.get_cargo_project(),
+                    run_and_reload.get_selected_members(),
+                );
+            }
+            Screen::Diagnostics => {
+                self.diagnostics.render(
+                    y,
+                    rows,
+                    cols,
+                    self.workspace_root().as_deref(),
+                    &self.host_fs,
+                    &self.warning_budget,
+                );
+            }
+            Screen::Main => {
+                let update_workspace = self.workspace.update_workspace();
+                let dashboard = Dashboard {
+                    project_folder: self.workspace.run_and_reload().get_cwd(),
+                    plugin_name: self.plugin_name(),
+                    cargo_project: self.workspace.run_and_reload().get_cargo_project(),
+                    selected_members: self.workspace.run_and_reload().get_selected_members(),
+                    reload_shortcut: format!("{}", update_workspace.get_reload_shortcut()),
+                    binding_state: update_workspace.get_binding_state(),
+                    build_status: self.workspace.run_and_reload().get_build_status(),
+                    quiet_builds: self.workspace.run_and_reload().get_quiet_builds(),
+                    has_build_output: self.build_output.has_output(),
+                    timing_summary: self.reload_timing.summary(),
+                    plugin_instances: update_workspace.get_plugin_pane_ids().len(),
+                    close_with_plugin: update_workspace.get_close_with_plugin(),
+                    crash: self.crash_detection.get_crash(),
+                    watching_log: self.crash_detection.is_watching(),
+                    size_matrix_status: self.size_matrix.get_status(),
+                    transcript_path: self.transcript.get_path(),
+                    hooks: self.workspace.hooks(),
+                    build_progress: self.build_progress.summary(),
+                    check: self.check_on_save.summary(),
+                    warnings: self.warning_budget.summary(),
+                    reload_blocked: self.warning_budget.is_blocking(),
+                    artifact: self.artifact_check.summary(),
+                    artifact_blocked: self.artifact_check.is_blocking(),
+                    reports: self.report_export.get_status(),
+                    errors: self.diagnostics.get_errors(),
+                };
+                dashboard.render(y + 1, rows, cols);
+            }
+        }
     }
 }
 
-impl State {
-    fn plugin_url(&self) -> Option<String> {
-        if let Some(cwd) = &self.run_and_reload.get_cwd() {
-            if let Some(Component::Normal(project_dir_name)) = cwd.components().last() {
-                if let Some(project_dir_name) = project_dir_name.to_str() {
-                    let mut plugin_path = cwd.clone();
-                    plugin_path.extend(
-                        PathBuf::from(format!(
-                            "target/wasm32-wasip1/debug/{}.wasm",
-                            project_dir_name.to_string()
-                        ))
-                        .components(),
+impl<H: Host> State<H> {
+    fn handle_key(&mut self, key: KeyWithModifier) -> bool {
+        let mut should_render = false;
+        match self.screen {
+            Screen::Main => match key.bare_key {
+                BareKey::Char(':') if key.has_no_modifiers() => {
+                    self.command_palette.open();
+                    self.screen = Screen::CommandPalette;
+                    should_render = true;
+                }
+                _ => {
+                    if let Some(command) = DevCommand::for_key(&key) {
+                        should_render = self.execute(command);
+                    }
+                }
+            },
+            Screen::CommandPalette => match key.bare_key {
+                BareKey::Esc if key.has_no_modifiers() => {
+                    self.screen = Screen::Main;
+                    should_render = true;
+                }
+                _ => {
+                    let (render, command) = self.command_palette.handle_key(key);
+                    should_render = render;
+                    if let Some(command) = command {
+                        self.screen = Screen::Main;
+                        self.execute(command);
+                        should_render = true;
+                    }
+                }
+            },
+            Screen::PipeWorkbench => match key.bare_key {
+                BareKey::Esc if key.has_no_modifiers() => {
+                    self.screen = Screen::Main;
+                    should_render = true;
+                }
+                _ => {
+                    let plugin_url = self.plugin_url();
+                    let session_name = self.workspace.update_workspace().get_session_name();
+                    should_render = self.pipe_workbench.handle_key(
+                        &mut self.host,
+                        &self.project_data,
+                        key,
+                        plugin_url,
+                        session_name.as_deref(),
+                    );
+                }
+            },
+            Screen::ReloadTiming => {
+                if key.is_key_without_modifier(BareKey::Esc) {
+                    self.screen = Screen::Main;
+                    should_render = true;
+                }
+            }
+            Screen::Doctor => match key.bare_key {
+                BareKey::Esc if key.has_no_modifiers() => {
+                    self.screen = Screen::Main;
+                    should_render = true;
+                }
+                BareKey::Char('r') if key.has_no_modifiers() => {
+                    self.run_doctor();
+                    should_render = true;
+                }
+                _ => {}
+            },
+            Screen::Snapshots => match key.bare_key {
+                BareKey::Esc if key.has_no_modifiers() => {
+                    self.screen = Screen::Main;
+                    should_render = true;
+                }
+                _ => {
+                    let plugin_name = self.plugin_name();
+                    should_render = self.snapshots.handle_key(
+                        &mut self.host,
+                        key,
+                        self.workspace.update_workspace().get_plugin_pane_info(),
+                        plugin_name,
+                        self.workspace.run_and_reload().get_cwd(),
+                        &self.host_fs,
+                    );
+                }
+            },
+            Screen::Members => match key.bare_key {
+                BareKey::Esc if key.has_no_modifiers() => {
+                    self.screen = Screen::Main;
+                    should_render = true;
+                }
+                _ => {
+                    let run_and_reload = self.workspace.run_and_reload();
+                    let (render, selected_members) = self.member_picker.handle_key(
+                        &self.project_data,
+                        key,
+                        run_and_reload.get_cargo_project(),
+                        run_and_reload.get_selected_members(),
+                        run_and_reload.get_cwd(),
+                    );
+                    if let Some(selected_members) = selected_members {
+                        self.dispatch(DevEvent::MembersSelected(selected_members));
+                    }
+                    should_render = render;
+                }
+            },
+            Screen::Diagnostics => match key.bare_key {
+                BareKey::Esc if key.has_no_modifiers() => {
+                    self.screen = Screen::Main;
+                    should_render = true;
+                }
+                _ => {
+                    let workspace_root = self.workspace_root();
+                    let (render, rebuild) = self.diagnostics.handle_key(
+                        &mut self.host,
+                        key,
+                        workspace_root,
+                        &self.host_fs,
                     );
-                    return Some(format!("file:{}", plugin_path.display()));
+                    if rebuild {
+                        self.recompile();
+                    }
+                    should_render = render;
+                }
+            },
+            Screen::Logs => match key.bare_key {
+                BareKey::Esc if key.has_no_modifiers() => {
+                    self.screen = Screen::Main;
+                    self.log_viewer.deactivate();
+                    should_render = true;
                 }
+                _ => {
+                    let plugin_ids = self.plugin_ids();
+                    should_render = self.log_viewer.handle_key(&mut self.host, key, &plugin_ids);
+                }
+            },
+        }
+        should_render
+    }
+    // returns true if we should render
+    fn execute(&mut self, command: DevCommand) -> bool {
+        let mut should_render = true;
+        match command {
+            DevCommand::BuildAndReload => {
+                self.recompile();
+            }
+            DevCommand::Reload => {
+                self.reload_timing.key_pressed();
+                self.crash_detection.build_started();
+                let effects = self.dispatch(DevEvent::ReloadRequested);
+                self.build_finished(&effects);
+            }
+            DevCommand::RunTests => {
+                let (command, args) = self.workspace.run_and_reload().test_command();
+                let cwd = self.workspace.run_and_reload().get_cwd();
+                open_command_pane(&mut self.host, cwd, &command, args);
+                should_render = false;
+            }
+            DevCommand::RunClippy => {
+                let (command, args) = self.workspace.run_and_reload().clippy_command();
+                let cwd = self.workspace.run_and_reload().get_cwd();
+                open_command_pane(&mut self.host, cwd, &command, args);
+                should_render = false;
+            }
+            DevCommand::BuildRelease => {
+                let mut args = self.workspace.run_and_reload().cargo_args("build");
+                args.push("--release".to_owned());
+                let cwd = self.workspace.run_and_reload().get_cwd();
+                open_command_pane(&mut self.host, cwd, "cargo", args);
+                should_render = false;
+            }
+            DevCommand::ToggleQuietBuilds => {
+                self.dispatch(DevEvent::QuietBuildsToggled);
+            }
+            DevCommand::ShowBuildOutput => {
+                self.build_output
+                    .open_pane(&mut self.host, self.workspace.run_and_reload().get_cwd());
+                should_render = false;
+            }
+            DevCommand::ToggleCheckOnSave => {
+                self.check_on_save.toggle(&mut self.host);
+            }
+            DevCommand::ToggleAutoReload => {
+                self.check_on_save.toggle_auto_reload();
+            }
+            DevCommand::OpenDiagnostics => {
+                self.screen = Screen::Diagnostics;
+            }
+            DevCommand::AcceptWarnings => {
+                self.warning_budget.accept_warnings(&self.project_data);
+            }
+            DevCommand::ExportReports => {
+                let workspace_root = self.workspace_root();
+                let run_and_reload = self.workspace.run_and_reload();
+                self.report_export.export(
+                    &mut self.host,
+                    run_and_reload.get_dev_directory(),
+                    workspace_root,
+                    run_and_reload.get_progress_file().is_some(),
+                    &self.host_fs,
+                );
+            }
+            DevCommand::ChangeFolder => {
+                self.send_filepicker_request();
+                should_render = false;
+            }
+            DevCommand::ChooseMembers => {
+                self.screen = Screen::Members;
+            }
+            DevCommand::OpenPipeWorkbench => {
+                self.screen = Screen::PipeWorkbench;
+            }
+            DevCommand::OpenLogs => {
+                self.screen = Screen::Logs;
+                self.log_viewer.activate(&mut self.host);
+            }
+            DevCommand::OpenReloadTiming => {
+                self.screen = Screen::ReloadTiming;
+            }
+            DevCommand::OpenSnapshots => {
+                self.screen = Screen::Snapshots;
+            }
+            DevCommand::OpenDoctor => {
+                self.screen = Screen::Doctor;
+                self.run_doctor();
+            }
+            DevCommand::ToggleSizeMatrix => {
+                if self.size_matrix.is_running() {
+                    self.size_matrix.stop(&mut self.host);
+                } else {
+                    self.size_matrix.start(
+                        &mut self.host,
+                        self.workspace.update_workspace().get_plugin_pane_info(),
+                    );
+                }
+            }
+            DevCommand::JumpToPanic => {
+                self.crash_detection
+                    .jump_to_source(&mut self.host, self.workspace.run_and_reload().get_cwd());
+                should_render = false;
+            }
+            DevCommand::EditConfig => {
+                let edit_config =
+                    "${EDITOR:-vi} \"${ZELLIJ_CONFIG_FILE:-$HOME/.config/zellij/config.kdl}\"";
+                open_command_pane(
+                    &mut self.host,
+                    None,
+                    "sh",
+                    vec!["-c".to_owned(), edit_config.to_owned()],
+                );
+                should_render = false;
             }
         }
-        None
+        should_render
     }
-    fn plugin_name(&self) -> Option<String> {
-        if let Some(cwd) = &self.run_and_reload.get_cwd() {
-            if let Some(Component::Normal(project_dir_name)) = cwd.components().last() {
-                if let Some(project_dir_name) = project_dir_name.to_str() {
-                    return Some(project_dir_name.to_owned());
+    fn recompile(&mut self) {
+        self.reload_timing.key_pressed();
+        self.dispatch(DevEvent::RecompileRequested);
+        // we start reading the log before reloading so that we'll only see what's logged
+        // afterwards
+        self.crash_detection.build_started();
+        self.log_viewer.keep_polling(&mut self.host, true);
+    }
+    // the effects are those of finishing the build, which reload the plugin if it succeeded
+    fn build_finished(&mut self, effects: &[HostEffect]) {
+        // the build only ends once its warnings and wasm are checked and the plugin is (or isn't)
+        // reloaded
+        if self.workspace.run_and_reload().is_reload_pending() {
+            self.warning_budget.build_exited(&mut self.host);
+            let plugin_paths = self.workspace.run_and_reload().get_plugin_paths();
+            if let Some(request) =
+                self.artifact_check
+                    .build_finished(&mut self.host, plugin_paths, &self.host_fs)
+            {
+                request.post(&mut self.host);
+            }
+            self.release_held_reload();
+            return;
+        }
+        let plugin_reloaded = effects
+            .iter()
+            .any(|e| matches!(e, HostEffect::StartOrReloadPlugin(_)));
+        self.crash_detection.build_finished(plugin_reloaded);
+        self.reload_timing.build_finished(plugin_reloaded);
+        if plugin_reloaded {
+            self.reload_timing
+                .reload_requested(self.workspace.update_workspace().get_plugin_pane_info());
+        }
+        self.log_viewer
+            .keep_polling(&mut self.host, self.crash_detection.is_watching());
+    }
+    // cargo might report the end of the build before or after zellij tells us the build exited
+    fn release_held_reload(&mut self) {
+        if !self.workspace.run_and_reload().is_reload_pending() {
+            return;
+        }
+        if let (Some(warnings_allowed), Some(artifact_allowed)) = (
+            self.warning_budget.get_reload_allowed(),
+            self.artifact_check.get_reload_allowed(),
+        ) {
+            let effects = self.dispatch(DevEvent::ReloadChecked {
+                reload: warnings_allowed && artifact_allowed,
+            });
+            self.build_finished(&effects);
+        }
+    }
+    // applies the event to the workspace, records it if we're recording a transcript and makes
+    // the resulting host calls, then follows the build if one started or finished
+    fn dispatch(&mut self, event: DevEvent) -> Vec<HostEffect> {
+        let was_building = self.is_building();
+        let effects = self.workspace.reduce(event.clone());
+        self.transcript.record(&event, &effects);
+        for effect in &effects {
+            effect.clone().apply(&mut self.host);
+        }
+        match (was_building, self.is_building()) {
+            (false, true) => {
+                let run_and_reload = self.workspace.run_and_reload();
+                let following = self.build_progress.build_started(
+                    &mut self.host,
+                    &self.project_data,
+                    run_and_reload.get_progress_file(),
+                    run_and_reload.get_cwd(),
+                    &self.host_fs,
+                );
+                if following {
+                    self.diagnostics.start(DiagnosticsOrigin::Build);
                 }
+                self.warning_budget.build_started(following);
+                self.artifact_check.build_started();
             }
+            (true, false) => self.build_progress.build_finished(&mut self.host),
+            _ => {}
+        }
+        effects
+    }
+    fn run_doctor(&mut self) {
+        let run_and_reload = self.workspace.run_and_reload();
+        self.doctor.run(
+            &mut self.host,
+            run_and_reload.get_cwd(),
+            run_and_reload.get_target_directory(),
+            run_and_reload.get_plugin_paths(),
+            &self.host_fs,
+        );
+    }
+    fn run_check(&mut self) {
+        let run_and_reload = self.workspace.run_and_reload();
+        self.check_on_save.run(
+            &mut self.host,
+            run_and_reload.get_cwd(),
+            run_and_reload.cargo_args("check"),
+        );
+    }
+    // the folder the paths of the compiler's diagnostics are relative to
+    fn workspace_root(&self) -> Option<PathBuf> {
+        let run_and_reload = self.workspace.run_and_reload();
+        match run_and_reload.get_cargo_project() {
+            Some(cargo_project) => Some(cargo_project.workspace_root.clone()),
+            None => run_and_reload.get_cwd(),
         }
-        None
+    }
+    fn is_building(&self) -> bool {
+        self.workspace.run_and_reload().get_build_status() == BuildStatus::Building
+    }
+    fn update_cwd(&mut self, cwd: PathBuf) {
+        self.pipe_workbench
+            .update_project_folder(&self.project_data, cwd.clone());
+        self.member_picker.request_metadata(&mut self.host, &cwd);
+        self.warning_budget
+            .update_project_folder(&self.project_data, cwd.clone());
+        self.dispatch(DevEvent::CwdChanged(cwd));
+    }
+    fn plugin_url(&self) -> Option<String> {
+        self.workspace.plugin_url()
+    }
+    fn plugin_file_name(&self) -> Option<String> {
+        self.plugin_name()
+            .map(|plugin_name| format!("{}.wasm", plugin_name))
+    }
+    fn plugin_ids(&self) -> Vec<u32> {
+        self.workspace
+            .update_workspace()
+            .get_plugin_pane_ids()
+            .to_vec()
+    }
+    fn plugin_name(&self) -> Option<String> {
+        self.workspace.plugin_name()
     }
     pub fn send_filepicker_request(&mut self) {
         let mut args = BTreeMap::new();
@@ -208,7 +950,7 @@ impl State {
         let mut config = BTreeMap::new();
         config.insert("request_id".to_owned(), request_id.to_string());
         args.insert("request_id".to_owned(), request_id.to_string());
-        pipe_message_to_plugin(
+        self.host.pipe_message_to_plugin(
             MessageToPlugin::new("filepicker")
                 .with_plugin_url("filepicker")
                 .with_plugin_config(config)
@@ -220,26 +962,151 @@ impl State {
     }
     fn handle_filepicker_result(&mut self, pipe_message: PipeMessage) -> bool {
         let mut should_render = false;
-        match (pipe_message.payload, pipe_message.args.get("request_id")) {
-            (Some(payload), Some(request_id)) => {
-                match self
-                    .filepicker_request_ids
-                    .iter()
-                    .position(|p| p == request_id)
-                {
-                    Some(request_id_position) => {
-                        self.filepicker_request_ids.remove(request_id_position);
-                        let chosen_plugin_location = std::path::PathBuf::from(payload);
-                        self.run_and_reload.update_cwd(chosen_plugin_location);
-                        should_render = true;
-                    }
-                    None => {
-                        eprintln!("request id not found");
-                    }
+        if let (Some(payload), Some(request_id)) =
+            (pipe_message.payload, pipe_message.args.get("request_id"))
+        {
+            match self
+                .filepicker_request_ids
+                .iter()
+                .position(|p| p == request_id)
+            {
+                Some(request_id_position) => {
+                    self.filepicker_request_ids.remove(request_id_position);
+                    let chosen_plugin_location = std::path::PathBuf::from(payload);
+                    self.update_cwd(chosen_plugin_location);
+                    should_render = true;
+                }
+                None => {
+                    eprintln!("request id not found");
                 }
             }
-            _ => {}
         }
         should_render
     }
 }
+
+fn context_source(context: &BTreeMap<String, String>) -> Option<&str> {
+    context.get("source").map(|s| s.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use host::RecordingHost;
+    use std::path::Path;
+
+    const PROJECT_FOLDER: &str = "/projects/my-plugin";
+
+    fn loaded(configuration: &[(&str, &str)]) -> State<RecordingHost> {
+        let mut state = State::<RecordingHost> {
+            project_data: ProjectData::new(
+                std::env::temp_dir().join(format!("zellij-plugin-dev-{}", Uuid::new_v4())),
+            ),
+            ..Default::default()
+        };
+        state.host.plugin_ids = Some(PluginIds {
+            plugin_id: 7,
+            zellij_pid: 1,
+            initial_cwd: PathBuf::from(PROJECT_FOLDER),
+        });
+        state.load(
+            configuration
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        );
+        state
+    }
+
+    fn press(state: &mut State<RecordingHost>, key: KeyWithModifier) -> bool {
+        state.update(Event::Key(key))
+    }
+
+    #[test]
+    fn loading_asks_for_permissions_and_reads_the_project_metadata() {
+        let mut state = loaded(&[]);
+        let effects = state.host.take_effects();
+        assert!(matches!(effects[0], HostEffect::RequestPermission(_)));
+        assert!(matches!(effects[1], HostEffect::Subscribe(_)));
+        assert!(effects.iter().any(|effect| matches!(
+            effect,
+            HostEffect::RunCommand { command, cwd, .. }
+                if command[..2] == ["cargo", "metadata"] && cwd == Path::new(PROJECT_FOLDER)
+        )));
+        assert_eq!(
+            state.workspace.run_and_reload().get_cwd(),
+            Some(PathBuf::from(PROJECT_FOLDER))
+        );
+    }
+
+    #[test]
+    fn a_saved_source_file_schedules_a_check() {
+        let mut state = loaded(&[("check_on_save", "true")]);
+        assert!(state
+            .host
+            .take_effects()
+            .contains(&HostEffect::WatchFilesystem));
+        state.update(Event::FileSystemUpdate(vec![(
+            PathBuf::from("README.md"),
+            None,
+        )]));
+        assert_eq!(state.host.take_effects(), vec![]);
+        state.update(Event::FileSystemUpdate(vec![(
+            PathBuf::from("src/main.rs"),
+            None,
+        )]));
+        assert_eq!(state.host.take_effects(), vec![HostEffect::SetTimeout(0.3)]);
+    }
+
+    #[test]
+    fn every_screen_renders_and_esc_goes_back_to_the_dashboard() {
+        let mut state = loaded(&[]);
+        state.render(24, 80);
+        for key in ['p', 'l', 't', 's', 'w', 'd', 'h', ':'].iter() {
+            assert!(press(&mut state, KeyWithModifier::new(BareKey::Char(*key))));
+            assert_ne!(state.screen, Screen::Main, "<{}>", key);
+            state.render(24, 80);
+            assert!(press(&mut state, KeyWithModifier::new(BareKey::Esc)));
+            assert_eq!(state.screen, Screen::Main, "<{}>", key);
+        }
+    }
+
+    #[test]
+    fn the_chosen_folder_becomes_the_project_folder() {
+        let mut state = loaded(&[]);
+        state.host.take_effects();
+        press(
+            &mut state,
+            KeyWithModifier::new(BareKey::Char('f')).with_ctrl_modifier(),
+        );
+        let request_id = match state.host.take_effects().as_slice() {
+            [HostEffect::PipeMessageToPlugin {
+                plugin_url, args, ..
+            }] if plugin_url == "filepicker" => args["request_id"].clone(),
+            effects => panic!("unexpected effects: {:?}", effects),
+        };
+        let mut args = BTreeMap::new();
+        args.insert("request_id".to_owned(), "another request".to_owned());
+        let other_request = PipeMessage::new(
+            PipeSource::Plugin(3),
+            "filepicker_result",
+            &Some("/projects/elsewhere".to_owned()),
+            &Some(args.clone()),
+            false,
+        );
+        assert!(!state.pipe(other_request));
+        args.insert("request_id".to_owned(), request_id);
+        let result = PipeMessage::new(
+            PipeSource::Plugin(3),
+            "filepicker_result",
+            &Some("/projects/other-plugin".to_owned()),
+            &Some(args),
+            false,
+        );
+        assert!(state.pipe(result));
+        assert_eq!(
+            state.workspace.run_and_reload().get_cwd(),
+            Some(PathBuf::from("/projects/other-plugin"))
+        );
+    }
+}
@@ -0,0 +1,540 @@
+use zellij_tile::prelude::*;
+
+use crate::host::Host;
+use crate::project_data::ProjectData;
+use crate::ui::{help_text, truncate};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const MAX_REPLIES: usize = 10;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedPipeMessage {
+    name: String,
+    payload: String,
+    args: String, // eg. "key=value, other_key=other_value"
+}
+
+impl SavedPipeMessage {
+    fn parsed_args(&self) -> BTreeMap<String, String> {
+        let mut args = BTreeMap::new();
+        for arg in self.args.split(',') {
+            if let Some((key, value)) = arg.split_once('=') {
+                args.insert(key.trim().to_owned(), value.trim().to_owned());
+            }
+        }
+        args
+    }
+    fn to_plugin_message(&self, plugin_url: String) -> MessageToPlugin {
+        let message_to_plugin = MessageToPlugin::new(&self.name)
+            .with_plugin_url(plugin_url)
+            .with_args(self.parsed_args());
+        if self.payload.is_empty() {
+            message_to_plugin
+        } else {
+            message_to_plugin.with_payload(&self.payload)
+        }
+    }
+    fn cli_command(&self, plugin_url: &str, session_name: &str) -> Vec<String> {
+        let mut command: Vec<String> = [
+            "zellij",
+            "--session",
+            session_name,
+            "pipe",
+            "--plugin",
+            plugin_url,
+            "--name",
+            &self.name,
+        ]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+        let cli_args = self
+            .parsed_args()
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(",");
+        if !cli_args.is_empty() {
+            command.push("--args".to_owned());
+            command.push(cli_args);
+        }
+        // we always send a payload, otherwise the CLI would wait for one on STDIN
+        command.push("--".to_owned());
+        command.push(self.payload.clone());
+        command
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Payload,
+    Args,
+    Collection,
+}
+
+impl Field {
+    fn next(&self) -> Self {
+        match self {
+            Field::Name => Field::Payload,
+            Field::Payload => Field::Args,
+            Field::Args => Field::Collection,
+            Field::Collection => Field::Name,
+        }
+    }
+    fn previous(&self) -> Self {
+        match self {
+            Field::Name => Field::Collection,
+            Field::Payload => Field::Name,
+            Field::Args => Field::Payload,
+            Field::Collection => Field::Args,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    // pipe_message_to_plugin, replies sent with cli_pipe_output are not routed back to us
+    PluginMessage,
+    // `zellij pipe` through run_command, its STDOUT is the plugin's cli_pipe_output
+    Cli,
+}
+
+#[derive(Debug)]
+pub struct PipeWorkbench {
+    project_folder: Option<PathBuf>,
+    draft: SavedPipeMessage,
+    focused_field: Field,
+    collection: Vec<SavedPipeMessage>,
+    selected_in_collection: usize,
+    delivery: Delivery,
+    replies: Vec<(String, String)>, // (message name, reply)
+}
+
+impl Default for PipeWorkbench {
+    fn default() -> Self {
+        PipeWorkbench {
+            project_folder: None,
+            draft: Default::default(),
+            focused_field: Field::Name,
+            collection: Default::default(),
+            selected_in_collection: 0,
+            delivery: Delivery::PluginMessage,
+            replies: Default::default(),
+        }
+    }
+}
+
+impl PipeWorkbench {
+    pub fn update_project_folder(&mut self, project_data: &ProjectData, project_folder: PathBuf) {
+        self.collection = project_data
+            .read(&project_folder, "pipe_workbench", "json")
+            .and_then(|collection| serde_json::from_str(&collection).ok())
+            .unwrap_or_default();
+        self.selected_in_collection = 0;
+        self.project_folder = Some(project_folder);
+    }
+    pub fn handle_key(
+        &mut self,
+        host: &mut impl Host,
+        project_data: &ProjectData,
+        key: KeyWithModifier,
+        plugin_url: Option<String>,
+        session_name: Option<&str>,
+    ) -> bool {
+        let mut should_render = true;
+        match key.bare_key {
+            BareKey::Tab if key.has_modifiers(&[KeyModifier::Shift]) => {
+                self.focused_field = self.focused_field.previous();
+            }
+            BareKey::Tab if key.has_no_modifiers() => {
+                self.focused_field = self.focused_field.next();
+            }
+            BareKey::Char('w') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.save_draft(project_data);
+            }
+            BareKey::Char('d') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.delivery = match self.delivery {
+                    Delivery::PluginMessage => Delivery::Cli,
+                    Delivery::Cli => Delivery::PluginMessage,
+                };
+            }
+            BareKey::Enter if key.has_no_modifiers() => {
+                let message = if self.focused_field == Field::Collection {
+                    self.collection.get(self.selected_in_collection).cloned()
+                } else {
+                    Some(self.draft.clone())
+                };
+                if let Some(message) = message {
+                    self.send(host, &message, plugin_url, session_name);
+                }
+            }
+            _ if self.focused_field == Field::Collection => {
+                should_render = self.handle_collection_key(project_data, key);
+            }
+            BareKey::Backspace if key.has_no_modifiers() => {
+                if let Some(text) = self.focused_text_mut() {
+                    text.pop();
+                }
+            }
+            BareKey::Char(character)
+                if !key.has_modifiers(&[KeyModifier::Ctrl])
+                    && !key.has_modifiers(&[KeyModifier::Alt]) =>
+            {
+                if let Some(text) = self.focused_text_mut() {
+                    text.push(character);
+                }
+            }
+            _ => {
+                should_render = false;
+            }
+        }
+        should_render
+    }
+    pub fn handle_cli_pipe_result(
+        &mut self,
+        exit_code: Option<i32>,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        context: BTreeMap<String, String>,
+    ) {
+        let message_name = context.get("message_name").cloned().unwrap_or_default();
+        let reply = if exit_code == Some(0) {
+            let stdout = String::from_utf8_lossy(&stdout).trim_end().to_owned();
+            if stdout.is_empty() {
+                "<no reply>".to_owned()
+            } else {
+                stdout
+            }
+        } else {
+            format!(
+                "<failed: {}>",
+                String::from_utf8_lossy(&stderr)
+                    .trim_end()
+                    .replace('\n', " ")
+            )
+        };
+        self.push_reply(message_name, reply);
+    }
+    pub fn render(&self, y: usize, rows: usize, cols: usize, plugin_url: Option<String>) {
+        let mut y = y;
+        let target = plugin_url.unwrap_or_else(|| "<NO FOLDER SELECTED>".to_owned());
+        let title = format!("Pipe Workbench - sending to {}", target);
+        print_text_with_coordinates(
+            Text::new(truncate(&title, cols)).color_range(2, ..13),
+            0,
+            y,
+            None,
+            None,
+        );
+        y += 2;
+        for (field, label, text) in [
+            (Field::Name, "Name:    ", &self.draft.name),
+            (Field::Payload, "Payload: ", &self.draft.payload),
+            (Field::Args, "Args:    ", &self.draft.args),
+        ]
+        .iter()
+        {
+            let cursor = if self.focused_field == *field {
+                "_"
+            } else {
+                ""
+            };
+            let line = truncate(&format!("{}{}{}", label, text, cursor), cols);
+            let mut line = Text::new(line).color_range(0, ..label.len());
+            if self.focused_field == *field {
+                line = line.selected();
+            }
+            print_text_with_coordinates(line, 0, y, None, None);
+            y += 1;
+        }
+        let delivery = match self.delivery {
+            Delivery::PluginMessage => "Delivery: plugin message (<Ctrl d> to send through the CLI and capture replies)",
+            Delivery::Cli => "Delivery: zellij pipe CLI, capturing replies (<Ctrl d> to send as a plugin message)",
+        };
+        print_text_with_coordinates(
+            Text::new(truncate(delivery, cols)).color_range(0, ..9),
+            0,
+            y,
+            None,
+            None,
+        );
+        y += 2;
+        let collection_title = "Saved messages (<Ctrl w> to save, <e> to edit, <Del> to remove):";
+        let mut collection_title = Text::new(truncate(collection_title, cols)).color_range(2, ..14);
+        if self.focused_field == Field::Collection {
+            collection_title = collection_title.selected();
+        }
+        print_text_with_coordinates(collection_title, 0, y, None, None);
+        y += 1;
+        let replies_height = std::cmp::min(self.replies.len(), MAX_REPLIES) + 1;
+        let available_rows = rows.saturating_sub(y + replies_height + 2);
+        let first_visible = self
+            .selected_in_collection
+            .saturating_sub(available_rows.saturating_sub(1));
+        for (i, message) in self
+            .collection
+            .iter()
+            .enumerate()
+            .skip(first_visible)
+            .take(available_rows)
+        {
+            let line = format!(
+                "  {} | {} | {}",
+                message.name, message.payload, message.args
+            );
+            let mut line = Text::new(truncate(&line, cols))
+                .color_range(0, 2..2 + message.name.chars().count());
+            if self.focused_field == Field::Collection && i == self.selected_in_collection {
+                line = line.selected();
+            }
+            print_text_with_coordinates(line, 0, y, None, None);
+            y += 1;
+        }
+        if self.collection.is_empty() {
+            print_text_with_coordinates(Text::new("  <NONE>"), 0, y, None, None);
+            y += 1;
+        }
+        y += 1;
+        print_text_with_coordinates(Text::new("Replies:").color_range(2, ..), 0, y, None, None);
+        y += 1;
+        for (message_name, reply) in &self.replies {
+            let line = truncate(&format!("  {}: {}", message_name, reply), cols);
+            print_text_with_coordinates(
+                Text::new(line).color_range(0, 2..2 + message_name.chars().count()),
+                0,
+                y,
+                None,
+                None,
+            );
+            y += 1;
+        }
+        let help = "<Tab> next field, <Enter> send, <Esc> back";
+        print_text_with_coordinates(help_text(help, cols), 0, rows.saturating_sub(1), None, None);
+    }
+    fn handle_collection_key(&mut self, project_data: &ProjectData, key: KeyWithModifier) -> bool {
+        let mut should_render = true;
+        match key.bare_key {
+            BareKey::Down if key.has_no_modifiers() => {
+                if self.selected_in_collection + 1 < self.collection.len() {
+                    self.selected_in_collection += 1;
+                }
+            }
+            BareKey::Up if key.has_no_modifiers() => {
+                self.selected_in_collection = self.selected_in_collection.saturating_sub(1);
+            }
+            BareKey::Char('e') if key.has_no_modifiers() => {
+                if let Some(message) = self.collection.get(self.selected_in_collection) {
+                    self.draft = message.clone();
+                    self.focused_field = Field::Name;
+                }
+            }
+            BareKey::Delete | BareKey::Char('d') if key.has_no_modifiers() => {
+                if self.selected_in_collection < self.collection.len() {
+                    self.collection.remove(self.selected_in_collection);
+                    self.selected_in_collection = std::cmp::min(
+                        self.selected_in_collection,
+                        self.collection.len().saturating_sub(1),
+                    );
+                    self.persist_collection(project_data);
+                }
+            }
+            _ => {
+                should_render = false;
+            }
+        }
+        should_render
+    }
+    fn focused_text_mut(&mut self) -> Option<&mut String> {
+        match self.focused_field {
+            Field::Name => Some(&mut self.draft.name),
+            Field::Payload => Some(&mut self.draft.payload),
+            Field::Args => Some(&mut self.draft.args),
+            Field::Collection => None,
+        }
+    }
+    fn save_draft(&mut self, project_data: &ProjectData) {
+        if self.draft.name.is_empty() || self.collection.contains(&self.draft) {
+            return;
+        }
+        self.collection.push(self.draft.clone());
+        self.persist_collection(project_data);
+    }
+    fn persist_collection(&self, project_data: &ProjectData) {
+        if let Some(project_folder) = &self.project_folder {
+            match serde_json::to_string_pretty(&self.collection) {
+                Ok(collection) => {
+                    project_data.write(project_folder, "pipe_workbench", "json", &collection)
+                }
+                Err(e) => eprintln!("Failed to serialize pipe messages: {}", e),
+            }
+        }
+    }
+    fn send(
+        &mut self,
+        host: &mut impl Host,
+        message: &SavedPipeMessage,
+        plugin_url: Option<String>,
+        session_name: Option<&str>,
+    ) {
+        let plugin_url = match plugin_url {
+            Some(plugin_url) => plugin_url,
+            None => {
+                self.push_reply(
+                    message.name.clone(),
+                    "<no plugin folder selected>".to_owned(),
+                );
+                return;
+            }
+        };
+        if message.name.is_empty() {
+            return;
+        }
+        match self.delivery {
+            Delivery::PluginMessage => {
+                host.pipe_message_to_plugin(message.to_plugin_message(plugin_url));
+                self.push_reply(message.name.clone(), "<sent>".to_owned());
+            }
+            Delivery::Cli => {
+                let session_name = match session_name {
+                    Some(session_name) => session_name,
+                    None => {
+                        self.push_reply(message.name.clone(), "<unknown session>".to_owned());
+                        return;
+                    }
+                };
+                let command = message.cli_command(&plugin_url, session_name);
+                let mut context = BTreeMap::new();
+                context.insert("source".to_owned(), "pipe_workbench".to_owned());
+                context.insert("message_name".to_owned(), message.name.clone());
+                host.run_command(command, BTreeMap::new(), PathBuf::from("."), context);
+            }
+        }
+    }
+    fn push_reply(&mut self, message_name: String, reply: String) {
+        self.replies.push((message_name, reply));
+        if self.replies.len() > MAX_REPLIES {
+            self.replies.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> SavedPipeMessage {
+        SavedPipeMessage {
+            name: "greet".to_owned(),
+            payload: "hello there".to_owned(),
+            args: " to = me, from=you,ignored".to_owned(),
+        }
+    }
+
+    #[test]
+    fn saved_messages_are_kept_per_project() {
+        let data_folder =
+            std::env::temp_dir().join(format!("zellij-plugin-dev-{}", uuid::Uuid::new_v4()));
+        let project_data = ProjectData::new(data_folder.clone());
+        let project_folder = PathBuf::from("/home/me/my_plugin");
+        let mut workbench = PipeWorkbench::default();
+        workbench.update_project_folder(&project_data, project_folder.clone());
+        assert_eq!(workbench.collection, vec![]);
+        workbench.draft.name = "greet".to_owned();
+        workbench.save_draft(&project_data);
+        // saving the same message twice keeps one copy
+        workbench.save_draft(&project_data);
+        let mut reopened = PipeWorkbench::default();
+        reopened.update_project_folder(&project_data, project_folder);
+        assert_eq!(
+            reopened.collection,
+            vec![SavedPipeMessage {
+                name: "greet".to_owned(),
+                ..Default::default()
+            }]
+        );
+        let mut other_project = PipeWorkbench::default();
+        other_project.update_project_folder(&project_data, PathBuf::from("/home/me/other_plugin"));
+        assert_eq!(other_project.collection, vec![]);
+        let _ = std::fs::remove_dir_all(data_folder);
+    }
+
+    #[test]
+    fn a_plugin_message_carries_the_payload_and_args() {
+        let message_to_plugin = message().to_plugin_message("file:/p.wasm".to_owned());
+        assert_eq!(message_to_plugin.message_name, "greet");
+        assert_eq!(
+            message_to_plugin.plugin_url.as_deref(),
+            Some("file:/p.wasm")
+        );
+        assert_eq!(
+            message_to_plugin.message_payload.as_deref(),
+            Some("hello there")
+        );
+        assert_eq!(
+            message_to_plugin.message_args.get("to").map(|a| a.as_str()),
+            Some("me")
+        );
+        assert_eq!(message_to_plugin.message_args.len(), 2);
+        let without_payload = SavedPipeMessage {
+            payload: String::new(),
+            ..message()
+        };
+        assert_eq!(
+            without_payload
+                .to_plugin_message("file:/p.wasm".to_owned())
+                .message_payload,
+            None
+        );
+    }
+
+    #[test]
+    fn the_cli_command_always_sends_a_payload() {
+        assert_eq!(
+            message().cli_command("file:/p.wasm", "dev"),
+            vec![
+                "zellij",
+                "--session",
+                "dev",
+                "pipe",
+                "--plugin",
+                "file:/p.wasm",
+                "--name",
+                "greet",
+                "--args",
+                "from=you,to=me",
+                "--",
+                "hello there",
+            ]
+        );
+        let bare = SavedPipeMessage {
+            name: "ping".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            bare.cli_command("file:/p.wasm", "dev")[6..],
+            ["--name", "ping", "--", ""]
+        );
+    }
+
+    #[test]
+    fn cli_replies_and_failures_are_listed() {
+        let mut workbench = PipeWorkbench::default();
+        let mut context = BTreeMap::new();
+        context.insert("message_name".to_owned(), "greet".to_owned());
+        workbench.handle_cli_pipe_result(Some(0), b"hi\n".to_vec(), vec![], context.clone());
+        workbench.handle_cli_pipe_result(Some(0), vec![], vec![], context.clone());
+        workbench.handle_cli_pipe_result(Some(1), vec![], b"no\nsession".to_vec(), context);
+        assert_eq!(
+            workbench.replies,
+            vec![
+                ("greet".to_owned(), "hi".to_owned()),
+                ("greet".to_owned(), "<no reply>".to_owned()),
+                ("greet".to_owned(), "<failed: no session>".to_owned()),
+            ]
+        );
+    }
+}
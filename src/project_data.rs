@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+
+const DATA_FOLDER: &str = "/data";
+
+// the plugin's /data folder is shared between all the projects we develop, so we keep a file per
+// project in a folder per feature (eg. /data/pipe_workbench/_home_me_my_plugin.json)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectData {
+    data_folder: PathBuf,
+}
+
+impl Default for ProjectData {
+    fn default() -> Self {
+        ProjectData::new(PathBuf::from(DATA_FOLDER))
+    }
+}
+
+impl ProjectData {
+    pub fn new(data_folder: PathBuf) -> Self {
+        ProjectData { data_folder }
+    }
+    fn file(&self, project_folder: &Path, feature: &str, extension: &str) -> PathBuf {
+        let project_key: String = project_folder
+            .display()
+            .to_string()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        self.data_folder
+            .join(feature)
+            .join(format!("{}.{}", project_key, extension))
+    }
+    pub fn read(&self, project_folder: &Path, feature: &str, extension: &str) -> Option<String> {
+        std::fs::read_to_string(self.file(project_folder, feature, extension)).ok()
+    }
+    pub fn write(&self, project_folder: &Path, feature: &str, extension: &str, contents: &str) {
+        let path = self.file(project_folder, feature, extension);
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                eprintln!("Failed to create {}: {}", parent.display(), e);
+                return;
+            }
+        }
+        if let Err(e) = std::fs::write(&path, contents) {
+            eprintln!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+}
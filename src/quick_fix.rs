@@ -0,0 +1,249 @@
+use crate::cargo_messages::Diagnostic;
+use crate::host_fs::HostFs;
+use crate::snapshots::{diff_lines, DiffLine};
+
+use std::path::{Path, PathBuf};
+
+// a replacement rustc suggests and is sure about (MachineApplicable), eg. removing an unused import
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub message: String, // eg. "remove the unused import"
+    pub edits: Vec<Edit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub file_name: String, // relative to the workspace root
+    pub line_start: usize,
+    pub column_start: usize, // 1-based, in characters
+    pub line_end: usize,
+    pub column_end: usize,
+    pub replacement: String,
+    pub expected_lines: Vec<String>, // what rustc saw, so we don't edit a file that changed since
+}
+
+impl Suggestion {
+    pub fn of(diagnostic: &Diagnostic) -> Option<Suggestion> {
+        std::iter::once(diagnostic)
+            .chain(diagnostic.children.iter())
+            .find_map(|d| {
+                let spans: Vec<_> = d
+                    .spans
+                    .iter()
+                    .filter(|s| s.suggested_replacement.is_some())
+                    .collect();
+                let machine_applicable = spans
+                    .iter()
+                    .all(|s| s.suggestion_applicability.as_deref() == Some("MachineApplicable"));
+                if spans.is_empty() || !machine_applicable {
+                    return None;
+                }
+                Some(Suggestion {
+                    message: d.message.clone(),
+                    edits: spans
+                        .iter()
+                        .map(|s| Edit {
+                            file_name: s.file_name.clone(),
+                            line_start: s.line_start,
+                            column_start: s.column_start,
+                            line_end: s.line_end,
+                            column_end: s.column_end,
+                            replacement: s.suggested_replacement.clone().unwrap_or_default(),
+                            expected_lines: s.text.iter().map(|t| t.text.clone()).collect(),
+                        })
+                        .collect(),
+                })
+            })
+    }
+    // the files the suggestion changes, in the order rustc mentioned them
+    fn file_names(&self) -> Vec<&str> {
+        let mut file_names: Vec<&str> = vec![];
+        for edit in &self.edits {
+            if !file_names.contains(&edit.file_name.as_str()) {
+                file_names.push(&edit.file_name);
+            }
+        }
+        file_names
+    }
+    fn edits_of(&self, file_name: &str) -> Vec<&Edit> {
+        self.edits
+            .iter()
+            .filter(|e| e.file_name == file_name)
+            .collect()
+    }
+    // the changed lines of every file
+    pub fn preview(
+        &self,
+        workspace_root: &Path,
+        host_fs: &HostFs,
+    ) -> Result<Vec<(String, Vec<DiffLine>)>, String> {
+        self.file_names()
+            .into_iter()
+            .map(|file_name| {
+                let content = host_fs.read_to_string(&workspace_root.join(file_name))?;
+                let diff = preview_edits(&content, &self.edits_of(file_name))?;
+                Ok((file_name.to_owned(), diff))
+            })
+            .collect()
+    }
+    pub fn apply(&self, workspace_root: &Path, host_fs: &HostFs) -> Result<(), String> {
+        // we check every file before writing any, so that it's all or nothing (unless writing fails)
+        let mut changed_files: Vec<(PathBuf, String)> = vec![];
+        for file_name in self.file_names() {
+            let path = workspace_root.join(file_name);
+            let content = host_fs.read_to_string(&path)?;
+            let changed = apply_edits(&content, &self.edits_of(file_name))?;
+            changed_files.push((path, changed));
+        }
+        for (path, changed) in changed_files {
+            host_fs.write(&path, &changed)?;
+        }
+        Ok(())
+    }
+}
+
+fn apply_edits(content: &str, edits: &[&Edit]) -> Result<String, String> {
+    let line_offsets: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let lines: Vec<&str> = content.split('\n').collect();
+    let offset_of = |line: usize, column: usize| -> Option<usize> {
+        let line_text = lines.get(line.checked_sub(1)?)?;
+        let column_offset = if column <= 1 {
+            0
+        } else {
+            line_text
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(line_text.len()))
+                .nth(column - 1)?
+        };
+        Some(line_offsets[line - 1] + column_offset)
+    };
+    let mut ranges = vec![];
+    for edit in edits {
+        for (i, expected_line) in edit.expected_lines.iter().enumerate() {
+            let line = lines
+                .get(edit.line_start - 1 + i)
+                .map(|l| l.trim_end_matches('\r'));
+            if line != Some(expected_line.as_str()) {
+                return Err(format!(
+                    "{} changed since it was compiled, rebuild first",
+                    edit.file_name
+                ));
+            }
+        }
+        let start = offset_of(edit.line_start, edit.column_start);
+        let end = offset_of(edit.line_end, edit.column_end);
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => ranges.push((start, end, edit)),
+            _ => return Err(format!("The suggestion is outside of {}", edit.file_name)),
+        }
+    }
+    // from the end of the file, so that the offsets of the other edits stay put
+    ranges.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+    let mut changed = content.to_owned();
+    let mut previous_start = content.len();
+    for (start, end, edit) in ranges {
+        if end > previous_start {
+            return Err("The suggestion's replacements overlap".to_owned());
+        }
+        changed.replace_range(start..end, &edit.replacement);
+        previous_start = start;
+    }
+    Ok(changed)
+}
+
+// the lines the edits touch, before and after
+fn preview_edits(content: &str, edits: &[&Edit]) -> Result<Vec<DiffLine>, String> {
+    let changed = apply_edits(content, edits)?;
+    let first_line = edits.iter().map(|e| e.line_start).min().unwrap_or(1);
+    // a span that ends at the start of a line (eg. removing whole lines) doesn't touch that line
+    let last_line = edits
+        .iter()
+        .map(|e| match e.column_end {
+            1 if e.line_end > e.line_start => e.line_end - 1,
+            _ => e.line_end,
+        })
+        .max()
+        .unwrap_or(1);
+    let old_lines: Vec<&str> = content.split('\n').collect();
+    let new_lines: Vec<&str> = changed.split('\n').collect();
+    // the lines after the edits only moved
+    let new_last_line = (last_line + new_lines.len()).saturating_sub(old_lines.len());
+    let block = |lines: &[&str], last_line: usize| -> String {
+        lines
+            .iter()
+            .skip(first_line - 1)
+            .take((last_line + 1).saturating_sub(first_line))
+            .copied()
+            .collect::<Vec<&str>>()
+            .join("\n")
+    };
+    Ok(diff_lines(
+        &block(&old_lines, last_line),
+        &block(&new_lines, new_last_line),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unused_import() -> Diagnostic {
+        serde_json::from_str(
+            r#"{"message":"unused import: `std::fmt`","level":"warning","spans":[],"children":[
+                {"message":"the following lint is on by default","level":"note","spans":[]},
+                {"message":"remove the unused import","level":"help","spans":[{
+                    "file_name":"src/main.rs","line_start":2,"line_end":3,"column_start":1,"column_end":1,
+                    "is_primary":true,"text":[{"text":"use std::fmt;","highlight_start":1,"highlight_end":14}],
+                    "suggested_replacement":"","suggestion_applicability":"MachineApplicable"}]}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn machine_applicable_suggestions_are_applied() {
+        let suggestion = Suggestion::of(&unused_import()).unwrap();
+        assert_eq!(suggestion.message, "remove the unused import");
+        let content = "use std::path::Path;\nuse std::fmt;\nfn main() {}\n";
+        let edits = suggestion.edits_of("src/main.rs");
+        assert_eq!(
+            apply_edits(content, &edits),
+            Ok("use std::path::Path;\nfn main() {}\n".to_owned())
+        );
+        assert_eq!(
+            preview_edits(content, &edits),
+            Ok(vec![DiffLine::Removed("use std::fmt;".to_owned())])
+        );
+        assert!(apply_edits("use std::path::Path;\nuse std::io;\n", &edits).is_err());
+    }
+
+    #[test]
+    fn replacements_use_character_columns() {
+        let edit = Edit {
+            file_name: "src/main.rs".to_owned(),
+            line_start: 1,
+            column_start: 10,
+            line_end: 1,
+            column_end: 11,
+            replacement: "y".to_owned(),
+            expected_lines: vec!["let é = (x);".to_owned()],
+        };
+        assert_eq!(
+            apply_edits("let é = (x);\n", &[&edit]),
+            Ok("let é = (y);\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn maybe_incorrect_suggestions_are_not_offered() {
+        let diagnostic = unused_import();
+        let json = serde_json::to_string(&diagnostic)
+            .unwrap()
+            .replace("MachineApplicable", "MaybeIncorrect");
+        let diagnostic: Diagnostic = serde_json::from_str(&json).unwrap();
+        assert_eq!(Suggestion::of(&diagnostic), None);
+    }
+}
@@ -0,0 +1,310 @@
+use zellij_tile::prelude::*;
+
+use crate::ui::{help_text, truncate};
+
+use std::time::{Duration, Instant};
+
+const MAX_ITERATIONS: usize = 100;
+const ROLLING_AVERAGE_OF: usize = 10;
+
+#[derive(Debug, Clone)]
+struct PendingIteration {
+    key_pressed: Instant,
+    build_finished: Option<Instant>,
+    reload_requested: Option<Instant>,
+    pane_before_reload: Option<PaneState>,
+    pane_changed: bool, // zellij reported the plugin pane differently since the reload
+}
+
+// what zellij tells us about the plugin pane that the reload changes, the pane being visible
+// before the reload doesn't mean the reloaded plugin was loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PaneState {
+    id: u32,
+    title: String,
+    is_suppressed: bool,
+    exited: bool,
+}
+
+impl PaneState {
+    fn new(pane: &PaneInfo) -> Self {
+        PaneState {
+            id: pane.id,
+            title: pane.title.clone(),
+            is_suppressed: pane.is_suppressed,
+            exited: pane.exited,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iteration {
+    pub build: Duration,  // key press until the compilation pane exited
+    pub reload: Duration, // start_or_reload_plugin until zellij showed the reloaded plugin pane
+    pub total: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct ReloadTiming {
+    pending: Option<PendingIteration>,
+    iterations: Vec<Iteration>,
+}
+
+impl ReloadTiming {
+    pub fn key_pressed(&mut self) {
+        self.pending = Some(PendingIteration {
+            key_pressed: Instant::now(),
+            build_finished: None,
+            reload_requested: None,
+            pane_before_reload: None,
+            pane_changed: false,
+        });
+    }
+    pub fn build_finished(&mut self, plugin_reloaded: bool) {
+        let now = Instant::now();
+        if !plugin_reloaded {
+            // the build failed, this loop doesn't count
+            self.pending = None;
+            return;
+        }
+        if let Some(pending) = self.pending.as_mut() {
+            pending.build_finished = Some(now);
+        }
+    }
+    pub fn reload_requested(&mut self, plugin_pane: Option<&PaneInfo>) {
+        if let Some(pending) = self.pending.as_mut() {
+            pending.reload_requested = Some(Instant::now());
+            pending.pane_before_reload = plugin_pane.map(PaneState::new);
+        }
+    }
+    // returns true if this completed an iteration
+    pub fn plugin_pane_updated(&mut self, plugin_pane: Option<&PaneInfo>) -> bool {
+        let plugin_is_visible = plugin_pane.map(|p| !p.is_suppressed).unwrap_or(false);
+        if let Some(pending) = self.pending.as_mut() {
+            if pending.reload_requested.is_some()
+                && plugin_pane.map(PaneState::new) != pending.pane_before_reload
+            {
+                pending.pane_changed = true;
+            }
+        }
+        match self.pending {
+            Some(PendingIteration {
+                key_pressed,
+                build_finished: Some(build_finished),
+                reload_requested: Some(reload_requested),
+                pane_changed: true,
+                ..
+            }) if plugin_is_visible => {
+                let now = Instant::now();
+                self.iterations.push(Iteration {
+                    build: build_finished.duration_since(key_pressed),
+                    reload: now.duration_since(reload_requested),
+                    total: now.duration_since(key_pressed),
+                });
+                if self.iterations.len() > MAX_ITERATIONS {
+                    self.iterations.remove(0);
+                }
+                self.pending = None;
+                true
+            }
+            _ => false,
+        }
+    }
+    pub fn last_iteration(&self) -> Option<&Iteration> {
+        self.iterations.last()
+    }
+    pub fn rolling_average(&self) -> Option<Iteration> {
+        let last_iterations: Vec<&Iteration> = self
+            .iterations
+            .iter()
+            .rev()
+            .take(ROLLING_AVERAGE_OF)
+            .collect();
+        if last_iterations.is_empty() {
+            return None;
+        }
+        let count = last_iterations.len() as u32;
+        Some(Iteration {
+            build: last_iterations.iter().map(|i| i.build).sum::<Duration>() / count,
+            reload: last_iterations.iter().map(|i| i.reload).sum::<Duration>() / count,
+            total: last_iterations.iter().map(|i| i.total).sum::<Duration>() / count,
+        })
+    }
+    pub fn summary(&self) -> Option<String> {
+        let last_iteration = self.last_iteration()?;
+        let average = self.rolling_average()?;
+        Some(format!(
+            "Last reload: {} (build {}, load {}), average: {}",
+            format_duration(last_iteration.total),
+            format_duration(last_iteration.build),
+            format_duration(last_iteration.reload),
+            format_duration(average.total),
+        ))
+    }
+    pub fn render(&self, y: usize, rows: usize, cols: usize) {
+        print_text_with_coordinates(
+            Text::new(truncate("Reload Timing", cols)).color_range(2, ..),
+            0,
+            y,
+            None,
+            None,
+        );
+        let header = format!("{:>4}  {:>9}  {:>9}  {:>9}", "#", "build", "load", "total");
+        print_text_with_coordinates(
+            Text::new(truncate(&header, cols)).color_range(0, ..),
+            0,
+            y + 2,
+            None,
+            None,
+        );
+        let mut y = y + 3;
+        if let Some(average) = self.rolling_average() {
+            let count = std::cmp::min(self.iterations.len(), ROLLING_AVERAGE_OF);
+            let line = format!(
+                "{:>4}  {:>9}  {:>9}  {:>9}  (average of the last {})",
+                "avg",
+                format_duration(average.build),
+                format_duration(average.reload),
+                format_duration(average.total),
+                count
+            );
+            print_text_with_coordinates(
+                Text::new(truncate(&line, cols)).color_range(2, ..),
+                0,
+                y,
+                None,
+                None,
+            );
+            y += 1;
+        } else {
+            print_text_with_coordinates(
+                Text::new(truncate("<NO RELOADS YET>", cols)),
+                0,
+                y,
+                None,
+                None,
+            );
+        }
+        let available_rows = rows.saturating_sub(y + 1);
+        let first_number = self.iterations.len();
+        for (i, iteration) in self
+            .iterations
+            .iter()
+            .rev()
+            .take(available_rows)
+            .enumerate()
+        {
+            let line = format!(
+                "{:>4}  {:>9}  {:>9}  {:>9}",
+                first_number - i,
+                format_duration(iteration.build),
+                format_duration(iteration.reload),
+                format_duration(iteration.total)
+            );
+            print_text_with_coordinates(Text::new(truncate(&line, cols)), 0, y + i, None, None);
+        }
+        print_text_with_coordinates(
+            help_text("<Esc> back", cols),
+            0,
+            rows.saturating_sub(1),
+            None,
+            None,
+        );
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() >= 60 {
+        format!(
+            "{}m{:02}s",
+            duration.as_secs() / 60,
+            duration.as_secs() % 60
+        )
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin_pane(title: &str) -> PaneInfo {
+        PaneInfo {
+            id: 2,
+            is_plugin: true,
+            title: title.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn iteration(secs: u64) -> Iteration {
+        Iteration {
+            build: Duration::from_secs(secs),
+            reload: Duration::from_secs(1),
+            total: Duration::from_secs(secs + 1),
+        }
+    }
+
+    #[test]
+    fn the_load_ends_when_the_reloaded_pane_changes_rather_than_on_any_update() {
+        let mut reload_timing = ReloadTiming::default();
+        let before = plugin_pane("file:/p.wasm");
+        reload_timing.key_pressed();
+        reload_timing.build_finished(true);
+        reload_timing.reload_requested(Some(&before));
+        // an update about another pane, the plugin pane is as it was before the reload
+        assert!(!reload_timing.plugin_pane_updated(Some(&before)));
+        assert!(reload_timing.last_iteration().is_none());
+        let suppressed = PaneInfo {
+            is_suppressed: true,
+            ..before.clone()
+        };
+        assert!(!reload_timing.plugin_pane_updated(Some(&suppressed)));
+        assert!(reload_timing.plugin_pane_updated(Some(&before)));
+        assert!(reload_timing.last_iteration().is_some());
+    }
+
+    #[test]
+    fn a_plugin_that_wasnt_running_is_loaded_once_its_pane_is_there() {
+        let mut reload_timing = ReloadTiming::default();
+        reload_timing.key_pressed();
+        reload_timing.build_finished(true);
+        reload_timing.reload_requested(None);
+        assert!(!reload_timing.plugin_pane_updated(None));
+        assert!(reload_timing.plugin_pane_updated(Some(&plugin_pane("my plugin"))));
+    }
+
+    #[test]
+    fn failed_builds_dont_count() {
+        let mut reload_timing = ReloadTiming::default();
+        reload_timing.key_pressed();
+        reload_timing.build_finished(false);
+        reload_timing.reload_requested(None);
+        assert!(!reload_timing.plugin_pane_updated(Some(&plugin_pane("my plugin"))));
+        assert_eq!(reload_timing.summary(), None);
+    }
+
+    #[test]
+    fn the_average_is_of_the_last_iterations() {
+        let mut reload_timing = ReloadTiming::default();
+        assert_eq!(reload_timing.rolling_average(), None);
+        reload_timing.iterations = (0..ROLLING_AVERAGE_OF as u64 + 2).map(iteration).collect();
+        // the last ten builds took 2 to 11 seconds
+        assert_eq!(
+            reload_timing.rolling_average(),
+            Some(Iteration {
+                build: Duration::from_millis(6500),
+                reload: Duration::from_secs(1),
+                total: Duration::from_millis(7500),
+            })
+        );
+    }
+
+    #[test]
+    fn durations_are_in_seconds_or_minutes() {
+        assert_eq!(format_duration(Duration::from_millis(1250)), "1.2s");
+        assert_eq!(format_duration(Duration::from_secs(59)), "59.0s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m05s");
+    }
+}
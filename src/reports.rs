@@ -0,0 +1,549 @@
+use crate::build_progress::plural;
+use crate::build_worker::{parse_messages, WorkerRequest};
+use crate::cargo_messages::Diagnostic;
+use crate::host::Host;
+use crate::host_fs::HostFs;
+use crate::run_and_reload::{BUILD_MESSAGES_FILE, CLIPPY_MESSAGES_FILE, TEST_OUTPUT_FILE};
+
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+pub const SARIF_FILE: &str = "diagnostics.sarif";
+pub const JUNIT_FILE: &str = "junit.xml";
+const BUILD_LEFT_OUT: &str =
+    "the build was left out, its messages are only kept when cargo builds with build_progress on";
+
+// writes the latest build, clippy and test results we kept in the dev folder as SARIF (for code
+// review tools) and JUnit XML (for dashboards), the worker does the work
+#[derive(Debug, Default)]
+pub struct ReportExport {
+    status: Option<Result<String, String>>,
+}
+
+impl ReportExport {
+    pub fn export(
+        &mut self,
+        host: &mut impl Host,
+        dev_directory: Option<PathBuf>,
+        workspace_root: Option<PathBuf>,
+        build_messages_kept: bool, // whether the builds write their messages to the dev folder
+        host_fs: &HostFs,
+    ) {
+        match (dev_directory, workspace_root) {
+            (Some(dev_directory), Some(workspace_root)) => {
+                WorkerRequest::ExportReports {
+                    dev_directory,
+                    workspace_root,
+                    build_messages_kept,
+                    host_fs: host_fs.clone(),
+                }
+                .post(host);
+                self.status = Some(Ok("exporting...".to_owned()));
+            }
+            _ => self.status = Some(Err("No folder selected".to_owned())),
+        }
+    }
+    pub fn result_received(&mut self, payload: &str) {
+        self.status = Some(match serde_json::from_str(payload) {
+            Ok(result) => result,
+            Err(e) => Err(format!("Failed to parse the export result: {}", e)),
+        });
+    }
+    pub fn get_status(&self) -> Option<&Result<String, String>> {
+        self.status.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestSuite {
+    pub name: String, // eg. "unittests src/main.rs"
+    pub cases: Vec<TestCase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    pub name: String,
+    pub outcome: TestOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestOutcome {
+    Passed,
+    Failed(String), // what the test printed, eg. its panic
+    Ignored,
+}
+
+// runs in the worker, returns what was exported
+pub fn export_reports(
+    dev_directory: &Path,
+    workspace_root: &Path,
+    build_messages_kept: bool,
+    host_fs: &HostFs,
+) -> Result<String, String> {
+    let read = |file_name: &str| host_fs.read_to_string(&dev_directory.join(file_name)).ok();
+    let mut exported = vec![];
+    let mut runs = vec![];
+    let mut suites = vec![];
+    for (tool, file_name, description) in [
+        ("rustc", BUILD_MESSAGES_FILE, "the build"),
+        ("clippy", CLIPPY_MESSAGES_FILE, "clippy"),
+    ] {
+        // those of a build that streamed its progress before it was turned off would be stale
+        if file_name == BUILD_MESSAGES_FILE && !build_messages_kept {
+            continue;
+        }
+        if let Some(messages) = read(file_name) {
+            let update = parse_messages(&messages);
+            let error_count = update.diagnostics.iter().filter(|d| d.is_error()).count();
+            exported.push(format!(
+                "{} ({}, {})",
+                description,
+                plural(error_count, "error"),
+                plural(update.diagnostics.len() - error_count, "warning")
+            ));
+            suites.push(compiler_suite(tool, &update.diagnostics));
+            runs.push((tool, update.diagnostics));
+        }
+    }
+    if let Some(output) = read(TEST_OUTPUT_FILE) {
+        let test_suites = parse_test_output(&output);
+        let cases: Vec<&TestCase> = test_suites.iter().flat_map(|s| &s.cases).collect();
+        let failed_count = cases
+            .iter()
+            .filter(|c| matches!(c.outcome, TestOutcome::Failed(_)))
+            .count();
+        exported.push(format!(
+            "the tests ({}, {} failed)",
+            plural(cases.len(), "test"),
+            failed_count
+        ));
+        suites.extend(test_suites);
+    }
+    let left_out = if build_messages_kept {
+        String::new()
+    } else {
+        format!(" ({})", BUILD_LEFT_OUT)
+    };
+    if exported.is_empty() {
+        return Err(format!(
+            "Nothing to export yet, build, run clippy or run the tests first{}",
+            left_out
+        ));
+    }
+    host_fs.write(
+        &dev_directory.join(SARIF_FILE),
+        &sarif_report(workspace_root, &runs),
+    )?;
+    host_fs.write(&dev_directory.join(JUNIT_FILE), &junit_report(&suites))?;
+    Ok(format!(
+        "exported {} to {} and {}{}",
+        exported.join(", "),
+        dev_directory.join(SARIF_FILE).display(),
+        JUNIT_FILE,
+        left_out
+    ))
+}
+
+// cargo test's output (with cargo's own lines, which name the test binaries), eg.
+//      Running unittests src/main.rs (target/debug/deps/my_plugin-1234)
+// running 2 tests
+// test tests::parses ... ok
+// test tests::renders ... FAILED
+pub fn parse_test_output(output: &str) -> Vec<TestSuite> {
+    let mut suites: Vec<TestSuite> = vec![];
+    let mut suite_name = None;
+    let mut failure: Option<(String, Vec<&str>)> = None; // the test whose output we're reading
+    let mut errors = vec![];
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if failure.is_some() && (trimmed == "failures:" || trimmed.starts_with("---- ")) {
+            if let (Some((name, output)), Some(suite)) = (failure.take(), suites.last_mut()) {
+                for case in suite.cases.iter_mut().filter(|c| c.name == name) {
+                    case.outcome = TestOutcome::Failed(output.join("\n").trim().to_owned());
+                }
+            }
+        }
+        if let Some((_, output)) = failure.as_mut() {
+            output.push(line);
+        } else if let Some(running) = trimmed.strip_prefix("Running ") {
+            suite_name = Some(running.split(" (").next().unwrap_or(running).to_owned());
+        } else if let Some(crate_name) = trimmed.strip_prefix("Doc-tests ") {
+            suite_name = Some(format!("doc-tests {}", crate_name));
+        } else if trimmed.starts_with("running ") && trimmed.contains(" test") {
+            suites.push(TestSuite {
+                name: suite_name
+                    .take()
+                    .unwrap_or_else(|| format!("tests {}", suites.len() + 1)),
+                cases: vec![],
+            });
+        } else if let Some(name) = trimmed
+            .strip_prefix("---- ")
+            .and_then(|l| l.strip_suffix(" stdout ----"))
+        {
+            failure = Some((name.to_owned(), vec![]));
+        } else if let Some((name, result)) = line
+            .strip_prefix("test ")
+            .and_then(|l| l.rsplit_once(" ... "))
+        {
+            let outcome = match result {
+                "ok" => TestOutcome::Passed,
+                "FAILED" => TestOutcome::Failed(String::new()),
+                result if result.starts_with("ignored") => TestOutcome::Ignored,
+                _ => continue, // eg. a benchmark
+            };
+            if let Some(suite) = suites.last_mut() {
+                suite.cases.push(TestCase {
+                    name: name.to_owned(),
+                    outcome,
+                });
+            }
+        } else if trimmed.starts_with("error") {
+            errors.push(trimmed);
+        }
+    }
+    // the tests didn't compile
+    if suites.is_empty() && !errors.is_empty() {
+        suites.push(TestSuite {
+            name: "cargo test".to_owned(),
+            cases: vec![TestCase {
+                name: "compile".to_owned(),
+                outcome: TestOutcome::Failed(errors.join("\n")),
+            }],
+        });
+    }
+    suites
+}
+
+// JUnit has no warnings, so the build (or clippy) passes unless it has errors, like cargo
+fn compiler_suite(tool: &str, diagnostics: &[Diagnostic]) -> TestSuite {
+    let mut cases: Vec<TestCase> = diagnostics
+        .iter()
+        .filter(|d| d.is_error())
+        .map(|error| TestCase {
+            name: error.summary(),
+            outcome: TestOutcome::Failed(
+                error
+                    .rendered
+                    .clone()
+                    .unwrap_or_else(|| error.message.clone()),
+            ),
+        })
+        .collect();
+    if cases.is_empty() {
+        cases.push(TestCase {
+            name: "no errors".to_owned(),
+            outcome: TestOutcome::Passed,
+        });
+    }
+    TestSuite {
+        name: tool.to_owned(),
+        cases,
+    }
+}
+
+pub fn junit_report(suites: &[TestSuite]) -> String {
+    let count = |suite: &TestSuite, failed: bool| {
+        suite
+            .cases
+            .iter()
+            .filter(|c| match c.outcome {
+                TestOutcome::Failed(_) => failed,
+                TestOutcome::Ignored => !failed,
+                TestOutcome::Passed => false,
+            })
+            .count()
+    };
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+        suites.iter().map(|s| s.cases.len()).sum::<usize>(),
+        suites.iter().map(|s| count(s, true)).sum::<usize>(),
+        suites.iter().map(|s| count(s, false)).sum::<usize>(),
+    ));
+    for suite in suites {
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+            xml_escape(&suite.name),
+            suite.cases.len(),
+            count(suite, true),
+            count(suite, false),
+        ));
+        for case in &suite.cases {
+            let attributes = format!(
+                "name=\"{}\" classname=\"{}\"",
+                xml_escape(&case.name),
+                xml_escape(&suite.name)
+            );
+            match &case.outcome {
+                TestOutcome::Passed => xml.push_str(&format!("    <testcase {}/>\n", attributes)),
+                TestOutcome::Ignored => xml.push_str(&format!(
+                    "    <testcase {}>\n      <skipped/>\n    </testcase>\n",
+                    attributes
+                )),
+                TestOutcome::Failed(output) => {
+                    let message = output
+                        .lines()
+                        .find(|l| !l.trim().is_empty())
+                        .unwrap_or("failed");
+                    xml.push_str(&format!(
+                        "    <testcase {}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                        attributes,
+                        xml_escape(message),
+                        xml_escape(output)
+                    ));
+                }
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+// a run per tool, with the files of the workspace relative to its root
+pub fn sarif_report(workspace_root: &Path, runs: &[(&str, Vec<Diagnostic>)]) -> String {
+    let runs: Vec<serde_json::Value> = runs
+        .iter()
+        .map(|(tool, diagnostics)| {
+            let rule_ids: BTreeSet<&str> = diagnostics
+                .iter()
+                .filter_map(|d| d.code.as_ref().map(|c| c.code.as_str()))
+                .collect();
+            let results: Vec<serde_json::Value> = diagnostics.iter().map(sarif_result).collect();
+            json!({
+                "tool": {
+                    "driver": {
+                        "name": tool,
+                        "informationUri": "https://www.rust-lang.org",
+                        "rules": rule_ids.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                    }
+                },
+                "originalUriBaseIds": {
+                    "SRCROOT": { "uri": format!("{}/", file_uri(workspace_root)) }
+                },
+                "results": results,
+            })
+        })
+        .collect();
+    let report = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": runs,
+    });
+    serde_json::to_string_pretty(&report).unwrap_or_default()
+}
+
+fn sarif_result(diagnostic: &Diagnostic) -> serde_json::Value {
+    let location = |span: &crate::cargo_messages::DiagnosticSpan| {
+        let artifact_location = if Path::new(&span.file_name).is_absolute() {
+            json!({ "uri": file_uri(Path::new(&span.file_name)) })
+        } else {
+            json!({ "uri": span.file_name, "uriBaseId": "SRCROOT" })
+        };
+        let mut location = json!({
+            "physicalLocation": {
+                "artifactLocation": artifact_location,
+                "region": {
+                    "startLine": span.line_start,
+                    "startColumn": span.column_start,
+                    "endLine": span.line_end,
+                    "endColumn": span.column_end,
+                }
+            }
+        });
+        if let Some(label) = span.label.as_ref().filter(|l| !l.is_empty()) {
+            location["message"] = json!({ "text": label });
+        }
+        location
+    };
+    let level = if diagnostic.is_error() {
+        "error"
+    } else if diagnostic.is_warning() {
+        "warning"
+    } else {
+        "note"
+    };
+    let mut result = json!({
+        "level": level,
+        "message": { "text": diagnostic.message },
+        "locations": diagnostic.spans.iter().filter(|s| s.is_primary).map(location).collect::<Vec<_>>(),
+        "relatedLocations": diagnostic.spans.iter().filter(|s| !s.is_primary).map(location).collect::<Vec<_>>(),
+    });
+    if let Some(code) = &diagnostic.code {
+        result["ruleId"] = json!(code.code);
+    }
+    result
+}
+
+fn file_uri(path: &Path) -> String {
+    let path = path.display().to_string();
+    let mut uri = String::from("file://");
+    for c in path.chars() {
+        match c {
+            ' ' => uri.push_str("%20"),
+            '%' => uri.push_str("%25"),
+            '#' => uri.push_str("%23"),
+            '?' => uri.push_str("%3F"),
+            c => uri.push(c),
+        }
+    }
+    uri.trim_end_matches('/').to_owned()
+}
+
+// XML 1.0 doesn't allow most control characters, even escaped (eg. the escape codes of colors)
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::RecordingHost;
+
+    const TEST_OUTPUT: &str = "   Compiling my-plugin v0.1.0
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 1.00s
+     Running unittests src/main.rs (target/debug/deps/my_plugin-1234)
+
+running 3 tests
+test tests::parses ... ok
+test tests::renders ... FAILED
+test tests::slow ... ignored, takes a minute
+
+failures:
+
+---- tests::renders stdout ----
+thread 'tests::renders' panicked at src/main.rs:10:5:
+assertion `left == right` failed
+
+failures:
+    tests::renders
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+
+    #[test]
+    fn test_output_becomes_suites_with_the_failures_output() {
+        let suites = parse_test_output(TEST_OUTPUT);
+        assert_eq!(suites.len(), 1);
+        assert_eq!(suites[0].name, "unittests src/main.rs");
+        assert_eq!(suites[0].cases[0].outcome, TestOutcome::Passed);
+        assert_eq!(
+            suites[0].cases[1].outcome,
+            TestOutcome::Failed(
+                "thread 'tests::renders' panicked at src/main.rs:10:5:\nassertion `left == right` failed"
+                    .to_owned()
+            )
+        );
+        assert_eq!(suites[0].cases[2].outcome, TestOutcome::Ignored);
+    }
+
+    #[test]
+    fn junit_counts_the_failures_and_escapes_their_message() {
+        let junit = junit_report(&parse_test_output(TEST_OUTPUT));
+        assert!(junit.contains("<testsuites tests=\"3\" failures=\"1\" skipped=\"1\">"));
+        assert!(junit.contains(
+            "<failure message=\"thread &apos;tests::renders&apos; panicked at src/main.rs:10:5:\">"
+        ));
+    }
+
+    #[test]
+    fn nothing_is_exported_without_results_to_export() {
+        let mut host_fs = HostFs::default();
+        host_fs.update_root(PathBuf::from("/projects"));
+        assert_eq!(
+            export_reports(
+                Path::new("/elsewhere/.zellij-dev"),
+                Path::new("/elsewhere"),
+                true,
+                &host_fs
+            ),
+            Err("Nothing to export yet, build, run clippy or run the tests first".to_owned())
+        );
+    }
+
+    #[test]
+    fn an_export_without_the_build_says_why() {
+        let mut host_fs = HostFs::default();
+        host_fs.update_root(PathBuf::from("/projects"));
+        assert_eq!(
+            export_reports(
+                Path::new("/elsewhere/.zellij-dev"),
+                Path::new("/elsewhere"),
+                false,
+                &host_fs
+            ),
+            Err(format!(
+                "Nothing to export yet, build, run clippy or run the tests first ({})",
+                BUILD_LEFT_OUT
+            ))
+        );
+    }
+
+    #[test]
+    fn a_failed_export_is_reported() {
+        let mut report_export = ReportExport::default();
+        let mut host = RecordingHost::default();
+        report_export.export(
+            &mut host,
+            None,
+            Some(PathBuf::from("/projects")),
+            true,
+            &HostFs::default(),
+        );
+        assert_eq!(host.take_effects(), vec![]);
+        assert_eq!(
+            report_export.get_status(),
+            Some(&Err("No folder selected".to_owned()))
+        );
+        let failed: Result<String, String> =
+            Err("Failed to write /projects/.zellij-dev/junit.xml: permission denied".to_owned());
+        report_export.result_received(&serde_json::to_string(&failed).unwrap());
+        assert_eq!(report_export.get_status(), Some(&failed));
+        report_export.result_received("{");
+        assert!(matches!(
+            report_export.get_status(),
+            Some(Err(e)) if e.starts_with("Failed to parse the export result: ")
+        ));
+    }
+
+    #[test]
+    fn diagnostics_become_sarif_results_relative_to_the_workspace() {
+        let warning: Diagnostic = serde_json::from_str(
+            r#"{"message":"unused variable: `x`","code":{"code":"unused_variables"},"level":"warning","spans":[
+                {"file_name":"src/main.rs","line_start":3,"line_end":3,"column_start":9,"column_end":10,
+                 "is_primary":true,"label":"help: prefix it with an underscore"}]}"#,
+        )
+        .unwrap();
+        let sarif = sarif_report(
+            Path::new("/projects/my plugin"),
+            &[("rustc", vec![warning])],
+        );
+        let sarif: serde_json::Value = serde_json::from_str(&sarif).unwrap();
+        let run = &sarif["runs"][0];
+        assert_eq!(
+            run["originalUriBaseIds"]["SRCROOT"]["uri"],
+            "file:///projects/my%20plugin/"
+        );
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "unused_variables");
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "unused_variables");
+        assert_eq!(result["level"], "warning");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/main.rs");
+        assert_eq!(location["region"]["startColumn"], 9);
+    }
+}
@@ -1,30 +1,361 @@
 use zellij_tile::prelude::*;
 
+use crate::cargo_workspace::CargoProject;
+use crate::host::{Host, ZellijHost};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Component, PathBuf};
 
+// what cargo and the tests print, kept in the dev folder of the target directory for the reports
+// (see crate::reports)
+pub const BUILD_MESSAGES_FILE: &str = "build.jsonl";
+pub const CLIPPY_MESSAGES_FILE: &str = "clippy.jsonl";
+pub const TEST_OUTPUT_FILE: &str = "test.log";
+// the target we load the plugin's debug build of
+pub const PLUGIN_TARGET: &str = "wasm32-wasip1";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BuildStatus {
+    #[default]
+    NotStarted,
+    Building,
+    Succeeded,
+    Failed(Option<i32>), // the exit code, if the build wasn't interrupted
+}
+
+// unless build_progress is "false"
+const STREAM_PROGRESS_BY_DEFAULT: bool = true;
+
+// replaces `cargo build` and where we look for the built plugin, for projects that are built
+// some other way (eg. with just or make)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildConfig {
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub artifact_path: Option<PathBuf>, // relative to the project folder, unless absolute
+    #[serde(default)]
+    pub quiet: bool, // build in the background instead of in a command pane
+    #[serde(default = "stream_progress_by_default")]
+    pub stream_progress: bool, // have cargo build write json messages we follow as it builds
+    #[serde(default)]
+    pub hold_reload: bool, // wait until the build is checked (its warnings and its wasm) to reload
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        BuildConfig {
+            command: None,
+            args: vec![],
+            env: vec![],
+            artifact_path: None,
+            quiet: false,
+            stream_progress: STREAM_PROGRESS_BY_DEFAULT,
+            hold_reload: false,
+        }
+    }
+}
+
+fn stream_progress_by_default() -> bool {
+    STREAM_PROGRESS_BY_DEFAULT
+}
+
+impl BuildConfig {
+    pub fn from_configuration(configuration: &BTreeMap<String, String>) -> Self {
+        let split = |key: &str| -> Vec<String> {
+            match configuration.get(key).map(|v| split_shell_words(v)) {
+                Some(Ok(words)) => words,
+                Some(Err(e)) => {
+                    eprintln!("Failed to parse {}: {}", key, e);
+                    vec![]
+                }
+                None => vec![],
+            }
+        };
+        let env = split("build_env")
+            .into_iter()
+            .filter_map(|variable| match variable.split_once('=') {
+                Some((key, value)) => Some((key.to_owned(), value.to_owned())),
+                None => {
+                    eprintln!("Ignoring build_env entry without a value: {}", variable);
+                    None
+                }
+            })
+            .collect();
+        BuildConfig {
+            command: configuration
+                .get("build_command")
+                .filter(|c| !c.trim().is_empty())
+                .map(|c| c.trim().to_owned()),
+            args: split("build_args"),
+            env,
+            artifact_path: configuration.get("artifact_path").map(PathBuf::from),
+            quiet: configuration.get("quiet_builds").map(|q| q.as_str()) == Some("true"),
+            stream_progress: configuration
+                .get("build_progress")
+                .map(|b| b != "false")
+                .unwrap_or(STREAM_PROGRESS_BY_DEFAULT),
+            hold_reload: false,
+        }
+    }
+}
+
+// splits on whitespace like a shell would, so quoted words (eg. RUSTFLAGS='-C opt-level=1') stay
+// whole, a backslash escapes the next character outside of single quotes
+fn split_shell_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut characters = line.chars();
+    while let Some(character) = characters.next() {
+        match (quote, character) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), _) => word.get_or_insert_with(String::new).push(character),
+            (_, '\\') => match characters.next() {
+                Some(escaped) => word.get_or_insert_with(String::new).push(escaped),
+                None => return Err(format!("it ends with a backslash: {}", line)),
+            },
+            (Some(_), _) => word.get_or_insert_with(String::new).push(character),
+            (None, '\'') | (None, '"') => {
+                quote = Some(character);
+                word.get_or_insert_with(String::new);
+            }
+            (None, _) if character.is_whitespace() => words.extend(word.take()),
+            (None, _) => word.get_or_insert_with(String::new).push(character),
+        }
+    }
+    if let Some(quote) = quote {
+        return Err(format!("its {} quote isn't closed: {}", quote, line));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
 #[derive(Default, Debug)]
-pub struct RunAndReload {
+pub struct RunAndReload<H: Host = ZellijHost> {
     cwd: Option<PathBuf>,
+    build_config: BuildConfig,
+    cargo_project: Option<CargoProject>,
+    selected_members: Vec<String>, // the workspace members we build, in the project's order
     compilation_pane_id: Option<u32>,
+    quiet_build_running: bool,
+    build_status: BuildStatus,
+    reload_pending: bool, // the build succeeded, but we're waiting for it to be checked
+    host: H,
 }
 
-impl RunAndReload {
+impl<H: Host> RunAndReload<H> {
     pub fn update_cwd(&mut self, cwd: PathBuf) {
+        let build_command = self.build_command();
         self.cwd = Some(cwd);
+        self.cargo_project = None;
+        self.selected_members.clear();
+        self.discard_compilation_pane_if_command_changed(build_command);
     }
     pub fn get_cwd(&self) -> Option<PathBuf> {
         self.cwd.clone()
     }
+    pub fn update_build_config(&mut self, build_config: BuildConfig) {
+        let build_command = self.build_command();
+        self.build_config = build_config;
+        self.discard_compilation_pane_if_command_changed(build_command);
+    }
+    pub fn update_cargo_project(
+        &mut self,
+        cargo_project: CargoProject,
+        selected_members: &[String],
+    ) {
+        let build_command = self.build_command();
+        self.selected_members = cargo_project.normalize_selection(selected_members);
+        self.cargo_project = Some(cargo_project);
+        self.discard_compilation_pane_if_command_changed(build_command);
+    }
+    pub fn get_cargo_project(&self) -> Option<&CargoProject> {
+        self.cargo_project.as_ref()
+    }
+    pub fn update_selected_members(&mut self, selected_members: &[String]) {
+        if let Some(cargo_project) = &self.cargo_project {
+            let build_command = self.build_command();
+            self.selected_members = cargo_project.normalize_selection(selected_members);
+            self.discard_compilation_pane_if_command_changed(build_command);
+        }
+    }
+    pub fn get_selected_members(&self) -> &[String] {
+        &self.selected_members
+    }
+    // cargo puts the artifacts of a workspace's members in the workspace's target directory
+    pub fn get_target_directory(&self) -> Option<PathBuf> {
+        if let Some(artifact_path) = self.get_configured_artifact_path() {
+            return artifact_path.parent().map(|p| p.to_path_buf());
+        }
+        match &self.cargo_project {
+            Some(cargo_project) => Some(cargo_project.target_directory.clone()),
+            None => self.cwd.as_ref().map(|cwd| cwd.join("target")),
+        }
+    }
+    // the names of the wasm files we build, without the extension
+    pub fn get_artifact_names(&self) -> Vec<String> {
+        if let Some(artifact_path) = self.get_configured_artifact_path() {
+            return artifact_path
+                .file_stem()
+                .and_then(|f| f.to_str())
+                .map(|f| f.to_owned())
+                .into_iter()
+                .collect();
+        }
+        match &self.cargo_project {
+            Some(cargo_project) if !self.selected_members.is_empty() => cargo_project
+                .plugins
+                .iter()
+                .filter(|p| self.selected_members.contains(&p.name))
+                .map(|p| p.artifact_name.clone())
+                .collect(),
+            Some(cargo_project) if cargo_project.is_workspace => vec![],
+            // until cargo tells us otherwise, we assume the plugin is named after its folder
+            _ => self
+                .cwd
+                .as_ref()
+                .and_then(|cwd| match cwd.components().next_back() {
+                    Some(Component::Normal(project_dir_name)) => {
+                        project_dir_name.to_str().map(|n| n.to_owned())
+                    }
+                    _ => None,
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+    pub fn get_plugin_paths(&self) -> Vec<PathBuf> {
+        if let Some(artifact_path) = self.get_configured_artifact_path() {
+            return vec![artifact_path];
+        }
+        let target_directory = match self.get_target_directory() {
+            Some(target_directory) => target_directory,
+            None => return vec![],
+        };
+        self.get_artifact_names()
+            .iter()
+            .map(|artifact_name| {
+                target_directory
+                    .join(PLUGIN_TARGET)
+                    .join("debug")
+                    .join(format!("{}.wasm", artifact_name))
+            })
+            .collect()
+    }
+    // where we keep the files about the project's builds and tests (eg. target/zellij-dev)
+    pub fn get_dev_directory(&self) -> Option<PathBuf> {
+        self.get_target_directory().map(|t| t.join("zellij-dev"))
+    }
+    // where cargo writes its json messages while building, we only know how to follow cargo
+    pub fn get_progress_file(&self) -> Option<PathBuf> {
+        if !self.build_config.stream_progress || self.build_config.command.is_some() {
+            return None;
+        }
+        self.get_dev_directory()
+            .map(|d| d.join(BUILD_MESSAGES_FILE))
+    }
+    // cargo clippy, with its json messages written to the dev folder and the rendered ones shown
+    pub fn clippy_command(&self) -> (String, Vec<String>) {
+        let mut cargo_args = self.cargo_args("clippy");
+        cargo_args.push("--message-format=json-render-diagnostics".to_owned());
+        match self.get_dev_directory() {
+            Some(dev_directory) => {
+                let mut args = vec![
+                    "-c".to_owned(),
+                    "mkdir -p \"$(dirname \"$0\")\" && \"$@\" > \"$0\"".to_owned(),
+                    dev_directory
+                        .join(CLIPPY_MESSAGES_FILE)
+                        .display()
+                        .to_string(),
+                    "cargo".to_owned(),
+                ];
+                args.extend(cargo_args);
+                ("sh".to_owned(), args)
+            }
+            None => ("cargo".to_owned(), cargo_args),
+        }
+    }
+    // cargo test, with a copy of its output in the dev folder, the pane still shows how it exited
+    pub fn test_command(&self) -> (String, Vec<String>) {
+        let cargo_args = self.cargo_args("test");
+        match self.get_dev_directory() {
+            Some(dev_directory) => {
+                let mut args = vec![
+                    "-c".to_owned(),
+                    "mkdir -p \"$(dirname \"$0\")\" && { { \"$@\" 2>&1; echo $? > \"$0.exit\"; } | tee \"$0\"; exit \"$(cat \"$0.exit\")\"; }".to_owned(),
+                    dev_directory.join(TEST_OUTPUT_FILE).display().to_string(),
+                    "cargo".to_owned(),
+                ];
+                args.extend(cargo_args);
+                ("sh".to_owned(), args)
+            }
+            None => ("cargo".to_owned(), cargo_args),
+        }
+    }
+    pub fn host_mut(&mut self) -> &mut H {
+        &mut self.host
+    }
     pub fn update_compilation_pane_id(&mut self, compilation_pane_id: u32) {
         self.compilation_pane_id = Some(compilation_pane_id);
     }
-    pub fn command_pane_exited(&mut self, exit_code: Option<i32>, terminal_pane_id: u32) {
-        if Some(terminal_pane_id) == self.compilation_pane_id && exit_code == Some(0) {
+    pub fn is_compilation_pane(&self, terminal_pane_id: u32) -> bool {
+        Some(terminal_pane_id) == self.compilation_pane_id
+    }
+    pub fn get_build_status(&self) -> BuildStatus {
+        self.build_status
+    }
+    // returns true if the plugin was reloaded
+    pub fn command_pane_exited(&mut self, exit_code: Option<i32>, terminal_pane_id: u32) -> bool {
+        if Some(terminal_pane_id) != self.compilation_pane_id {
+            return false;
+        }
+        if exit_code != Some(0) {
+            self.build_status = BuildStatus::Failed(exit_code);
+            return false;
+        }
+        self.build_status = BuildStatus::Succeeded;
+        let reloaded = self.reload_unless_held();
+        self.host
+            .hide_pane_with_id(PaneId::Terminal(terminal_pane_id));
+        reloaded
+    }
+    // returns true if the plugin was reloaded
+    pub fn quiet_build_finished(&mut self, exit_code: Option<i32>) -> bool {
+        if !self.quiet_build_running {
+            return false;
+        }
+        self.quiet_build_running = false;
+        if exit_code != Some(0) {
+            self.build_status = BuildStatus::Failed(exit_code);
+            return false;
+        }
+        self.build_status = BuildStatus::Succeeded;
+        self.reload_unless_held()
+    }
+    // returns true if the plugin was reloaded
+    pub fn reload_checked(&mut self, reload: bool) -> bool {
+        if !self.reload_pending {
+            return false;
+        }
+        self.reload_pending = false;
+        if reload {
             self.start_or_reload_plugin();
-            if exit_code == Some(0) {
-                hide_pane_with_id(PaneId::Terminal(terminal_pane_id));
-            }
         }
+        reload
+    }
+    pub fn is_reload_pending(&self) -> bool {
+        self.reload_pending
+    }
+    pub fn is_quiet_build_running(&self) -> bool {
+        self.quiet_build_running
+    }
+    pub fn toggle_quiet_builds(&mut self) {
+        self.build_config.quiet = !self.build_config.quiet;
+    }
+    pub fn get_quiet_builds(&self) -> bool {
+        self.build_config.quiet
     }
     pub fn pane_closed(&mut self, pane_id: PaneId) {
         if let PaneId::Terminal(terminal_pane_id) = pane_id {
@@ -33,39 +364,148 @@ impl RunAndReload {
             }
         }
     }
-    pub fn run_compilation(&self) {
-        if let Some(compilation_pane_id) = self.compilation_pane_id {
-            show_pane_with_id(PaneId::Terminal(compilation_pane_id), true);
-            rerun_command_pane(compilation_pane_id);
-            hide_self();
+    pub fn run_compilation(&mut self) {
+        if self.build_config.command.is_none()
+            && self.is_workspace()
+            && self.selected_members.is_empty()
+        {
+            eprintln!("no workspace member selected!");
+            return;
+        }
+        self.reload_pending = false;
+        if self.build_config.quiet {
+            self.run_quiet_build();
+        } else if let Some(compilation_pane_id) = self.compilation_pane_id {
+            self.build_status = BuildStatus::Building;
+            self.host
+                .show_pane_with_id(PaneId::Terminal(compilation_pane_id), true);
+            self.host.rerun_command_pane(compilation_pane_id);
+            self.host.hide_self();
         } else {
-            let mut command = CommandToRun::new_with_args("cargo", vec!["build"]);
+            let (command, args) = self.build_command();
+            let mut command = CommandToRun::new_with_args(command, args);
             if let Some(cwd) = &self.cwd {
                 command.cwd = Some(cwd.clone());
-                hide_self();
-                open_command_pane_floating(command, None, Default::default());
+                self.build_status = BuildStatus::Building;
+                self.host.hide_self();
+                let mut context = BTreeMap::new();
+                context.insert("source".to_owned(), "compilation".to_owned());
+                self.host.open_command_pane_floating(command, context);
             } else {
                 eprintln!("no cwd found!");
             }
         }
     }
-    pub fn start_or_reload_plugin(&self) {
-        if let Some(cwd) = &self.cwd {
-            if let Some(Component::Normal(project_dir_name)) = cwd.components().last() {
-                if let Some(project_dir_name) = project_dir_name.to_str() {
-                    let mut plugin_path = cwd.clone();
-                    plugin_path.extend(
-                        PathBuf::from(format!(
-                            "target/wasm32-wasip1/debug/{}.wasm",
-                            project_dir_name.to_string()
-                        ))
-                        .components(),
-                    );
-                    start_or_reload_plugin(&format!("file:{}", plugin_path.display()));
+    pub fn start_or_reload_plugin(&mut self) {
+        self.reload_pending = false;
+        if self.cwd.is_none() {
+            eprintln!("no cwd found!");
+        }
+        for plugin_path in self.get_plugin_paths() {
+            self.host
+                .start_or_reload_plugin(&format!("file:{}", plugin_path.display()));
+        }
+    }
+    // the checks of the build decide whether to reload it (see DevEvent::ReloadChecked)
+    fn reload_unless_held(&mut self) -> bool {
+        if self.build_config.hold_reload {
+            self.reload_pending = true;
+            return false;
+        }
+        self.start_or_reload_plugin();
+        true
+    }
+    // runs the build in the background, leaving our pane and the focus where they are
+    fn run_quiet_build(&mut self) {
+        let cwd = match &self.cwd {
+            Some(cwd) => cwd.clone(),
+            None => {
+                eprintln!("no cwd found!");
+                return;
+            }
+        };
+        let (command, args) = self.build_command();
+        let mut env = BTreeMap::new();
+        // cargo only colors its output in a terminal
+        env.insert("CARGO_TERM_COLOR".to_owned(), "always".to_owned());
+        let mut context = BTreeMap::new();
+        context.insert("source".to_owned(), "quiet_build".to_owned());
+        self.build_status = BuildStatus::Building;
+        self.quiet_build_running = true;
+        self.host.run_command(
+            std::iter::once(command).chain(args).collect(),
+            env,
+            cwd,
+            context,
+        );
+    }
+    fn is_workspace(&self) -> bool {
+        self.cargo_project.as_ref().map(|c| c.is_workspace) == Some(true)
+    }
+    // eg. ["test", "-p", "my-plugin"] in a workspace
+    pub fn cargo_args(&self, subcommand: &str) -> Vec<String> {
+        let mut cargo_args = vec![subcommand.to_owned()];
+        if self.is_workspace() {
+            for member in &self.selected_members {
+                cargo_args.push("-p".to_owned());
+                cargo_args.push(member.clone());
+            }
+        }
+        cargo_args
+    }
+    fn build_command(&self) -> (String, Vec<String>) {
+        match &self.build_config.command {
+            Some(command) if self.build_config.env.is_empty() => {
+                (command.clone(), self.build_config.args.clone())
+            }
+            // command panes don't take environment variables, so we set them with env
+            Some(command) => {
+                let mut args: Vec<String> = self
+                    .build_config
+                    .env
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect();
+                args.push(command.clone());
+                args.extend(self.build_config.args.iter().cloned());
+                ("env".to_owned(), args)
+            }
+            // cargo prints its json messages to stdout, which we send to the progress file, and
+            // still renders the diagnostics on stderr for the compilation pane
+            None => match self.get_progress_file() {
+                Some(progress_file) => {
+                    let mut args = vec![
+                        "-c".to_owned(),
+                        "mkdir -p \"$(dirname \"$0\")\" && \"$@\" > \"$0\"".to_owned(),
+                        progress_file.display().to_string(),
+                        "cargo".to_owned(),
+                    ];
+                    args.extend(self.cargo_args("build"));
+                    args.push("--message-format=json-render-diagnostics".to_owned());
+                    ("sh".to_owned(), args)
                 }
+                None => ("cargo".to_owned(), self.cargo_args("build")),
+            },
+        }
+    }
+    fn get_configured_artifact_path(&self) -> Option<PathBuf> {
+        let artifact_path = self.build_config.artifact_path.as_ref()?;
+        match &self.cwd {
+            Some(cwd) => Some(cwd.join(artifact_path)),
+            None if artifact_path.is_absolute() => Some(artifact_path.clone()),
+            None => None,
+        }
+    }
+    // the compilation pane reruns the command it was opened with, so we close it if we'd now
+    // build something else
+    fn discard_compilation_pane_if_command_changed(
+        &mut self,
+        previous_build_command: (String, Vec<String>),
+    ) {
+        if self.build_command() != previous_build_command {
+            if let Some(compilation_pane_id) = self.compilation_pane_id.take() {
+                self.host.close_terminal_pane(compilation_pane_id);
             }
-        } else {
-            eprintln!("no cwd found!");
         }
     }
 }
@@ -176,3 +616,441 @@ impl RunAndReload {
 //     }
 //
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cargo_workspace::PluginCrate;
+    use crate::host::{HostEffect, RecordingHost};
+
+    // builds with plain cargo build, streaming the progress has tests of its own
+    fn run_and_reload() -> RunAndReload<RecordingHost> {
+        let mut run_and_reload = RunAndReload::<RecordingHost>::default();
+        run_and_reload.update_build_config(BuildConfig {
+            stream_progress: false,
+            ..Default::default()
+        });
+        run_and_reload.update_cwd(PathBuf::from("/projects/my-plugin"));
+        run_and_reload
+    }
+
+    #[test]
+    fn first_compilation_opens_a_floating_command_pane() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.run_compilation();
+        let mut context = BTreeMap::new();
+        context.insert("source".to_owned(), "compilation".to_owned());
+        assert_eq!(
+            run_and_reload.host.take_effects(),
+            vec![
+                HostEffect::HideSelf,
+                HostEffect::OpenCommandPaneFloating {
+                    command: "cargo".to_owned(),
+                    args: vec!["build".to_owned()],
+                    cwd: Some(PathBuf::from("/projects/my-plugin")),
+                    context,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn streaming_progress_sends_cargo_json_messages_to_the_progress_file() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_build_config(BuildConfig::default());
+        assert_eq!(
+            run_and_reload.get_progress_file(),
+            Some(PathBuf::from(
+                "/projects/my-plugin/target/zellij-dev/build.jsonl"
+            ))
+        );
+        run_and_reload.run_compilation();
+        match &run_and_reload.host.take_effects()[1] {
+            HostEffect::OpenCommandPaneFloating { command, args, .. } => {
+                assert_eq!(command, "sh");
+                assert_eq!(
+                    args[2..],
+                    [
+                        "/projects/my-plugin/target/zellij-dev/build.jsonl",
+                        "cargo",
+                        "build",
+                        "--message-format=json-render-diagnostics"
+                    ]
+                );
+            }
+            effect => panic!("unexpected effect: {:?}", effect),
+        }
+        // we don't know what a custom command prints
+        run_and_reload.update_build_config(BuildConfig {
+            command: Some("just".to_owned()),
+            stream_progress: true,
+            ..Default::default()
+        });
+        assert_eq!(run_and_reload.get_progress_file(), None);
+    }
+
+    #[test]
+    fn compilation_without_cwd_does_nothing() {
+        let mut run_and_reload = RunAndReload::<RecordingHost>::default();
+        run_and_reload.run_compilation();
+        assert!(run_and_reload.host.take_effects().is_empty());
+    }
+
+    #[test]
+    fn later_compilations_rerun_the_existing_pane() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_compilation_pane_id(3);
+        run_and_reload.run_compilation();
+        assert_eq!(
+            run_and_reload.host.take_effects(),
+            vec![
+                HostEffect::ShowPaneWithId(PaneId::Terminal(3), true),
+                HostEffect::RerunCommandPane(3),
+                HostEffect::HideSelf,
+            ]
+        );
+    }
+
+    #[test]
+    fn successful_build_reloads_the_plugin_and_hides_the_pane() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_compilation_pane_id(3);
+        assert!(run_and_reload.command_pane_exited(Some(0), 3));
+        assert_eq!(
+            run_and_reload.host.take_effects(),
+            vec![
+                HostEffect::StartOrReloadPlugin(
+                    "file:/projects/my-plugin/target/wasm32-wasip1/debug/my-plugin.wasm".to_owned()
+                ),
+                HostEffect::HidePaneWithId(PaneId::Terminal(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_build_leaves_the_pane_open() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_compilation_pane_id(3);
+        assert!(!run_and_reload.command_pane_exited(Some(101), 3));
+        assert!(!run_and_reload.command_pane_exited(None, 3));
+        assert!(run_and_reload.host.take_effects().is_empty());
+    }
+
+    #[test]
+    fn other_command_panes_exiting_are_ignored() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_compilation_pane_id(3);
+        assert!(!run_and_reload.command_pane_exited(Some(0), 4));
+        assert!(run_and_reload.host.take_effects().is_empty());
+    }
+
+    #[test]
+    fn closing_the_compilation_pane_opens_a_new_one_next_time() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_compilation_pane_id(3);
+        run_and_reload.pane_closed(PaneId::Plugin(3));
+        assert!(run_and_reload.is_compilation_pane(3));
+        run_and_reload.pane_closed(PaneId::Terminal(3));
+        assert!(!run_and_reload.is_compilation_pane(3));
+        run_and_reload.run_compilation();
+        assert!(matches!(
+            run_and_reload.host.take_effects().last(),
+            Some(HostEffect::OpenCommandPaneFloating { .. })
+        ));
+    }
+
+    #[test]
+    fn build_status_follows_the_compilation() {
+        let mut run_and_reload = run_and_reload();
+        assert_eq!(run_and_reload.get_build_status(), BuildStatus::NotStarted);
+        run_and_reload.run_compilation();
+        assert_eq!(run_and_reload.get_build_status(), BuildStatus::Building);
+        run_and_reload.update_compilation_pane_id(3);
+        run_and_reload.command_pane_exited(Some(101), 3);
+        assert_eq!(
+            run_and_reload.get_build_status(),
+            BuildStatus::Failed(Some(101))
+        );
+        run_and_reload.run_compilation();
+        assert_eq!(run_and_reload.get_build_status(), BuildStatus::Building);
+        run_and_reload.command_pane_exited(Some(0), 3);
+        assert_eq!(run_and_reload.get_build_status(), BuildStatus::Succeeded);
+    }
+
+    fn workspace() -> CargoProject {
+        let plugin = |name: &str| PluginCrate {
+            name: name.to_owned(),
+            artifact_name: name.to_owned(),
+            manifest_dir: PathBuf::from("/projects/plugins").join(name),
+        };
+        CargoProject {
+            workspace_root: PathBuf::from("/projects/plugins"),
+            target_directory: PathBuf::from("/projects/plugins/target"),
+            is_workspace: true,
+            plugins: vec![plugin("file-picker"), plugin("status-bar")],
+        }
+    }
+
+    #[test]
+    fn workspace_members_are_built_with_p_and_reloaded_from_the_workspace_target() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_cwd(PathBuf::from("/projects/plugins"));
+        run_and_reload.update_cargo_project(
+            workspace(),
+            &["status-bar".to_owned(), "file-picker".to_owned()],
+        );
+        run_and_reload.run_compilation();
+        assert!(matches!(
+            run_and_reload.host.take_effects().last(),
+            Some(HostEffect::OpenCommandPaneFloating { args, .. })
+                if args == &["build", "-p", "file-picker", "-p", "status-bar"]
+        ));
+        run_and_reload.update_compilation_pane_id(3);
+        run_and_reload.command_pane_exited(Some(0), 3);
+        assert_eq!(
+            run_and_reload.host.take_effects(),
+            vec![
+                HostEffect::StartOrReloadPlugin(
+                    "file:/projects/plugins/target/wasm32-wasip1/debug/file-picker.wasm".to_owned()
+                ),
+                HostEffect::StartOrReloadPlugin(
+                    "file:/projects/plugins/target/wasm32-wasip1/debug/status-bar.wasm".to_owned()
+                ),
+                HostEffect::HidePaneWithId(PaneId::Terminal(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn workspace_without_selected_members_is_not_built() {
+        let mut run_and_reload = RunAndReload::<RecordingHost>::default();
+        run_and_reload.update_cwd(PathBuf::from("/projects/plugins"));
+        run_and_reload.update_cargo_project(workspace(), &[]);
+        run_and_reload.run_compilation();
+        assert!(run_and_reload.host.take_effects().is_empty());
+        assert_eq!(run_and_reload.get_build_status(), BuildStatus::NotStarted);
+    }
+
+    #[test]
+    fn changing_the_members_closes_the_compilation_pane() {
+        let mut run_and_reload = RunAndReload::<RecordingHost>::default();
+        run_and_reload.update_cwd(PathBuf::from("/projects/plugins"));
+        run_and_reload.update_cargo_project(workspace(), &["status-bar".to_owned()]);
+        run_and_reload.update_compilation_pane_id(3);
+        run_and_reload.update_selected_members(&["status-bar".to_owned()]);
+        assert!(run_and_reload.host.take_effects().is_empty());
+        run_and_reload.update_selected_members(&["file-picker".to_owned()]);
+        assert_eq!(
+            run_and_reload.host.take_effects(),
+            vec![HostEffect::CloseTerminalPane(3)]
+        );
+        assert!(!run_and_reload.is_compilation_pane(3));
+    }
+
+    #[test]
+    fn quoted_build_args_and_env_values_stay_whole() {
+        let mut configuration = BTreeMap::new();
+        configuration.insert(
+            "build_args".to_owned(),
+            r#"build-plugin "my assets" 'it''s' escaped\ space """#.to_owned(),
+        );
+        configuration.insert(
+            "build_env".to_owned(),
+            "RUSTFLAGS='-C opt-level=1 -C debuginfo=0' NAME=\"my plugin\"".to_owned(),
+        );
+        let build_config = BuildConfig::from_configuration(&configuration);
+        assert_eq!(
+            build_config.args,
+            vec!["build-plugin", "my assets", "its", "escaped space", ""]
+        );
+        assert_eq!(
+            build_config.env,
+            vec![
+                (
+                    "RUSTFLAGS".to_owned(),
+                    "-C opt-level=1 -C debuginfo=0".to_owned()
+                ),
+                ("NAME".to_owned(), "my plugin".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn an_empty_configuration_is_the_default_build_config() {
+        let build_config = BuildConfig::from_configuration(&BTreeMap::new());
+        assert_eq!(build_config, BuildConfig::default());
+        assert!(build_config.stream_progress);
+        let mut configuration = BTreeMap::new();
+        configuration.insert("build_progress".to_owned(), "false".to_owned());
+        assert!(!BuildConfig::from_configuration(&configuration).stream_progress);
+    }
+
+    #[test]
+    fn unclosed_quotes_in_build_args_or_env_are_rejected() {
+        assert_eq!(
+            split_shell_words("build 'my assets"),
+            Err("its ' quote isn't closed: build 'my assets".to_owned())
+        );
+        assert_eq!(
+            split_shell_words("build\\"),
+            Err("it ends with a backslash: build\\".to_owned())
+        );
+        let mut configuration = BTreeMap::new();
+        configuration.insert("build_args".to_owned(), "build \"assets".to_owned());
+        configuration.insert("build_env".to_owned(), "PROFILE='dev".to_owned());
+        let build_config = BuildConfig::from_configuration(&configuration);
+        assert!(build_config.args.is_empty());
+        assert!(build_config.env.is_empty());
+    }
+
+    #[test]
+    fn custom_build_command_is_run_with_its_env_and_reloads_the_artifact() {
+        let mut configuration = BTreeMap::new();
+        configuration.insert("build_command".to_owned(), "just".to_owned());
+        configuration.insert("build_args".to_owned(), "build-plugin  assets".to_owned());
+        configuration.insert(
+            "build_env".to_owned(),
+            "PROFILE=dev BROKEN RUSTFLAGS=-Copt-level=1".to_owned(),
+        );
+        configuration.insert("artifact_path".to_owned(), "dist/my-plugin.wasm".to_owned());
+        let build_config = BuildConfig::from_configuration(&configuration);
+        assert_eq!(
+            build_config.env,
+            vec![
+                ("PROFILE".to_owned(), "dev".to_owned()),
+                ("RUSTFLAGS".to_owned(), "-Copt-level=1".to_owned()),
+            ]
+        );
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_build_config(build_config);
+        run_and_reload.run_compilation();
+        assert!(matches!(
+            run_and_reload.host.take_effects().last(),
+            Some(HostEffect::OpenCommandPaneFloating { command, args, .. })
+                if command == "env"
+                    && args == &[
+                        "PROFILE=dev",
+                        "RUSTFLAGS=-Copt-level=1",
+                        "just",
+                        "build-plugin",
+                        "assets"
+                    ]
+        ));
+        run_and_reload.update_compilation_pane_id(3);
+        run_and_reload.command_pane_exited(Some(0), 3);
+        assert_eq!(
+            run_and_reload.host.take_effects()[0],
+            HostEffect::StartOrReloadPlugin(
+                "file:/projects/my-plugin/dist/my-plugin.wasm".to_owned()
+            )
+        );
+        assert_eq!(
+            run_and_reload.get_artifact_names(),
+            vec!["my-plugin".to_owned()]
+        );
+    }
+
+    #[test]
+    fn custom_build_command_without_env_is_run_directly() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_build_config(BuildConfig {
+            command: Some("make".to_owned()),
+            args: vec!["plugin".to_owned()],
+            ..Default::default()
+        });
+        run_and_reload.run_compilation();
+        assert!(matches!(
+            run_and_reload.host.take_effects().last(),
+            Some(HostEffect::OpenCommandPaneFloating { command, args, .. })
+                if command == "make" && args == &["plugin"]
+        ));
+    }
+
+    fn quiet_build_running() -> RunAndReload<RecordingHost> {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.toggle_quiet_builds();
+        run_and_reload.run_compilation();
+        run_and_reload
+    }
+
+    #[test]
+    fn quiet_builds_run_cargo_in_the_background() {
+        let mut run_and_reload = quiet_build_running();
+        let mut env = BTreeMap::new();
+        env.insert("CARGO_TERM_COLOR".to_owned(), "always".to_owned());
+        let mut context = BTreeMap::new();
+        context.insert("source".to_owned(), "quiet_build".to_owned());
+        assert_eq!(
+            run_and_reload.host.take_effects(),
+            vec![HostEffect::RunCommand {
+                command: vec!["cargo".to_owned(), "build".to_owned()],
+                env,
+                cwd: PathBuf::from("/projects/my-plugin"),
+                context,
+            }]
+        );
+    }
+
+    #[test]
+    fn a_failed_quiet_build_isnt_reloaded() {
+        let mut run_and_reload = quiet_build_running();
+        run_and_reload.host.take_effects();
+        assert!(!run_and_reload.quiet_build_finished(Some(101)));
+        assert_eq!(
+            run_and_reload.get_build_status(),
+            BuildStatus::Failed(Some(101))
+        );
+        assert!(run_and_reload.host.take_effects().is_empty());
+    }
+
+    #[test]
+    fn a_quiet_build_result_we_dont_wait_for_is_ignored() {
+        let mut run_and_reload = quiet_build_running();
+        run_and_reload.quiet_build_finished(Some(101));
+        run_and_reload.host.take_effects();
+        assert!(!run_and_reload.quiet_build_finished(Some(0)));
+        assert_eq!(
+            run_and_reload.get_build_status(),
+            BuildStatus::Failed(Some(101))
+        );
+        assert!(run_and_reload.host.take_effects().is_empty());
+    }
+
+    #[test]
+    fn a_successful_quiet_build_reloads_the_plugin() {
+        let mut run_and_reload = quiet_build_running();
+        run_and_reload.host.take_effects();
+        assert!(run_and_reload.quiet_build_finished(Some(0)));
+        assert_eq!(run_and_reload.get_build_status(), BuildStatus::Succeeded);
+        assert_eq!(
+            run_and_reload.host.take_effects(),
+            vec![HostEffect::StartOrReloadPlugin(
+                "file:/projects/my-plugin/target/wasm32-wasip1/debug/my-plugin.wasm".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn held_reloads_wait_for_the_build_to_be_checked() {
+        let mut run_and_reload = run_and_reload();
+        run_and_reload.update_build_config(BuildConfig {
+            quiet: true,
+            hold_reload: true,
+            ..Default::default()
+        });
+        run_and_reload.run_compilation();
+        run_and_reload.host.take_effects();
+        assert!(!run_and_reload.quiet_build_finished(Some(0)));
+        assert!(run_and_reload.is_reload_pending());
+        assert!(run_and_reload.host.take_effects().is_empty());
+        assert!(!run_and_reload.reload_checked(false));
+        assert!(run_and_reload.host.take_effects().is_empty());
+        assert!(!run_and_reload.is_reload_pending());
+        run_and_reload.run_compilation();
+        run_and_reload.host.take_effects();
+        run_and_reload.quiet_build_finished(Some(0));
+        assert!(run_and_reload.reload_checked(true));
+        assert!(!run_and_reload.is_reload_pending());
+        assert_eq!(run_and_reload.host.take_effects().len(), 1);
+    }
+}
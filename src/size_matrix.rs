@@ -0,0 +1,349 @@
+use zellij_tile::prelude::*;
+
+use crate::deadline::Deadline;
+use crate::host::Host;
+
+use std::str::FromStr;
+
+const DEFAULT_SIZES: &str = "1x80 5x40 24x80 full";
+const DEFAULT_PAUSE_SECS: f64 = 3.0;
+// floating panes are resized in steps, so we might never land on the exact size
+const MAX_RESIZE_ATTEMPTS: usize = 60;
+// zellij doesn't tell us when a resize couldn't change the pane, so we stop waiting for it
+const RESIZE_TIMEOUT_SECS: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresetSize {
+    Fixed { rows: usize, cols: usize },
+    Full,
+}
+
+impl FromStr for PresetSize {
+    type Err = String;
+    fn from_str(size: &str) -> Result<Self, Self::Err> {
+        if size.eq_ignore_ascii_case("full") {
+            return Ok(PresetSize::Full);
+        }
+        let (rows, cols) = size
+            .split_once('x')
+            .ok_or_else(|| format!("Size should be <rows>x<cols> or full, got: {}", size))?;
+        let rows = rows
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid rows in size: {}", size))?;
+        let cols = cols
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid cols in size: {}", size))?;
+        Ok(PresetSize::Fixed { rows, cols })
+    }
+}
+
+impl std::fmt::Display for PresetSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PresetSize::Fixed { rows, cols } => write!(f, "{}x{}", rows, cols),
+            PresetSize::Full => write!(f, "full"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct DimensionResize {
+    settled: bool,
+    last_difference: Option<isize>,
+}
+
+impl DimensionResize {
+    // returns the resize to apply to get closer to the target, if any
+    fn step(&mut self, current: usize, target: usize) -> Option<Resize> {
+        let difference = target as isize - current as isize;
+        let overshot = self
+            .last_difference
+            .map(|last| last.signum() != difference.signum())
+            .unwrap_or(false);
+        let stuck = self.last_difference == Some(difference);
+        if difference == 0 || overshot || stuck {
+            self.settled = true;
+        }
+        self.last_difference = Some(difference);
+        if self.settled {
+            None
+        } else if difference > 0 {
+            Some(Resize::Increase)
+        } else {
+            Some(Resize::Decrease)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SizeMatrixRun {
+    plugin_pane_id: PaneId,
+    index: usize,
+    pausing: bool,
+    resize_attempts: usize,
+    rows: DimensionResize,
+    cols: DimensionResize,
+    was_floating: bool,
+    // the pane's content size when we last resized it, until zellij reports it changed
+    resizing_from: Option<(usize, usize)>,
+}
+
+impl SizeMatrixRun {
+    // the dimension we're resizing couldn't get any closer to the target
+    fn settle_dimension(&mut self) {
+        if self.rows.settled {
+            self.cols.settled = true;
+        } else {
+            self.rows.settled = true;
+        }
+        self.resizing_from = None;
+    }
+}
+
+#[derive(Debug)]
+pub struct SizeMatrix {
+    sizes: Vec<PresetSize>,
+    pause_secs: f64,
+    run: Option<SizeMatrixRun>,
+    pause: Deadline,
+    resize_timeout: Deadline,
+    status: Option<String>,
+}
+
+impl Default for SizeMatrix {
+    fn default() -> Self {
+        SizeMatrix {
+            sizes: parse_sizes(DEFAULT_SIZES).unwrap_or_default(),
+            pause_secs: DEFAULT_PAUSE_SECS,
+            run: None,
+            pause: Default::default(),
+            resize_timeout: Default::default(),
+            status: None,
+        }
+    }
+}
+
+impl SizeMatrix {
+    pub fn update_sizes(&mut self, sizes: &str) {
+        match parse_sizes(sizes) {
+            Ok(sizes) if !sizes.is_empty() => self.sizes = sizes,
+            Ok(_) => eprintln!("size_matrix is empty, using the default sizes"),
+            Err(e) => eprintln!("Failed to parse size_matrix: {}", e),
+        }
+    }
+    pub fn update_pause(&mut self, pause_secs: &str) {
+        match pause_secs.parse() {
+            Ok(pause_secs) => self.pause_secs = pause_secs,
+            Err(_) => eprintln!("Failed to parse size_matrix_pause: {}", pause_secs),
+        }
+    }
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+    pub fn get_status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+    pub fn start(&mut self, host: &mut impl Host, plugin_pane: Option<&PaneInfo>) {
+        let plugin_pane = match plugin_pane {
+            Some(plugin_pane) => plugin_pane,
+            None => {
+                self.status = Some("Size matrix: the plugin pane is not open".to_owned());
+                return;
+            }
+        };
+        let plugin_pane_id = PaneId::Plugin(plugin_pane.id);
+        if !plugin_pane.is_floating {
+            // only floating panes can be resized freely
+            host.toggle_pane_embed_or_eject_for_pane_id(plugin_pane_id);
+        }
+        self.run = Some(SizeMatrixRun {
+            plugin_pane_id,
+            index: 0,
+            pausing: false,
+            resize_attempts: 0,
+            rows: Default::default(),
+            cols: Default::default(),
+            was_floating: plugin_pane.is_floating,
+            resizing_from: None,
+        });
+        self.update_status();
+        if plugin_pane.is_floating {
+            self.plugin_pane_updated(host, Some(plugin_pane));
+        }
+    }
+    pub fn stop(&mut self, host: &mut impl Host) {
+        if let Some(run) = self.run.take() {
+            if !run.was_floating {
+                host.toggle_pane_embed_or_eject_for_pane_id(run.plugin_pane_id);
+            }
+        }
+        self.pause.cancel();
+        self.resize_timeout.cancel();
+        self.status = Some("Size matrix: done".to_owned());
+    }
+    pub fn plugin_pane_updated(&mut self, host: &mut impl Host, plugin_pane: Option<&PaneInfo>) {
+        let plugin_pane = match (plugin_pane, self.run.as_ref()) {
+            (Some(plugin_pane), Some(run)) if !run.pausing => plugin_pane,
+            (None, Some(_)) => {
+                // the plugin pane was closed
+                self.run = None;
+                self.pause.cancel();
+                self.resize_timeout.cancel();
+                self.status = Some("Size matrix: the plugin pane was closed".to_owned());
+                return;
+            }
+            _ => return,
+        };
+        if !plugin_pane.is_floating {
+            // we're still waiting for the pane to float
+            return;
+        }
+        let (rows, cols) = match self.run.as_ref().and_then(|run| self.sizes.get(run.index)) {
+            Some(PresetSize::Fixed { rows, cols }) => (*rows, *cols),
+            // we grow the pane until it can't grow anymore
+            Some(PresetSize::Full) => (usize::MAX / 2, usize::MAX / 2),
+            None => return,
+        };
+        let pause_secs = self.pause_secs;
+        let run = match self.run.as_mut() {
+            Some(run) => run,
+            None => return,
+        };
+        let content_size = (
+            plugin_pane.pane_content_rows,
+            plugin_pane.pane_content_columns,
+        );
+        if run.resizing_from == Some(content_size) {
+            // an update about another pane, or our resize wasn't applied yet
+            return;
+        }
+        run.resizing_from = None;
+        self.resize_timeout.cancel();
+        // we resize one dimension at a time, and wait for each resize to change the pane
+        run.resize_attempts += 1;
+        let resize = match run.rows.step(plugin_pane.pane_content_rows, rows) {
+            Some(resize) => Some((resize, Direction::Down)),
+            None => run
+                .cols
+                .step(plugin_pane.pane_content_columns, cols)
+                .map(|resize| (resize, Direction::Right)),
+        };
+        if let Some((resize, direction)) = resize {
+            host.resize_pane_with_id(
+                ResizeStrategy::new(resize, Some(direction)),
+                run.plugin_pane_id,
+            );
+            run.resizing_from = Some(content_size);
+            self.resize_timeout.schedule(host, RESIZE_TIMEOUT_SECS);
+        }
+        if resize.is_none() || run.resize_attempts >= MAX_RESIZE_ATTEMPTS {
+            run.pausing = true;
+            run.resizing_from = None;
+            self.resize_timeout.cancel();
+            self.pause.schedule(host, pause_secs);
+            self.update_status();
+        }
+    }
+    // returns true if the status changed
+    pub fn timer_elapsed(&mut self, host: &mut impl Host, plugin_pane: Option<&PaneInfo>) -> bool {
+        if self.resize_timeout.elapsed() {
+            if let Some(run) = self.run.as_mut().filter(|run| run.resizing_from.is_some()) {
+                run.settle_dimension();
+                self.plugin_pane_updated(host, plugin_pane);
+                return true;
+            }
+        }
+        if !self.pause.elapsed() {
+            return false;
+        }
+        let sizes_count = self.sizes.len();
+        match self.run.as_mut() {
+            Some(run) if run.index + 1 < sizes_count => {
+                run.index += 1;
+                run.pausing = false;
+                run.resize_attempts = 0;
+                run.rows = Default::default();
+                run.cols = Default::default();
+                run.resizing_from = None;
+                self.update_status();
+                self.plugin_pane_updated(host, plugin_pane);
+            }
+            Some(_) => self.stop(host),
+            None => return false,
+        }
+        true
+    }
+    fn update_status(&mut self) {
+        if let Some(run) = &self.run {
+            if let Some(size) = self.sizes.get(run.index) {
+                self.status = Some(format!(
+                    "Size matrix: {} ({}/{}){}",
+                    size,
+                    run.index + 1,
+                    self.sizes.len(),
+                    if run.pausing { "" } else { " - resizing..." }
+                ));
+            }
+        }
+    }
+}
+
+fn parse_sizes(sizes: &str) -> Result<Vec<PresetSize>, String> {
+    sizes
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(PresetSize::from_str)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes_separated_by_spaces_or_commas() {
+        assert_eq!(
+            parse_sizes(" 1x80, 24x80\tFULL "),
+            Ok(vec![
+                PresetSize::Fixed { rows: 1, cols: 80 },
+                PresetSize::Fixed { rows: 24, cols: 80 },
+                PresetSize::Full,
+            ])
+        );
+        assert_eq!(parse_sizes(""), Ok(vec![]));
+        assert_eq!(
+            parse_sizes("1x80 big"),
+            Err("Size should be <rows>x<cols> or full, got: big".to_owned())
+        );
+        assert_eq!(
+            parse_sizes("ax80"),
+            Err("Invalid rows in size: ax80".to_owned())
+        );
+    }
+
+    #[test]
+    fn steps_towards_the_target_until_reaching_it() {
+        let mut rows = DimensionResize::default();
+        assert_eq!(rows.step(10, 12), Some(Resize::Increase));
+        assert_eq!(rows.step(11, 12), Some(Resize::Increase));
+        assert_eq!(rows.step(12, 12), None);
+        assert!(rows.settled);
+        let mut cols = DimensionResize::default();
+        assert_eq!(cols.step(80, 40), Some(Resize::Decrease));
+        assert_eq!(cols.step(60, 40), Some(Resize::Decrease));
+    }
+
+    #[test]
+    fn settles_when_a_step_overshoots_or_doesnt_move_the_pane() {
+        let mut overshooting = DimensionResize::default();
+        assert_eq!(overshooting.step(38, 40), Some(Resize::Increase));
+        assert_eq!(overshooting.step(44, 40), None);
+        assert!(overshooting.settled);
+        let mut stuck = DimensionResize::default();
+        assert_eq!(stuck.step(30, 40), Some(Resize::Increase));
+        assert_eq!(stuck.step(30, 40), None);
+        assert!(stuck.settled);
+    }
+}
@@ -0,0 +1,475 @@
+use zellij_tile::prelude::*;
+
+use crate::deadline::Deadline;
+use crate::host::Host;
+use crate::host_fs::HostFs;
+use crate::ui::{help_text, truncate};
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// time for the plugin pane to be focused before we dump the focused pane
+const FOCUS_DELAY_SECS: f64 = 0.3;
+const SNAPSHOTS_FOLDER: &str = "snapshots";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaptureKind {
+    Record,
+    Compare,
+}
+
+#[derive(Debug, Clone)]
+struct PendingCapture {
+    kind: CaptureKind,
+    snapshot_path: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Unchanged(String),
+    Removed(String),
+    Added(String),
+}
+
+#[derive(Debug)]
+struct Comparison {
+    snapshot_path: PathBuf,
+    diff: Vec<DiffLine>,
+}
+
+impl Comparison {
+    fn has_changes(&self) -> bool {
+        self.diff
+            .iter()
+            .any(|l| !matches!(l, DiffLine::Unchanged(_)))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Snapshots {
+    pending_capture: Option<PendingCapture>,
+    focus_delay: Deadline,
+    comparison: Option<Comparison>,
+    status: Option<String>,
+    scroll: usize,
+}
+
+impl Snapshots {
+    pub fn handle_key(
+        &mut self,
+        host: &mut impl Host,
+        key: KeyWithModifier,
+        plugin_pane: Option<&PaneInfo>,
+        plugin_name: Option<String>,
+        project_folder: Option<PathBuf>,
+        host_fs: &HostFs,
+    ) -> bool {
+        let mut should_render = true;
+        match key.bare_key {
+            BareKey::Char('r') if key.has_no_modifiers() => {
+                self.capture(
+                    host,
+                    CaptureKind::Record,
+                    plugin_pane,
+                    plugin_name,
+                    project_folder,
+                    host_fs,
+                );
+            }
+            BareKey::Char('c') if key.has_no_modifiers() => {
+                self.capture(
+                    host,
+                    CaptureKind::Compare,
+                    plugin_pane,
+                    plugin_name,
+                    project_folder,
+                    host_fs,
+                );
+            }
+            BareKey::Char('a') if key.has_no_modifiers() => {
+                self.approve(host_fs);
+            }
+            BareKey::Down if key.has_no_modifiers() => {
+                self.scroll += 1;
+            }
+            BareKey::Up if key.has_no_modifiers() => {
+                self.scroll = self.scroll.saturating_sub(1);
+            }
+            _ => {
+                should_render = false;
+            }
+        }
+        should_render
+    }
+    pub fn timer_elapsed(&mut self, host: &mut impl Host, session_name: Option<String>) {
+        if !self.focus_delay.elapsed() {
+            return;
+        }
+        let pending_capture = match &self.pending_capture {
+            Some(pending_capture) => pending_capture,
+            None => return,
+        };
+        let session_name = match session_name {
+            Some(session_name) => session_name,
+            None => {
+                self.pending_capture = None;
+                self.status = Some("Unknown session, cannot dump the screen".to_owned());
+                return;
+            }
+        };
+        let dump_path = match pending_capture.kind {
+            CaptureKind::Record => pending_capture.snapshot_path.clone(),
+            CaptureKind::Compare => new_snapshot_path(&pending_capture.snapshot_path),
+        };
+        let dump_path = dump_path.display().to_string();
+        let mut context = BTreeMap::new();
+        context.insert("source".to_owned(), "snapshots".to_owned());
+        // dump-screen dumps the viewport of the focused pane
+        host.run_command(
+            vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                "mkdir -p \"$(dirname \"$1\")\" && zellij --session \"$2\" action dump-screen \"$1\"".to_owned(),
+                "sh".to_owned(),
+                dump_path,
+                session_name,
+            ],
+            BTreeMap::new(),
+            PathBuf::from("."),
+            context,
+        );
+    }
+    pub fn handle_dump_result(
+        &mut self,
+        host: &mut impl Host,
+        exit_code: Option<i32>,
+        stderr: Vec<u8>,
+        own_plugin_id: Option<u32>,
+        host_fs: &HostFs,
+    ) {
+        if let Some(own_plugin_id) = own_plugin_id {
+            host.focus_pane_with_id(PaneId::Plugin(own_plugin_id), false);
+        }
+        let pending_capture = match self.pending_capture.take() {
+            Some(pending_capture) => pending_capture,
+            None => return,
+        };
+        if exit_code != Some(0) {
+            self.status = Some(format!(
+                "Failed to dump the plugin's screen: {}",
+                String::from_utf8_lossy(&stderr).trim()
+            ));
+            return;
+        }
+        match pending_capture.kind {
+            CaptureKind::Record => {
+                self.comparison = None;
+                self.status = Some(format!(
+                    "Recorded {}",
+                    pending_capture.snapshot_path.display()
+                ));
+            }
+            CaptureKind::Compare => {
+                self.compare(&pending_capture.snapshot_path, host_fs);
+            }
+        }
+    }
+    pub fn render(&self, y: usize, rows: usize, cols: usize) {
+        print_text_with_coordinates(
+            Text::new(truncate("Snapshots", cols)).color_range(2, ..),
+            0,
+            y,
+            None,
+            None,
+        );
+        let status = match (&self.status, &self.pending_capture) {
+            (_, Some(_)) => "Capturing...".to_owned(),
+            (Some(status), None) => status.clone(),
+            (None, None) => {
+                "Record the plugin's current screen or compare it to the recorded one".to_owned()
+            }
+        };
+        print_text_with_coordinates(Text::new(truncate(&status, cols)), 0, y + 1, None, None);
+        let mut y = y + 3;
+        if let Some(comparison) = &self.comparison {
+            let available_rows = rows.saturating_sub(y + 1);
+            // (line number in the recorded or current screen, line)
+            let mut changed_lines: Vec<(usize, &DiffLine)> = vec![];
+            let (mut recorded_line, mut current_line) = (0, 0);
+            for line in &comparison.diff {
+                match line {
+                    DiffLine::Unchanged(_) => {
+                        recorded_line += 1;
+                        current_line += 1;
+                    }
+                    DiffLine::Removed(_) => {
+                        recorded_line += 1;
+                        changed_lines.push((recorded_line, line));
+                    }
+                    DiffLine::Added(_) => {
+                        current_line += 1;
+                        changed_lines.push((current_line, line));
+                    }
+                }
+            }
+            for (line_number, line) in changed_lines.iter().skip(self.scroll).take(available_rows) {
+                let (prefix, text, color) = match line {
+                    DiffLine::Removed(text) => ("-", text, 3),
+                    DiffLine::Added(text) => ("+", text, 2),
+                    DiffLine::Unchanged(text) => (" ", text, 0),
+                };
+                let line = format!("{:>4} {} {}", line_number, prefix, text);
+                print_text_with_coordinates(
+                    Text::new(truncate(&line, cols)).color_range(color, 5..6),
+                    0,
+                    y,
+                    None,
+                    None,
+                );
+                y += 1;
+            }
+        }
+        print_text_with_coordinates(
+            help_text(
+                "<r> record, <c> compare, <a> approve new snapshot, <↓↑> scroll, <Esc> back",
+                cols,
+            ),
+            0,
+            rows.saturating_sub(1),
+            None,
+            None,
+        );
+    }
+    fn capture(
+        &mut self,
+        host: &mut impl Host,
+        kind: CaptureKind,
+        plugin_pane: Option<&PaneInfo>,
+        plugin_name: Option<String>,
+        project_folder: Option<PathBuf>,
+        host_fs: &HostFs,
+    ) {
+        let (plugin_pane, plugin_name, project_folder) =
+            match (plugin_pane, plugin_name, project_folder) {
+                (Some(plugin_pane), Some(plugin_name), Some(project_folder)) => {
+                    (plugin_pane, plugin_name, project_folder)
+                }
+                _ => {
+                    self.status = Some("The plugin pane is not open".to_owned());
+                    return;
+                }
+            };
+        // snapshots are per size, so that they can be taken at every step of the size matrix
+        let snapshot_path = project_folder.join(SNAPSHOTS_FOLDER).join(format!(
+            "{}__{}x{}.snap",
+            plugin_name, plugin_pane.pane_content_rows, plugin_pane.pane_content_columns
+        ));
+        // zellij can dump the screen anywhere, but we can only read and approve it under the
+        // folder zellij was started in
+        if let Err(e) = host_fs.mounted_path(&snapshot_path) {
+            self.status = Some(format!("Snapshots can't be kept there: {}", e));
+            return;
+        }
+        host.focus_pane_with_id(PaneId::Plugin(plugin_pane.id), false);
+        self.pending_capture = Some(PendingCapture {
+            kind,
+            snapshot_path,
+        });
+        self.scroll = 0;
+        self.focus_delay.schedule(host, FOCUS_DELAY_SECS);
+    }
+    fn compare(&mut self, snapshot_path: &Path, host_fs: &HostFs) {
+        let recorded = match host_fs.read_to_string(snapshot_path) {
+            Ok(recorded) => recorded,
+            Err(e) => {
+                self.status = Some(format!("No recorded snapshot to compare to ({})", e));
+                return;
+            }
+        };
+        let current = match host_fs.read_to_string(&new_snapshot_path(snapshot_path)) {
+            Ok(current) => current,
+            Err(e) => {
+                self.status = Some(e);
+                return;
+            }
+        };
+        let comparison = Comparison {
+            snapshot_path: snapshot_path.to_path_buf(),
+            diff: diff_lines(&recorded, &current),
+        };
+        self.status = Some(if comparison.has_changes() {
+            format!(
+                "{} changed, <a> to approve the new snapshot",
+                snapshot_path.display()
+            )
+        } else {
+            format!("{} is unchanged", snapshot_path.display())
+        });
+        self.comparison = Some(comparison);
+    }
+    fn approve(&mut self, host_fs: &HostFs) {
+        let comparison = match self.comparison.take() {
+            Some(comparison) => comparison,
+            None => return,
+        };
+        let new_snapshot_path = new_snapshot_path(&comparison.snapshot_path);
+        let result = host_fs
+            .read_to_string(&new_snapshot_path)
+            .and_then(|new_snapshot| host_fs.write(&comparison.snapshot_path, &new_snapshot))
+            .and_then(|_| host_fs.remove_file(&new_snapshot_path));
+        self.status = Some(match result {
+            Ok(()) => format!("Approved {}", comparison.snapshot_path.display()),
+            Err(e) => e,
+        });
+    }
+}
+
+fn new_snapshot_path(snapshot_path: &Path) -> PathBuf {
+    let mut new_snapshot_path = snapshot_path.as_os_str().to_owned();
+    new_snapshot_path.push(".new");
+    PathBuf::from(new_snapshot_path)
+}
+
+// a longest-common-subsequence line diff, screens are small enough for this to be cheap
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().map(|l| l.trim_end()).collect();
+    let new: Vec<&str> = new.lines().map(|l| l.trim_end()).collect();
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                std::cmp::max(common[i + 1][j], common[i][j + 1])
+            };
+        }
+    }
+    let mut diff = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Unchanged(old[i].to_owned()));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i].to_owned()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_owned()));
+            j += 1;
+        }
+    }
+    diff.extend(old[i..].iter().map(|l| DiffLine::Removed(l.to_string())));
+    diff.extend(new[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostEffect, RecordingHost};
+
+    use DiffLine::*;
+
+    fn line(text: &str) -> String {
+        text.to_owned()
+    }
+
+    fn capture(snapshots: &mut Snapshots, host: &mut RecordingHost, project_folder: &str) {
+        let plugin_pane = PaneInfo {
+            id: 2,
+            pane_content_rows: 20,
+            pane_content_columns: 80,
+            ..Default::default()
+        };
+        let mut host_fs = HostFs::default();
+        host_fs.update_root(PathBuf::from("/projects"));
+        snapshots.capture(
+            host,
+            CaptureKind::Record,
+            Some(&plugin_pane),
+            Some("my-plugin".to_owned()),
+            Some(PathBuf::from(project_folder)),
+            &host_fs,
+        );
+    }
+
+    #[test]
+    fn a_snapshot_is_captured_once_the_plugin_pane_is_focused() {
+        let mut snapshots = Snapshots::default();
+        let mut host = RecordingHost::default();
+        capture(&mut snapshots, &mut host, "/projects/my-plugin");
+        assert_eq!(
+            host.take_effects(),
+            vec![
+                HostEffect::FocusPaneWithId(PaneId::Plugin(2), false),
+                HostEffect::SetTimeout(FOCUS_DELAY_SECS),
+            ]
+        );
+        assert_eq!(
+            snapshots.pending_capture.map(|p| p.snapshot_path),
+            Some(PathBuf::from(
+                "/projects/my-plugin/snapshots/my-plugin__20x80.snap"
+            ))
+        );
+    }
+
+    #[test]
+    fn snapshots_outside_the_host_folder_arent_captured() {
+        let mut snapshots = Snapshots::default();
+        let mut host = RecordingHost::default();
+        capture(&mut snapshots, &mut host, "/elsewhere/my-plugin");
+        assert_eq!(host.take_effects(), vec![]);
+        assert!(snapshots.pending_capture.is_none());
+        assert_eq!(
+            snapshots.status.as_deref(),
+            Some(
+                "Snapshots can't be kept there: /elsewhere/my-plugin/snapshots/my-plugin__20x80.snap \
+                 is outside of the folder zellij was started in (/projects)"
+            )
+        );
+    }
+
+    #[test]
+    fn an_added_line_is_between_the_unchanged_ones() {
+        assert_eq!(
+            diff_lines("a\nc", "a\nb\nc"),
+            vec![Unchanged(line("a")), Added(line("b")), Unchanged(line("c"))]
+        );
+    }
+
+    #[test]
+    fn a_removed_line_is_between_the_unchanged_ones() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nc"),
+            vec![
+                Unchanged(line("a")),
+                Removed(line("b")),
+                Unchanged(line("c"))
+            ]
+        );
+    }
+
+    #[test]
+    fn a_replaced_line_is_removed_and_then_added() {
+        assert_eq!(
+            diff_lines("a\nb  \nc", "a\nB\nc"),
+            vec![
+                Unchanged(line("a")),
+                Removed(line("b")),
+                Added(line("B")),
+                Unchanged(line("c"))
+            ]
+        );
+    }
+
+    #[test]
+    fn diffs_against_nothing_are_all_added_or_removed() {
+        assert_eq!(diff_lines("", ""), vec![]);
+        assert_eq!(
+            diff_lines("", "a\nb"),
+            vec![Added(line("a")), Added(line("b"))]
+        );
+        assert_eq!(diff_lines("a", ""), vec![Removed(line("a"))]);
+    }
+}
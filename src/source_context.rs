@@ -0,0 +1,233 @@
+use zellij_tile::prelude::*;
+
+use crate::cargo_messages::{Diagnostic, DiagnosticSpan};
+use crate::ui::truncate;
+
+use std::ops::Range;
+
+const PRIMARY_COLOR: usize = 3;
+const SECONDARY_COLOR: usize = 1;
+
+// a line of the file around a diagnostic, with the parts its spans point at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextLine {
+    pub line_number: usize,
+    pub text: String,
+    pub highlights: Vec<(Range<usize>, bool)>, // in characters, and whether it's the primary span
+    pub labels: Vec<SpanLabel>,                // shown under the line, like rustc does
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanLabel {
+    pub columns: Range<usize>, // in characters
+    pub label: String,
+    pub is_primary: bool,
+}
+
+// the lines the diagnostic's spans in file_name cover, and context_lines around them
+pub fn source_context(
+    content: &str,
+    diagnostic: &Diagnostic,
+    file_name: &str,
+    context_lines: usize,
+) -> Vec<ContextLine> {
+    let spans: Vec<&DiagnosticSpan> = diagnostic
+        .spans
+        .iter()
+        .filter(|s| s.file_name == file_name)
+        .collect();
+    let (first_line, last_line) = match (
+        spans.iter().map(|s| s.line_start).min(),
+        spans.iter().map(|s| s.line_end).max(),
+    ) {
+        (Some(first_line), Some(last_line)) => (first_line, last_line),
+        _ => return vec![],
+    };
+    let first_line = std::cmp::max(first_line.saturating_sub(context_lines), 1);
+    let last_line = last_line + context_lines;
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, text)| (i + 1, text))
+        .skip(first_line - 1)
+        .take(last_line + 1 - first_line)
+        .map(|(line_number, text)| {
+            // a tab is one column for rustc, so it's one space for us
+            let text = text.replace('\t', " ");
+            let line_length = text.chars().count();
+            let mut highlights = vec![];
+            let mut labels = vec![];
+            for span in &spans {
+                if line_number < span.line_start || line_number > span.line_end {
+                    continue;
+                }
+                let start = if line_number == span.line_start {
+                    span.column_start.saturating_sub(1)
+                } else {
+                    0
+                };
+                let end = if line_number == span.line_end {
+                    span.column_end.saturating_sub(1)
+                } else {
+                    line_length
+                };
+                let columns = start..std::cmp::max(end, start + 1);
+                highlights.push((columns.clone(), span.is_primary));
+                if line_number == span.line_end {
+                    labels.push(SpanLabel {
+                        columns,
+                        label: span.label.clone().unwrap_or_default(),
+                        is_primary: span.is_primary,
+                    });
+                }
+            }
+            ContextLine {
+                line_number,
+                text,
+                highlights,
+                labels,
+            }
+        })
+        .collect()
+}
+
+// eg.
+//   12 | let x: u32 = "a";
+//      |        ---   ^^^ expected `u32`, found `&str`
+pub fn render_lines(context: &[ContextLine], cols: usize) -> Vec<Text> {
+    let gutter_width = context
+        .iter()
+        .map(|l| l.line_number.to_string().len())
+        .max()
+        .unwrap_or(1);
+    let prefix_length = gutter_width + 3;
+    let mut lines = vec![];
+    for line in context {
+        let text = truncate(
+            &format!(
+                "{:>width$} | {}",
+                line.line_number,
+                line.text,
+                width = gutter_width
+            ),
+            cols,
+        );
+        let mut text_line = Text::new(&text).color_range(0, ..prefix_length);
+        for (columns, is_primary) in &line.highlights {
+            text_line = highlight(text_line, &text, prefix_length, columns, *is_primary);
+        }
+        lines.push(text_line);
+        for label in &line.labels {
+            let marker = if label.is_primary { "^" } else { "-" };
+            let markers = marker.repeat(label.columns.len());
+            let text = truncate(
+                &format!(
+                    "{:width$} | {}{} {}",
+                    "",
+                    " ".repeat(label.columns.start),
+                    markers,
+                    label.label,
+                    width = gutter_width
+                ),
+                cols,
+            );
+            let label_end = text.chars().count().saturating_sub(prefix_length);
+            let columns = label.columns.start..std::cmp::max(label_end, label.columns.start);
+            let label_line = Text::new(&text).color_range(0, ..prefix_length);
+            lines.push(highlight(
+                label_line,
+                &text,
+                prefix_length,
+                &columns,
+                label.is_primary,
+            ));
+        }
+    }
+    lines
+}
+
+fn highlight(
+    text_line: Text,
+    text: &str,
+    prefix_length: usize,
+    columns: &Range<usize>,
+    is_primary: bool,
+) -> Text {
+    let length = text.chars().count();
+    let start = std::cmp::min(prefix_length + columns.start, length);
+    let end = std::cmp::min(prefix_length + columns.end, length);
+    let color = if is_primary {
+        PRIMARY_COLOR
+    } else {
+        SECONDARY_COLOR
+    };
+    text_line.color_range(color, start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "fn main() {\n    // a comment\n    let x: u32 = \"a\";\n}\n";
+
+    fn mismatched_types() -> Diagnostic {
+        serde_json::from_str(
+            r#"{"message":"mismatched types","level":"error","spans":[
+                {"file_name":"src/main.rs","line_start":3,"line_end":3,"column_start":18,"column_end":21,
+                 "is_primary":true,"label":"expected `u32`, found `&str`"},
+                {"file_name":"src/main.rs","line_start":3,"line_end":3,"column_start":12,"column_end":15,
+                 "is_primary":false,"label":"expected due to this"}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    fn line_numbers(context: &[ContextLine]) -> Vec<usize> {
+        context.iter().map(|l| l.line_number).collect()
+    }
+
+    #[test]
+    fn includes_the_lines_around_the_spans() {
+        let context = source_context(CONTENT, &mismatched_types(), "src/main.rs", 1);
+        assert_eq!(line_numbers(&context), vec![2, 3, 4]);
+        assert!(context[0].highlights.is_empty());
+        // there's no line before the first one, nor after the last one
+        let context = source_context(CONTENT, &mismatched_types(), "src/main.rs", 5);
+        assert_eq!(line_numbers(&context), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn spans_are_highlighted_with_their_labels_under_them() {
+        let context = source_context(CONTENT, &mismatched_types(), "src/main.rs", 0);
+        assert_eq!(context[0].highlights, vec![(17..20, true), (11..14, false)]);
+        assert_eq!(
+            context[0].labels[0],
+            SpanLabel {
+                columns: 17..20,
+                label: "expected `u32`, found `&str`".to_owned(),
+                is_primary: true,
+            }
+        );
+    }
+
+    #[test]
+    fn a_span_over_several_lines_is_labelled_under_its_last_line() {
+        let diagnostic: Diagnostic = serde_json::from_str(
+            r#"{"message":"unused","level":"warning","spans":[
+                {"file_name":"src/main.rs","line_start":1,"line_end":2,"column_start":4,"column_end":7,
+                 "is_primary":true,"label":"here"}
+            ]}"#,
+        )
+        .unwrap();
+        let context = source_context(CONTENT, &diagnostic, "src/main.rs", 0);
+        assert_eq!(context[0].highlights, vec![(3..11, true)]);
+        assert!(context[0].labels.is_empty());
+        assert_eq!(context[1].highlights, vec![(0..6, true)]);
+        assert_eq!(context[1].labels[0].label, "here");
+    }
+
+    #[test]
+    fn spans_in_other_files_have_no_context() {
+        assert!(source_context(CONTENT, &mismatched_types(), "src/lib.rs", 1).is_empty());
+    }
+}
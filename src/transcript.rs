@@ -0,0 +1,141 @@
+use crate::host::HostEffect;
+use crate::workspace::DevEvent;
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TRANSCRIPTS_FOLDER: &str = "/data/transcripts";
+
+// a line in a transcript, the event the workspace received and the host calls it made because of
+// it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    pub event: DevEvent,
+    pub effects: Vec<HostEffect>,
+}
+
+#[derive(Debug, Default)]
+pub struct TranscriptRecorder {
+    path: Option<PathBuf>,
+}
+
+impl TranscriptRecorder {
+    pub fn start(&mut self) {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = PathBuf::from(TRANSCRIPTS_FOLDER).join(format!("{}.jsonl", started_at));
+        if let Err(e) = std::fs::create_dir_all(TRANSCRIPTS_FOLDER) {
+            eprintln!("Failed to create {}: {}", TRANSCRIPTS_FOLDER, e);
+            return;
+        }
+        eprintln!("Recording the workspace transcript to {}", path.display());
+        self.path = Some(path);
+    }
+    pub fn get_path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+    pub fn record(&self, event: &DevEvent, effects: &[HostEffect]) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let entry = TranscriptEntry {
+            event: event.clone(),
+            effects: effects.to_vec(),
+        };
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Failed to serialize transcript entry: {}", e);
+                return;
+            }
+        };
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = written {
+            eprintln!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::Workspace;
+
+    fn parse_transcript(transcript: &str) -> Result<Vec<TranscriptEntry>, String> {
+        transcript
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("Invalid transcript entry on line {}: {}", i + 1, e))
+            })
+            .collect()
+    }
+
+    // feeds the recorded events to a fresh workspace, failing on the first event that doesn't
+    // result in the recorded effects
+    fn replay(entries: &[TranscriptEntry]) -> Result<(), String> {
+        let mut workspace = Workspace::default();
+        for (i, entry) in entries.iter().enumerate() {
+            let effects = workspace.reduce(entry.event.clone());
+            if effects != entry.effects {
+                return Err(format!(
+                    "Entry {} ({:?}) resulted in:\n{:#?}\nbut the transcript expects:\n{:#?}",
+                    i + 1,
+                    entry.event,
+                    effects,
+                    entry.effects
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // a session: focusing the tab, switching away and back, reloading and closing the
+    // developed plugin
+    const TAB_FOCUS_AND_RELOAD: &str =
+        include_str!("../tests/transcripts/tab_focus_and_reload.jsonl");
+
+    #[test]
+    fn recorded_transcripts_replay() {
+        let entries = parse_transcript(TAB_FOCUS_AND_RELOAD).unwrap();
+        replay(&entries).unwrap();
+    }
+
+    #[test]
+    fn binds_once_per_tab_focus() {
+        let entries = parse_transcript(TAB_FOCUS_AND_RELOAD).unwrap();
+        let binds = entries
+            .iter()
+            .flat_map(|entry| entry.effects.iter())
+            .filter(|effect| matches!(effect, HostEffect::Reconfigure(..)))
+            .count();
+        assert_eq!(binds, 2);
+    }
+
+    #[test]
+    fn replay_reports_diverging_effects() {
+        let mut entries = parse_transcript(TAB_FOCUS_AND_RELOAD).unwrap();
+        let last_entry = entries.last_mut().unwrap();
+        last_entry.effects.clear();
+        let error = replay(&entries).unwrap_err();
+        assert!(error.starts_with(&format!("Entry {} (PaneClosed", entries.len())));
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        let error =
+            parse_transcript("\n{\"event\": \"NoSuchEvent\", \"effects\": []}").unwrap_err();
+        assert!(error.starts_with("Invalid transcript entry on line 2"));
+    }
+}
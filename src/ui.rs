@@ -0,0 +1,77 @@
+use zellij_tile::prelude::*;
+
+pub fn truncate(line: &str, cols: usize) -> String {
+    if line.chars().count() > cols {
+        line.chars()
+            .take(cols.saturating_sub(3))
+            .chain("...".chars())
+            .take(cols)
+            .collect()
+    } else {
+        line.to_owned()
+    }
+}
+
+// colors every <shortcut> in the line, eg. "<Tab> next field, <Esc> back"
+pub fn help_text(line: &str, cols: usize) -> Text {
+    let line = truncate(line, cols);
+    let mut text = Text::new(&line);
+    let mut shortcut_start = None;
+    for (i, character) in line.chars().enumerate() {
+        match character {
+            '<' => shortcut_start = Some(i),
+            '>' => {
+                if let Some(start) = shortcut_start.take() {
+                    text = text.color_range(3, start..=i);
+                }
+            }
+            _ => {}
+        }
+    }
+    text
+}
+
+// a ribbon per tab, eg. ("<p>", "Pipes"), falling back to shorter labels in narrow panes
+pub fn print_tabs(tabs: &[(&str, &str)], selected_tab: usize, y: usize, cols: usize) {
+    let full_labels: Vec<String> = tabs
+        .iter()
+        .map(|(key, name)| {
+            if key.is_empty() {
+                name.to_string()
+            } else {
+                format!("{} {}", key, name)
+            }
+        })
+        .collect();
+    let short_labels: Vec<String> = tabs.iter().map(|(_, name)| name.to_string()).collect();
+    let tiny_labels: Vec<String> = tabs
+        .iter()
+        .map(|(key, name)| {
+            if key.is_empty() {
+                name.chars().take(4).collect()
+            } else {
+                key.to_string()
+            }
+        })
+        .collect();
+    // ribbons are padded and separated by arrows
+    let width = |labels: &[String]| -> usize { labels.iter().map(|l| l.chars().count() + 4).sum() };
+    let labels = [full_labels, short_labels]
+        .iter()
+        .find(|labels| width(labels) <= cols)
+        .cloned()
+        .unwrap_or(tiny_labels);
+    let mut x = 0;
+    for (i, label) in labels.iter().enumerate() {
+        let label_width = label.chars().count() + 4;
+        if x + label_width > cols {
+            break;
+        }
+        let mut text = help_text(label, label.chars().count());
+        if i == selected_tab {
+            text = text.selected();
+        }
+        print_ribbon_with_coordinates(text, x, y, None, None);
+        x += label_width;
+    }
+}
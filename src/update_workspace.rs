@@ -1,70 +1,206 @@
-use zellij_tile::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::str::{self, FromStr};
+use zellij_tile::prelude::*;
+
+use crate::host::{Host, ZellijHost};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindingState {
+    Unbound,
+    Requested(Vec<InputMode>), // we asked zellij to bind it, but haven't seen it bound yet
+    Bound(Vec<InputMode>),
+}
+
+impl fmt::Display for BindingState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mode_names = |modes: &[InputMode]| {
+            modes
+                .iter()
+                .map(|m| format!("{:?}", m).to_lowercase())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            BindingState::Unbound => write!(f, "not bound, focus this tab to bind it"),
+            BindingState::Requested(modes) => write!(f, "binding in {}...", mode_names(modes)),
+            BindingState::Bound(modes) => write!(f, "bound in {}", mode_names(modes)),
+        }
+    }
+}
 
 #[derive(Debug)]
-pub struct UpdateWorkspace {
+pub struct UpdateWorkspace<H: Host = ZellijHost> {
     base_mode: Option<InputMode>,
     own_plugin_id: Option<u32>,
     own_tab_index: Option<usize>,
     own_tab_is_active: bool,
-    bound_key: bool,
+    bound_modes: Vec<InputMode>, // the modes we asked zellij to bind the reload shortcut in
+    binding_confirmed: bool,     // zellij reported the shortcut as bound in all of bound_modes
+    bind_in_locked_mode: bool,
     reload_shortcut: KeyWithModifier,
     tab_infos: Vec<TabInfo>,
     pane_manifest: PaneManifest,
-    plugin_pane_id: Option<PaneId>, // this is the plugin we're reloading
-    renamed_plugin_pane: bool,
+    plugin_pane_ids: Vec<u32>, // the instances of the plugin we're reloading, oldest first
+    renamed_plugin_panes: Vec<u32>,
+    close_with_plugin: bool,
+    session_name: Option<String>,
+    host: H,
 }
 
-impl UpdateWorkspace {
-    pub fn new(reload_shortcut: KeyWithModifier) -> Self {
+impl<H: Host> UpdateWorkspace<H> {
+    pub fn with_host(reload_shortcut: KeyWithModifier, host: H) -> Self {
         UpdateWorkspace {
             reload_shortcut,
             base_mode: Default::default(),
             own_plugin_id: Default::default(),
             own_tab_index: Default::default(),
             own_tab_is_active: Default::default(),
-            bound_key: Default::default(),
+            bound_modes: Default::default(),
+            binding_confirmed: Default::default(),
+            bind_in_locked_mode: Default::default(),
             tab_infos: Default::default(),
             pane_manifest: Default::default(),
-            plugin_pane_id: Default::default(),
-            renamed_plugin_pane: Default::default(),
+            plugin_pane_ids: Default::default(),
+            renamed_plugin_panes: Default::default(),
+            close_with_plugin: true,
+            session_name: Default::default(),
+            host,
         }
     }
     pub fn update_own_plugin_id(&mut self, plugin_id: u32) {
         self.own_plugin_id = Some(plugin_id);
     }
-    pub fn get_own_plugin_id(&mut self) -> Option<u32> {
+    pub fn get_own_plugin_id(&self) -> Option<u32> {
         self.own_plugin_id
     }
+    pub fn host_mut(&mut self) -> &mut H {
+        &mut self.host
+    }
+    pub fn rename_own_pane(&mut self) {
+        if let Some(own_plugin_id) = self.own_plugin_id {
+            self.host.rename_pane_with_id(
+                PaneId::Plugin(own_plugin_id),
+                "Develop Zellij Plugin".to_owned(),
+            );
+        }
+    }
     pub fn update_pane_manifest(&mut self, pane_manifest: PaneManifest) {
         self.pane_manifest = pane_manifest;
     }
     pub fn update_tab_infos(&mut self, tab_infos: Vec<TabInfo>) {
         self.tab_infos = tab_infos;
     }
+    pub fn update_session_name(&mut self, session_name: String) {
+        self.session_name = Some(session_name);
+    }
+    pub fn get_session_name(&self) -> Option<String> {
+        self.session_name.clone()
+    }
     pub fn update_base_mode(&mut self, base_mode: InputMode) {
         self.base_mode = Some(base_mode);
     }
-    pub fn pane_closed(&self, pane_id: PaneId) {
-        if Some(pane_id) == self.plugin_pane_id {
-            // if the plugin we're reloading closed, we close ourselves
-            close_self();
+    pub fn update_bind_in_locked_mode(&mut self, bind_in_locked_mode: bool) {
+        self.bind_in_locked_mode = bind_in_locked_mode;
+    }
+    // the modes our reload shortcut is currently bound in, as reported by zellij in ModeUpdate
+    pub fn update_recompile_bindings(
+        &mut self,
+        recompile_bindings: &[(InputMode, KeyWithModifier)],
+    ) {
+        let bound_in: Vec<InputMode> = recompile_bindings
+            .iter()
+            .filter(|(_, key)| key == &self.reload_shortcut)
+            .map(|(mode, _)| *mode)
+            .collect();
+        if self.bound_modes.iter().all(|mode| bound_in.contains(mode)) {
+            self.binding_confirmed = !self.bound_modes.is_empty();
+        } else if self.binding_confirmed {
+            // the binding was there and now it's gone, eg. because the configuration was
+            // reloaded, so we'll bind it again in the modes it's missing from
+            self.bound_modes.retain(|mode| bound_in.contains(mode));
+            self.binding_confirmed = false;
         }
     }
+    pub fn get_binding_state(&self) -> BindingState {
+        if self.bound_modes.is_empty() {
+            BindingState::Unbound
+        } else if self.binding_confirmed {
+            BindingState::Bound(self.bound_modes.clone())
+        } else {
+            BindingState::Requested(self.bound_modes.clone())
+        }
+    }
+    pub fn update_close_with_plugin(&mut self, close_with_plugin: bool) {
+        self.close_with_plugin = close_with_plugin;
+    }
+    pub fn get_close_with_plugin(&self) -> bool {
+        self.close_with_plugin
+    }
+    pub fn pane_closed(&mut self, pane_id: PaneId) {
+        let closed_plugin_id = match pane_id {
+            PaneId::Plugin(plugin_id) if self.plugin_pane_ids.contains(&plugin_id) => plugin_id,
+            _ => return,
+        };
+        self.plugin_pane_ids.retain(|id| *id != closed_plugin_id);
+        self.renamed_plugin_panes
+            .retain(|id| *id != closed_plugin_id);
+        if self.plugin_pane_ids.is_empty() && self.close_with_plugin {
+            // if the last instance of the plugin we're reloading closed, we close ourselves
+            self.host.close_self();
+        }
+    }
+    pub fn get_plugin_pane_ids(&self) -> &[u32] {
+        &self.plugin_pane_ids
+    }
+    // the most recently launched instance of the plugin we're reloading
+    pub fn get_plugin_pane_info(&self) -> Option<&PaneInfo> {
+        let plugin_id = self.plugin_pane_ids.last()?;
+        self.pane_manifest
+            .panes
+            .values()
+            .flatten()
+            .find(|p| p.is_plugin && p.id == *plugin_id)
+    }
     pub fn get_reload_shortcut(&self) -> &KeyWithModifier {
         &self.reload_shortcut
     }
     pub fn bind_key_if_not_bound_and_tab_is_focused(&mut self) {
         self.update_own_tab_index();
         self.update_own_tab_is_active();
-        match (self.base_mode, self.own_plugin_id) {
-            (Some(base_mode), Some(own_plugin_id)) if self.own_tab_is_active && !self.bound_key => {
-                bind_key(base_mode, own_plugin_id, &self.reload_shortcut);
-                self.bound_key = true;
+        let unbound_modes: Vec<InputMode> = self
+            .wanted_modes()
+            .into_iter()
+            .filter(|mode| !self.bound_modes.contains(mode))
+            .collect();
+        match self.own_plugin_id {
+            Some(own_plugin_id) if self.own_tab_is_active && !unbound_modes.is_empty() => {
+                bind_key(
+                    &mut self.host,
+                    &unbound_modes,
+                    own_plugin_id,
+                    &self.reload_shortcut,
+                );
+                // the binding in the previous base mode (if any) stays, so we keep track of it
+                // as well
+                self.bound_modes.extend(unbound_modes);
+                self.binding_confirmed = false;
             }
             _ => {}
         }
     }
+    // the shortcut follows the base mode, and can also be bound in locked mode so that it works
+    // while the other zellij keys are locked
+    fn wanted_modes(&self) -> Vec<InputMode> {
+        let mut wanted_modes = vec![];
+        if let Some(base_mode) = self.base_mode {
+            wanted_modes.push(base_mode);
+            if self.bind_in_locked_mode && base_mode != InputMode::Locked {
+                wanted_modes.push(InputMode::Locked);
+            }
+        }
+        wanted_modes
+    }
     fn update_own_tab_is_active(&mut self) {
         if let Some(own_tab) = self
             .tab_infos
@@ -75,7 +211,8 @@ impl UpdateWorkspace {
             if !own_tab.active {
                 // this is so that when we our tab loses focus, we'll rebind the key once it gains
                 // it
-                self.bound_key = false;
+                self.bound_modes.clear();
+                self.binding_confirmed = false;
             }
         }
     }
@@ -87,28 +224,53 @@ impl UpdateWorkspace {
             self.own_tab_index = Some(own_tab_index);
         }
     }
-    pub fn update_plugin_pane_id(&mut self, plugin_url: Option<String>) {
-        if self.plugin_pane_id.is_none() {
-            for (_tab_index, panes) in &self.pane_manifest.panes {
-                for pane in panes {
-                    if pane.plugin_url == plugin_url {
-                        self.plugin_pane_id = Some(PaneId::Plugin(pane.id));
-                        return;
-                    }
-                }
+    // tracks every pane (in every tab) running the plugin we're reloading, so that we keep track
+    // of it when it's relaunched or opened more than once
+    pub fn update_plugin_pane_ids(&mut self, is_developed_plugin: impl Fn(&str) -> bool) {
+        let own_plugin_id = self.own_plugin_id;
+        let mut current_plugin_pane_ids: Vec<u32> = self
+            .pane_manifest
+            .panes
+            .values()
+            .flatten()
+            .filter(|p| p.is_plugin && Some(p.id) != own_plugin_id)
+            .filter(|p| p.plugin_url.as_deref().map(&is_developed_plugin) == Some(true))
+            .map(|p| p.id)
+            .collect();
+        // plugin ids grow with every plugin zellij launches
+        current_plugin_pane_ids.sort_unstable();
+        self.plugin_pane_ids
+            .retain(|id| current_plugin_pane_ids.contains(id));
+        for plugin_pane_id in current_plugin_pane_ids {
+            if !self.plugin_pane_ids.contains(&plugin_pane_id) {
+                self.plugin_pane_ids.push(plugin_pane_id);
             }
         }
+        let plugin_pane_ids = &self.plugin_pane_ids;
+        self.renamed_plugin_panes
+            .retain(|id| plugin_pane_ids.contains(id));
     }
-    pub fn rename_plugin_pane_if_needed(&mut self, plugin_name: Option<String>) {
-        if !self.renamed_plugin_pane {
-            if let Some(plugin_pane_id) = self.plugin_pane_id.as_ref() {
-                if let Some(plugin_name) = plugin_name {
-                    rename_pane_with_id(
-                        *plugin_pane_id,
-                        format!("{} (<Ctrl-Shift r> to rebuild)", plugin_name),
-                    );
-                    self.renamed_plugin_pane = true;
-                }
+    // plugin_name gets the pane's plugin url, as we might develop several plugins at once
+    pub fn rename_plugin_pane_if_needed(&mut self, plugin_name: impl Fn(&str) -> Option<String>) {
+        let plugin_urls: BTreeMap<u32, &str> = self
+            .pane_manifest
+            .panes
+            .values()
+            .flatten()
+            .filter(|p| p.is_plugin)
+            .filter_map(|p| p.plugin_url.as_deref().map(|u| (p.id, u)))
+            .collect();
+        for plugin_pane_id in &self.plugin_pane_ids {
+            let plugin_name = match plugin_urls.get(plugin_pane_id).and_then(|u| plugin_name(u)) {
+                Some(plugin_name) => plugin_name,
+                None => continue,
+            };
+            if !self.renamed_plugin_panes.contains(plugin_pane_id) {
+                self.host.rename_pane_with_id(
+                    PaneId::Plugin(*plugin_pane_id),
+                    format!("{} (<{}> to rebuild)", plugin_name, self.reload_shortcut),
+                );
+                self.renamed_plugin_panes.push(*plugin_pane_id);
             }
         }
     }
@@ -116,7 +278,6 @@ impl UpdateWorkspace {
         if let Ok(reload_shortcut) = KeyWithModifier::from_str(reload_shortcut) {
             self.reload_shortcut = reload_shortcut;
         }
-
     }
 }
 
@@ -131,22 +292,464 @@ pub fn get_tab_index_of_pane(own_pane_id: u32, pane_manifest: &PaneManifest) ->
     None
 }
 
-pub fn bind_key(base_mode: InputMode, own_plugin_id: u32, reload_shortcut: &KeyWithModifier) {
-    let new_config = format!(
-        "
-        keybinds {{
+pub fn bind_key(
+    host: &mut impl Host,
+    modes: &[InputMode],
+    own_plugin_id: u32,
+    reload_shortcut: &KeyWithModifier,
+) {
+    let mode_binds: String = modes
+        .iter()
+        .map(|mode| {
+            format!(
+                "
             {:?} {{
                 bind \"{}\" {{
                     MessagePluginId {} {{
                         name \"recompile\"
                     }}
                 }}
-            }}
+            }}",
+                format!("{:?}", mode).to_lowercase(),
+                reload_shortcut,
+                own_plugin_id
+            )
+        })
+        .collect();
+    let new_config = format!(
+        "
+        keybinds {{{}
         }}
         ",
-        format!("{:?}", base_mode).to_lowercase(),
-        reload_shortcut,
-        own_plugin_id
+        mode_binds
     );
-    reconfigure(new_config, false);
+    host.reconfigure(new_config, false);
+}
+
+// the keys bound to send the "recompile" message to the plugin with this id, in each mode
+pub fn recompile_bindings(
+    keybinds: &KeybindsVec,
+    own_plugin_id: Option<u32>,
+) -> Vec<(InputMode, KeyWithModifier)> {
+    let mut recompile_bindings = vec![];
+    for (mode, mode_keybinds) in keybinds {
+        for (key, key_actions) in mode_keybinds {
+            let sends_recompile = key_actions.iter().any(|action| match action {
+                actions::Action::KeybindPipe {
+                    name: Some(name),
+                    plugin_id,
+                    ..
+                } => name == "recompile" && plugin_id.is_some() && *plugin_id == own_plugin_id,
+                _ => false,
+            });
+            if sends_recompile {
+                recompile_bindings.push((*mode, key.clone()));
+            }
+        }
+    }
+    recompile_bindings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostEffect, RecordingHost};
+
+    use std::collections::HashMap;
+
+    const OWN_PLUGIN_ID: u32 = 1;
+    const DEVELOPED_PLUGIN_ID: u32 = 2;
+    const DEVELOPED_PLUGIN_URL: &str = "file:/projects/my-plugin/target/my-plugin.wasm";
+
+    fn update_workspace() -> UpdateWorkspace<RecordingHost> {
+        let reload_shortcut = KeyWithModifier::from_str("Ctrl Shift r").unwrap();
+        let mut update_workspace =
+            UpdateWorkspace::with_host(reload_shortcut, RecordingHost::default());
+        update_workspace.update_own_plugin_id(OWN_PLUGIN_ID);
+        let mut panes = HashMap::new();
+        panes.insert(
+            0,
+            vec![
+                PaneInfo {
+                    id: OWN_PLUGIN_ID,
+                    is_plugin: true,
+                    ..Default::default()
+                },
+                PaneInfo {
+                    id: DEVELOPED_PLUGIN_ID,
+                    is_plugin: true,
+                    plugin_url: Some(DEVELOPED_PLUGIN_URL.to_owned()),
+                    ..Default::default()
+                },
+            ],
+        );
+        update_workspace.update_pane_manifest(PaneManifest { panes });
+        update_workspace
+    }
+
+    fn tabs(active_tab: usize) -> Vec<TabInfo> {
+        (0..2)
+            .map(|position| TabInfo {
+                position,
+                active: position == active_tab,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    // the modes the reload shortcut was bound in, eg. `"normal" {` in the keybinds block
+    fn bound_modes(effects: Vec<HostEffect>) -> Vec<String> {
+        effects
+            .into_iter()
+            .filter_map(|effect| match effect {
+                HostEffect::Reconfigure(config, false) => config
+                    .lines()
+                    .map(|l| l.trim())
+                    .skip_while(|l| !l.starts_with("keybinds"))
+                    .nth(1)
+                    .map(|l| l.trim_end_matches('{').trim().trim_matches('"').to_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn binds_the_reload_shortcut_once_the_tab_is_focused() {
+        let mut update_workspace = update_workspace();
+        update_workspace.update_base_mode(InputMode::Normal);
+        update_workspace.update_tab_infos(tabs(1));
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        assert!(update_workspace.host.take_effects().is_empty());
+
+        update_workspace.update_tab_infos(tabs(0));
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        let effects = update_workspace.host.take_effects();
+        match effects.as_slice() {
+            [HostEffect::Reconfigure(config, false)] => {
+                assert!(config.contains("bind \"Ctrl Shift r\""));
+                assert!(config.contains(&format!("MessagePluginId {}", OWN_PLUGIN_ID)));
+                assert!(config.contains("name \"recompile\""));
+            }
+            effects => panic!("unexpected effects: {:?}", effects),
+        }
+        assert_eq!(bound_modes(effects), vec!["normal".to_owned()]);
+    }
+
+    #[test]
+    fn does_not_bind_before_the_base_mode_is_known() {
+        let mut update_workspace = update_workspace();
+        update_workspace.update_tab_infos(tabs(0));
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        assert!(update_workspace.host.take_effects().is_empty());
+    }
+
+    #[test]
+    fn does_not_rebind_while_the_tab_stays_focused() {
+        let mut update_workspace = update_workspace();
+        update_workspace.update_base_mode(InputMode::Normal);
+        update_workspace.update_tab_infos(tabs(0));
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        update_workspace.host.take_effects();
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        assert!(update_workspace.host.take_effects().is_empty());
+    }
+
+    #[test]
+    fn rebinds_when_the_tab_regains_focus() {
+        let mut update_workspace = update_workspace();
+        update_workspace.update_base_mode(InputMode::Normal);
+        update_workspace.update_tab_infos(tabs(0));
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        update_workspace.host.take_effects();
+
+        update_workspace.update_tab_infos(tabs(1));
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        assert!(update_workspace.host.take_effects().is_empty());
+
+        update_workspace.update_base_mode(InputMode::Locked);
+        update_workspace.update_tab_infos(tabs(0));
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        assert_eq!(
+            bound_modes(update_workspace.host.take_effects()),
+            vec!["locked".to_owned()]
+        );
+    }
+
+    #[test]
+    fn closes_itself_when_the_developed_plugin_closes() {
+        let mut update_workspace = update_workspace();
+        update_workspace.update_plugin_pane_ids(|url| url == DEVELOPED_PLUGIN_URL);
+        update_workspace.pane_closed(PaneId::Terminal(DEVELOPED_PLUGIN_ID));
+        update_workspace.pane_closed(PaneId::Plugin(3));
+        assert!(update_workspace.host.take_effects().is_empty());
+        update_workspace.pane_closed(PaneId::Plugin(DEVELOPED_PLUGIN_ID));
+        assert_eq!(
+            update_workspace.host.take_effects(),
+            vec![HostEffect::CloseSelf]
+        );
+    }
+
+    #[test]
+    fn renames_the_developed_plugin_pane_once() {
+        let mut update_workspace = update_workspace();
+        update_workspace.rename_plugin_pane_if_needed(|_| Some("my-plugin".to_owned()));
+        assert!(update_workspace.host.take_effects().is_empty());
+
+        update_workspace.update_plugin_pane_ids(|url| url == DEVELOPED_PLUGIN_URL);
+        update_workspace.rename_plugin_pane_if_needed(|_| Some("my-plugin".to_owned()));
+        update_workspace.rename_plugin_pane_if_needed(|_| Some("my-plugin".to_owned()));
+        assert_eq!(
+            update_workspace.host.take_effects(),
+            vec![HostEffect::RenamePaneWithId(
+                PaneId::Plugin(DEVELOPED_PLUGIN_ID),
+                "my-plugin (<Ctrl Shift r> to rebuild)".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn names_the_configured_shortcut_in_the_pane_title() {
+        let mut update_workspace = update_workspace();
+        update_workspace.update_reload_shortcut("Alt b");
+        update_workspace.update_plugin_pane_ids(|url| url == DEVELOPED_PLUGIN_URL);
+        update_workspace.rename_plugin_pane_if_needed(|_| Some("my-plugin".to_owned()));
+        assert_eq!(
+            update_workspace.host.take_effects(),
+            vec![HostEffect::RenamePaneWithId(
+                PaneId::Plugin(DEVELOPED_PLUGIN_ID),
+                "my-plugin (<Alt b> to rebuild)".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn finds_the_tab_of_a_plugin_pane() {
+        let update_workspace = update_workspace();
+        assert_eq!(
+            get_tab_index_of_pane(DEVELOPED_PLUGIN_ID, &update_workspace.pane_manifest),
+            Some(0)
+        );
+        assert_eq!(
+            get_tab_index_of_pane(7, &update_workspace.pane_manifest),
+            None
+        );
+    }
+
+    fn focused_and_bound(base_mode: InputMode) -> UpdateWorkspace<RecordingHost> {
+        let mut update_workspace = update_workspace();
+        update_workspace.update_base_mode(base_mode);
+        update_workspace.update_tab_infos(tabs(0));
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        update_workspace.host.take_effects();
+        update_workspace
+    }
+
+    fn bound_in(modes: &[InputMode]) -> Vec<(InputMode, KeyWithModifier)> {
+        let reload_shortcut = KeyWithModifier::from_str("Ctrl Shift r").unwrap();
+        modes
+            .iter()
+            .map(|mode| (*mode, reload_shortcut.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn binding_follows_the_base_mode() {
+        let mut update_workspace = focused_and_bound(InputMode::Normal);
+        update_workspace.update_recompile_bindings(&bound_in(&[InputMode::Normal]));
+        assert_eq!(
+            update_workspace.get_binding_state(),
+            BindingState::Bound(vec![InputMode::Normal])
+        );
+
+        update_workspace.update_base_mode(InputMode::Locked);
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        assert_eq!(
+            bound_modes(update_workspace.host.take_effects()),
+            vec!["locked".to_owned()]
+        );
+        assert_eq!(
+            update_workspace.get_binding_state(),
+            BindingState::Requested(vec![InputMode::Normal, InputMode::Locked])
+        );
+
+        // switching back doesn't need another binding
+        update_workspace.update_base_mode(InputMode::Normal);
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        assert!(update_workspace.host.take_effects().is_empty());
+    }
+
+    #[test]
+    fn binds_in_locked_mode_too_when_configured() {
+        let mut update_workspace = update_workspace();
+        update_workspace.update_bind_in_locked_mode(true);
+        update_workspace.update_base_mode(InputMode::Normal);
+        update_workspace.update_tab_infos(tabs(0));
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        let effects = update_workspace.host.take_effects();
+        match effects.as_slice() {
+            [HostEffect::Reconfigure(config, false)] => {
+                assert!(config.contains("\"normal\" {"));
+                assert!(config.contains("\"locked\" {"));
+            }
+            effects => panic!("unexpected effects: {:?}", effects),
+        }
+    }
+
+    #[test]
+    fn rebinds_after_the_configuration_wiped_the_binding() {
+        let mut update_workspace = focused_and_bound(InputMode::Normal);
+        update_workspace.update_recompile_bindings(&bound_in(&[InputMode::Normal]));
+        update_workspace.update_recompile_bindings(&[]);
+        assert_eq!(update_workspace.get_binding_state(), BindingState::Unbound);
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        assert_eq!(
+            bound_modes(update_workspace.host.take_effects()),
+            vec!["normal".to_owned()]
+        );
+    }
+
+    #[test]
+    fn does_not_rebind_before_zellij_applied_the_binding() {
+        let mut update_workspace = focused_and_bound(InputMode::Normal);
+        // a ModeUpdate sent before zellij applied our configuration
+        update_workspace.update_recompile_bindings(&[]);
+        update_workspace.bind_key_if_not_bound_and_tab_is_focused();
+        assert!(update_workspace.host.take_effects().is_empty());
+        assert_eq!(
+            update_workspace.get_binding_state(),
+            BindingState::Requested(vec![InputMode::Normal])
+        );
+    }
+
+    #[test]
+    fn finds_the_recompile_bindings_of_this_plugin() {
+        let keybind_pipe = |name: &str, plugin_id: u32| actions::Action::KeybindPipe {
+            name: Some(name.to_owned()),
+            payload: None,
+            args: None,
+            plugin: None,
+            plugin_id: Some(plugin_id),
+            configuration: None,
+            launch_new: false,
+            skip_cache: false,
+            floating: None,
+            in_place: None,
+            cwd: None,
+            pane_title: None,
+        };
+        let reload_shortcut = KeyWithModifier::from_str("Ctrl Shift r").unwrap();
+        let other_key = KeyWithModifier::from_str("Ctrl Shift x").unwrap();
+        let keybinds = vec![
+            (
+                InputMode::Normal,
+                vec![
+                    (
+                        reload_shortcut.clone(),
+                        vec![keybind_pipe("recompile", OWN_PLUGIN_ID)],
+                    ),
+                    (
+                        other_key.clone(),
+                        vec![keybind_pipe("other", OWN_PLUGIN_ID)],
+                    ),
+                ],
+            ),
+            (
+                InputMode::Locked,
+                vec![(other_key, vec![keybind_pipe("recompile", 7)])],
+            ),
+        ];
+        assert_eq!(
+            recompile_bindings(&keybinds, Some(OWN_PLUGIN_ID)),
+            vec![(InputMode::Normal, reload_shortcut)]
+        );
+    }
+
+    #[test]
+    fn describes_the_binding_state() {
+        assert_eq!(
+            BindingState::Bound(vec![InputMode::Normal, InputMode::Locked]).to_string(),
+            "bound in normal, locked"
+        );
+        assert_eq!(
+            BindingState::Requested(vec![InputMode::Normal]).to_string(),
+            "binding in normal..."
+        );
+    }
+
+    fn developed_plugin_pane(id: u32) -> PaneInfo {
+        PaneInfo {
+            id,
+            is_plugin: true,
+            plugin_url: Some(DEVELOPED_PLUGIN_URL.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn with_panes(update_workspace: &mut UpdateWorkspace<RecordingHost>, tabs: Vec<Vec<PaneInfo>>) {
+        let panes = tabs.into_iter().enumerate().collect();
+        update_workspace.update_pane_manifest(PaneManifest { panes });
+        update_workspace.update_plugin_pane_ids(|url| url == DEVELOPED_PLUGIN_URL);
+    }
+
+    #[test]
+    fn tracks_every_instance_across_tabs() {
+        let mut update_workspace = update_workspace();
+        with_panes(
+            &mut update_workspace,
+            vec![
+                vec![developed_plugin_pane(2)],
+                vec![developed_plugin_pane(4), developed_plugin_pane(3)],
+            ],
+        );
+        assert_eq!(update_workspace.get_plugin_pane_ids(), &[2, 3, 4]);
+        update_workspace.rename_plugin_pane_if_needed(|_| Some("my-plugin".to_owned()));
+        assert_eq!(update_workspace.host.take_effects().len(), 3);
+        assert_eq!(
+            update_workspace.get_plugin_pane_info().map(|p| p.id),
+            Some(4)
+        );
+
+        update_workspace.pane_closed(PaneId::Plugin(2));
+        update_workspace.pane_closed(PaneId::Plugin(4));
+        assert!(update_workspace.host.take_effects().is_empty());
+        assert_eq!(
+            update_workspace.get_plugin_pane_info().map(|p| p.id),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn follows_the_plugin_when_it_is_relaunched() {
+        let mut update_workspace = update_workspace();
+        with_panes(&mut update_workspace, vec![vec![developed_plugin_pane(2)]]);
+        with_panes(&mut update_workspace, vec![vec![]]);
+        with_panes(&mut update_workspace, vec![vec![developed_plugin_pane(5)]]);
+        assert_eq!(update_workspace.get_plugin_pane_ids(), &[5]);
+        assert_eq!(
+            update_workspace.get_plugin_pane_info().map(|p| p.id),
+            Some(5)
+        );
+
+        update_workspace.rename_plugin_pane_if_needed(|_| Some("my-plugin".to_owned()));
+        assert_eq!(
+            update_workspace.host.take_effects(),
+            vec![HostEffect::RenamePaneWithId(
+                PaneId::Plugin(5),
+                "my-plugin (<Ctrl Shift r> to rebuild)".to_owned()
+            )]
+        );
+        // the old instance is gone, closing it again doesn't close us
+        update_workspace.pane_closed(PaneId::Plugin(2));
+        assert!(update_workspace.host.take_effects().is_empty());
+    }
+
+    #[test]
+    fn stays_open_when_configured_to() {
+        let mut update_workspace = update_workspace();
+        update_workspace.update_close_with_plugin(false);
+        with_panes(&mut update_workspace, vec![vec![developed_plugin_pane(2)]]);
+        update_workspace.pane_closed(PaneId::Plugin(2));
+        assert!(update_workspace.host.take_effects().is_empty());
+        assert!(update_workspace.get_plugin_pane_ids().is_empty());
+    }
 }
@@ -0,0 +1,305 @@
+use crate::build_progress::plural;
+use crate::cargo_messages::Diagnostic;
+use crate::deadline::Deadline;
+use crate::host::Host;
+use crate::project_data::ProjectData;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+// how long after the build exited we wait for cargo's last message before reloading anyway
+const COUNTING_TIMEOUT_SECS: f64 = 5.0;
+
+// compares the warnings of each build we follow to a baseline (the warnings of the last
+// successful build that added none), and can hold back the reload when there are too many
+#[derive(Debug, Default)]
+pub struct WarningBudget {
+    budget: Option<usize>, // the most warnings a build can have and still be reloaded
+    block_new_warnings: bool, // don't reload when a build has warnings the baseline doesn't
+    project_folder: Option<PathBuf>,
+    baseline: Option<BTreeSet<String>>, // None until a build of the project succeeded
+    warnings: BTreeSet<String>,         // of the last build
+    new_warnings: BTreeSet<String>,
+    reload_allowed: Option<bool>, // once the build's warnings were counted
+    blocked_reason: Option<String>,
+    counting_timeout: Deadline,
+    counting_timed_out: bool, // the last build's warnings weren't counted, so it wasn't held
+}
+
+impl WarningBudget {
+    pub fn update_config(&mut self, configuration: &BTreeMap<String, String>) {
+        self.budget =
+            configuration
+                .get("warning_budget")
+                .and_then(|budget| match budget.trim().parse() {
+                    Ok(budget) => Some(budget),
+                    Err(_) => {
+                        eprintln!("Ignoring warning_budget that isn't a number: {}", budget);
+                        None
+                    }
+                });
+        self.block_new_warnings =
+            configuration.get("block_new_warnings").map(|b| b.as_str()) == Some("true");
+    }
+    // whether the warnings can keep a build from being reloaded
+    pub fn is_enforced(&self) -> bool {
+        self.budget.is_some() || self.block_new_warnings
+    }
+    pub fn update_project_folder(&mut self, project_data: &ProjectData, project_folder: PathBuf) {
+        self.baseline = project_data
+            .read(&project_folder, "warning_budget", "json")
+            .and_then(|baseline| match serde_json::from_str(&baseline) {
+                Ok(baseline) => Some(baseline),
+                Err(e) => {
+                    eprintln!("Failed to parse the warning baseline: {}", e);
+                    None
+                }
+            });
+        self.project_folder = Some(project_folder);
+        self.warnings.clear();
+        self.new_warnings.clear();
+        self.blocked_reason = None;
+    }
+    // we can't count the warnings of a build we don't follow, so we don't hold its reload
+    pub fn build_started(&mut self, following: bool) {
+        self.reload_allowed = if following && self.is_enforced() {
+            None
+        } else {
+            Some(true)
+        };
+        self.counting_timeout.cancel();
+        self.counting_timed_out = false;
+    }
+    // the build exited, its last messages might never arrive (eg. if cargo's output was cut off)
+    pub fn build_exited(&mut self, host: &mut impl Host) {
+        if self.reload_allowed.is_none() {
+            self.counting_timeout.schedule(host, COUNTING_TIMEOUT_SECS);
+        }
+    }
+    // returns true if we gave up counting the warnings, so the reload can be released
+    pub fn timer_elapsed(&mut self) -> bool {
+        if self.counting_timeout.elapsed() && self.reload_allowed.is_none() {
+            self.stop_counting();
+            return true;
+        }
+        false
+    }
+    pub fn build_finished(
+        &mut self,
+        project_data: &ProjectData,
+        succeeded: bool,
+        warnings: &[&Diagnostic],
+    ) {
+        self.counting_timeout.cancel();
+        self.warnings = warnings.iter().map(|w| warning_key(w)).collect();
+        self.new_warnings = match &self.baseline {
+            Some(baseline) => self.warnings.difference(baseline).cloned().collect(),
+            None => BTreeSet::new(),
+        };
+        self.update_blocked_reason();
+        if self.reload_allowed.is_none() {
+            self.reload_allowed = Some(self.blocked_reason.is_none());
+        }
+        // fixing warnings lowers the baseline, adding some takes accepting them
+        if succeeded && self.new_warnings.is_empty() {
+            self.save_baseline(project_data);
+        }
+    }
+    // makes the last build's warnings the baseline, so that none of them are new anymore
+    pub fn accept_warnings(&mut self, project_data: &ProjectData) {
+        self.new_warnings.clear();
+        self.save_baseline(project_data);
+        self.update_blocked_reason();
+    }
+    pub fn get_reload_allowed(&self) -> Option<bool> {
+        self.reload_allowed
+    }
+    pub fn is_blocking(&self) -> bool {
+        self.blocked_reason.is_some()
+    }
+    pub fn is_new(&self, warning: &Diagnostic) -> bool {
+        warning.is_warning() && self.new_warnings.contains(&warning_key(warning))
+    }
+    pub fn get_new_warning_count(&self) -> usize {
+        self.new_warnings.len()
+    }
+    // eg. "12, 2 new since the baseline, budget 10 - reload blocked: 12 warnings, over the budget
+    // of 10"
+    pub fn summary(&self) -> Option<String> {
+        if self.baseline.is_none() && !self.is_enforced() {
+            return None;
+        }
+        let mut summary = match self.baseline {
+            Some(_) => format!(
+                "{}, {} new since the baseline",
+                self.warnings.len(),
+                self.new_warnings.len()
+            ),
+            None => "no baseline yet".to_owned(),
+        };
+        if let Some(budget) = self.budget {
+            summary.push_str(&format!(", budget {}", budget));
+        }
+        if let Some(blocked_reason) = &self.blocked_reason {
+            summary.push_str(&format!(" - reload blocked: {}", blocked_reason));
+        }
+        if self.counting_timed_out {
+            summary.push_str(" - the last build's warnings weren't counted, so it was reloaded");
+        }
+        Some(summary)
+    }
+    fn stop_counting(&mut self) {
+        self.reload_allowed = Some(true);
+        self.counting_timed_out = true;
+    }
+    fn update_blocked_reason(&mut self) {
+        self.blocked_reason = match self.budget {
+            Some(budget) if self.warnings.len() > budget => Some(format!(
+                "{}, over the budget of {}",
+                plural(self.warnings.len(), "warning"),
+                budget
+            )),
+            _ if self.block_new_warnings && !self.new_warnings.is_empty() => Some(format!(
+                "{} since the baseline",
+                plural(self.new_warnings.len(), "new warning")
+            )),
+            _ => None,
+        };
+    }
+    fn save_baseline(&mut self, project_data: &ProjectData) {
+        self.baseline = Some(self.warnings.clone());
+        if let Some(project_folder) = &self.project_folder {
+            match serde_json::to_string(&self.warnings) {
+                Ok(baseline) => {
+                    project_data.write(project_folder, "warning_budget", "json", &baseline)
+                }
+                Err(e) => eprintln!("Failed to serialize the warning baseline: {}", e),
+            }
+        }
+    }
+}
+
+// identifies a warning across builds, by its file, message and source line rather than by its
+// line number, which changes whenever code is added above it
+fn warning_key(warning: &Diagnostic) -> String {
+    match warning.primary_span() {
+        Some(span) => {
+            let line = span.text.first().map(|t| t.text.trim()).unwrap_or_default();
+            format!("{}: {}: {}", span.file_name, warning.message, line)
+        }
+        None => warning.message.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warning(message: &str, line_start: usize) -> Diagnostic {
+        serde_json::from_str(&format!(
+            r#"{{"message":"{}","level":"warning","spans":[{{"file_name":"src/main.rs",
+                "line_start":{},"line_end":{},"column_start":9,"column_end":10,"is_primary":true,
+                "text":[{{"text":"    let x = 5;","highlight_start":9,"highlight_end":10}}]}}]}}"#,
+            message, line_start, line_start
+        ))
+        .unwrap()
+    }
+
+    fn enforced(budget: Option<usize>, block_new_warnings: bool) -> WarningBudget {
+        WarningBudget {
+            budget,
+            block_new_warnings,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_moved_warning_isnt_new_but_an_added_one_is() {
+        let mut warning_budget = enforced(None, true);
+        warning_budget.build_started(true);
+        warning_budget.build_finished(
+            &ProjectData::default(),
+            true,
+            &[&warning("unused variable: `x`", 3)],
+        );
+        assert_eq!(warning_budget.get_reload_allowed(), Some(true));
+        let moved = warning("unused variable: `x`", 7);
+        let dead_code = warning("function `f` is never used", 12);
+        warning_budget.build_started(true);
+        assert_eq!(warning_budget.get_reload_allowed(), None);
+        warning_budget.build_finished(&ProjectData::default(), true, &[&moved, &dead_code]);
+        assert!(!warning_budget.is_new(&moved));
+        assert!(warning_budget.is_new(&dead_code));
+        assert_eq!(warning_budget.get_new_warning_count(), 1);
+        assert_eq!(warning_budget.get_reload_allowed(), Some(false));
+    }
+
+    #[test]
+    fn accepting_the_warnings_makes_them_the_baseline() {
+        let mut warning_budget = enforced(None, true);
+        let dead_code = warning("function `f` is never used", 12);
+        warning_budget.build_started(true);
+        warning_budget.build_finished(&ProjectData::default(), true, &[]);
+        warning_budget.build_started(true);
+        warning_budget.build_finished(&ProjectData::default(), true, &[&dead_code]);
+        assert!(warning_budget.is_blocking());
+        warning_budget.accept_warnings(&ProjectData::default());
+        assert!(!warning_budget.is_blocking());
+        assert!(!warning_budget.is_new(&dead_code));
+        warning_budget.build_started(true);
+        warning_budget.build_finished(&ProjectData::default(), true, &[&dead_code]);
+        assert_eq!(warning_budget.get_reload_allowed(), Some(true));
+    }
+
+    #[test]
+    fn more_warnings_than_the_budget_block_the_reload() {
+        let mut warning_budget = enforced(Some(1), false);
+        warning_budget.build_started(true);
+        warning_budget.build_finished(
+            &ProjectData::default(),
+            true,
+            &[
+                &warning("unused variable: `x`", 3),
+                &warning("unused variable: `y`", 4),
+            ],
+        );
+        assert_eq!(warning_budget.get_reload_allowed(), Some(false));
+        assert_eq!(
+            warning_budget.summary().as_deref(),
+            Some("2, 0 new since the baseline, budget 1 - reload blocked: 2 warnings, over the budget of 1")
+        );
+    }
+
+    #[test]
+    fn unfollowed_builds_and_builds_without_a_budget_arent_held() {
+        let mut warning_budget = enforced(Some(0), false);
+        warning_budget.build_started(false);
+        assert_eq!(warning_budget.get_reload_allowed(), Some(true));
+        let mut warning_budget = WarningBudget::default();
+        warning_budget.build_started(true);
+        assert_eq!(warning_budget.get_reload_allowed(), Some(true));
+        warning_budget.build_finished(
+            &ProjectData::default(),
+            true,
+            &[&warning("unused variable: `x`", 3)],
+        );
+        assert_eq!(warning_budget.get_reload_allowed(), Some(true));
+    }
+
+    #[test]
+    fn a_build_whose_end_never_arrives_is_reloaded_after_the_timeout() {
+        let mut warning_budget = enforced(Some(0), false);
+        warning_budget.build_started(true);
+        // no finished update, eg. because the progress file couldn't be read
+        assert!(!warning_budget.timer_elapsed());
+        assert_eq!(warning_budget.get_reload_allowed(), None);
+        warning_budget.stop_counting();
+        assert_eq!(warning_budget.get_reload_allowed(), Some(true));
+        assert!(warning_budget
+            .summary()
+            .unwrap()
+            .ends_with("the last build's warnings weren't counted, so it was reloaded"));
+        warning_budget.build_started(true);
+        assert_eq!(warning_budget.get_reload_allowed(), None);
+    }
+}
@@ -0,0 +1,417 @@
+// the imports and exports of a wasm module, read straight from its binary format (see
+// https://webassembly.github.io/spec/core/binary/modules.html), which is all we need to tell
+// whether zellij can load it
+
+const MAGIC: &[u8] = b"\0asm";
+const IMPORT_SECTION: u8 = 2;
+const EXPORT_SECTION: u8 = 7;
+
+// what register_plugin! exports, zellij calls _start (the plugin's main) and then load
+const PLUGIN_EXPORTS: [&str; 6] = [
+    "_start",
+    "load",
+    "update",
+    "pipe",
+    "render",
+    "plugin_version",
+];
+// what zellij provides besides WASI, every plugin command goes through this one function
+const ZELLIJ_MODULE: &str = "zellij";
+const ZELLIJ_FUNCTIONS: [&str; 1] = ["host_run_plugin_command"];
+const WASI_MODULE: &str = "wasi_snapshot_preview1";
+const WASI_FUNCTIONS: [&str; 46] = [
+    "args_get",
+    "args_sizes_get",
+    "environ_get",
+    "environ_sizes_get",
+    "clock_res_get",
+    "clock_time_get",
+    "fd_advise",
+    "fd_allocate",
+    "fd_close",
+    "fd_datasync",
+    "fd_fdstat_get",
+    "fd_fdstat_set_flags",
+    "fd_fdstat_set_rights",
+    "fd_filestat_get",
+    "fd_filestat_set_size",
+    "fd_filestat_set_times",
+    "fd_pread",
+    "fd_prestat_get",
+    "fd_prestat_dir_name",
+    "fd_pwrite",
+    "fd_read",
+    "fd_readdir",
+    "fd_renumber",
+    "fd_seek",
+    "fd_sync",
+    "fd_tell",
+    "fd_write",
+    "path_create_directory",
+    "path_filestat_get",
+    "path_filestat_set_times",
+    "path_link",
+    "path_open",
+    "path_readlink",
+    "path_remove_directory",
+    "path_rename",
+    "path_symlink",
+    "path_unlink_file",
+    "poll_oneoff",
+    "proc_exit",
+    "proc_raise",
+    "sched_yield",
+    "random_get",
+    "sock_accept",
+    "sock_recv",
+    "sock_send",
+    "sock_shutdown",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalKind {
+    Function,
+    Table,
+    Memory,
+    Global,
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    pub kind: ExternalKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub name: String,
+    pub kind: ExternalKind,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WasmModule {
+    pub imports: Vec<Import>,
+    pub exports: Vec<Export>,
+}
+
+impl WasmModule {
+    pub fn parse(bytes: &[u8]) -> Result<WasmModule, String> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err("it isn't a wasm module".to_owned());
+        }
+        if reader.take(4)? != [1, 0, 0, 0] {
+            return Err("it isn't a version 1 wasm module (eg. it's a component)".to_owned());
+        }
+        let mut module = WasmModule::default();
+        while !reader.is_at_end() {
+            let id = reader.byte()?;
+            let size = reader.u32()? as usize;
+            let mut section = Reader {
+                bytes: reader.take(size)?,
+                position: 0,
+            };
+            // the other sections (eg. the code) are skipped whole
+            match id {
+                IMPORT_SECTION => {
+                    for _ in 0..section.u32()? {
+                        let module_name = section.name()?;
+                        let name = section.name()?;
+                        let kind = section.import_description()?;
+                        module.imports.push(Import {
+                            module: module_name,
+                            name,
+                            kind,
+                        });
+                    }
+                }
+                EXPORT_SECTION => {
+                    for _ in 0..section.u32()? {
+                        let name = section.name()?;
+                        let kind = external_kind(section.byte()?)?;
+                        section.u32()?; // the index of what's exported
+                        module.exports.push(Export { name, kind });
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(module)
+    }
+    // why zellij can't load it, if it can't
+    pub fn compatibility_problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let missing_exports: Vec<&str> = PLUGIN_EXPORTS
+            .iter()
+            .copied()
+            .filter(|name| {
+                !self
+                    .exports
+                    .iter()
+                    .any(|e| e.name == *name && e.kind == ExternalKind::Function)
+            })
+            .collect();
+        if !missing_exports.is_empty() {
+            problems.push(format!(
+                "it doesn't export {} (is it a binary crate that calls register_plugin!?)",
+                missing_exports.join(", ")
+            ));
+        }
+        let unknown_imports: Vec<String> = self
+            .imports
+            .iter()
+            .filter(|i| !is_provided(i))
+            .map(|i| format!("{}::{}", i.module, i.name))
+            .collect();
+        if !unknown_imports.is_empty() {
+            problems.push(format!(
+                "it imports {}, which zellij doesn't provide (is a dependency native only?)",
+                unknown_imports.join(", ")
+            ));
+        }
+        problems
+    }
+}
+
+fn is_provided(import: &Import) -> bool {
+    import.kind == ExternalKind::Function
+        && match import.module.as_str() {
+            ZELLIJ_MODULE => ZELLIJ_FUNCTIONS.contains(&import.name.as_str()),
+            WASI_MODULE => WASI_FUNCTIONS.contains(&import.name.as_str()),
+            _ => false,
+        }
+}
+
+fn external_kind(byte: u8) -> Result<ExternalKind, String> {
+    match byte {
+        0 => Ok(ExternalKind::Function),
+        1 => Ok(ExternalKind::Table),
+        2 => Ok(ExternalKind::Memory),
+        3 => Ok(ExternalKind::Global),
+        4 => Ok(ExternalKind::Tag),
+        byte => Err(format!("unknown external kind {}", byte)),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_at_end(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "it's truncated".to_owned())?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+    // unsigned LEB128, which is how wasm encodes every integer
+    fn leb128(&mut self, max_bits: u32) -> Result<u64, String> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= max_bits {
+                return Err("it has an integer that's too large".to_owned());
+            }
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        self.leb128(32).map(|v| v as u32)
+    }
+    fn name(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| "it has a name that isn't UTF-8".to_owned())
+    }
+    // the minimum and maximum of a table or a memory, 64 bit for memory64
+    fn limits(&mut self) -> Result<(), String> {
+        let flags = self.byte()?;
+        let bits = if flags & 0x04 != 0 { 64 } else { 32 };
+        self.leb128(bits)?;
+        if flags & 0x01 != 0 {
+            self.leb128(bits)?;
+        }
+        Ok(())
+    }
+    fn import_description(&mut self) -> Result<ExternalKind, String> {
+        let kind = external_kind(self.byte()?)?;
+        match kind {
+            ExternalKind::Function => {
+                self.u32()?; // its type
+            }
+            ExternalKind::Table => {
+                self.byte()?; // the type of its elements
+                self.limits()?;
+            }
+            ExternalKind::Memory => self.limits()?,
+            ExternalKind::Global => {
+                self.byte()?; // its type
+                self.byte()?; // whether it's mutable
+            }
+            ExternalKind::Tag => {
+                self.byte()?; // its attribute
+                self.u32()?; // its type
+            }
+        }
+        Ok(kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut bytes = vec![name.len() as u8];
+        bytes.extend(name.as_bytes());
+        bytes
+    }
+
+    fn section(id: u8, content: Vec<u8>) -> Vec<u8> {
+        // a size that takes two bytes, to exercise LEB128
+        let mut bytes = vec![
+            id,
+            0x80 | (content.len() as u8 & 0x7f),
+            (content.len() >> 7) as u8,
+        ];
+        bytes.extend(content);
+        bytes
+    }
+
+    fn module(imports: &[(&str, &str)], exports: &[(&str, u8)]) -> Vec<u8> {
+        let mut import_section = vec![imports.len() as u8];
+        for (module, field) in imports {
+            import_section.extend(name(module));
+            import_section.extend(name(field));
+            import_section.extend(&[0, 0]); // a function of type 0
+        }
+        let mut export_section = vec![exports.len() as u8];
+        for (export, kind) in exports {
+            export_section.extend(name(export));
+            export_section.extend(&[*kind, 0]);
+        }
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        bytes.extend(section(1, vec![1, 0x60, 0, 0])); // a type section, which is skipped
+        bytes.extend(section(IMPORT_SECTION, import_section));
+        bytes.extend(section(EXPORT_SECTION, export_section));
+        bytes
+    }
+
+    fn plugin_exports() -> Vec<(&'static str, u8)> {
+        PLUGIN_EXPORTS.iter().map(|export| (*export, 0)).collect()
+    }
+
+    #[test]
+    fn reads_the_imports_and_exports() {
+        let bytes = module(&[("zellij", "host_run_plugin_command")], &[("memory", 2)]);
+        let module = WasmModule::parse(&bytes).unwrap();
+        assert_eq!(
+            module.imports,
+            vec![Import {
+                module: "zellij".to_owned(),
+                name: "host_run_plugin_command".to_owned(),
+                kind: ExternalKind::Function,
+            }]
+        );
+        assert_eq!(
+            module.exports,
+            vec![Export {
+                name: "memory".to_owned(),
+                kind: ExternalKind::Memory
+            }]
+        );
+    }
+
+    #[test]
+    fn a_plugin_importing_only_zellij_and_wasi_is_compatible() {
+        let imports = [
+            ("zellij", "host_run_plugin_command"),
+            ("wasi_snapshot_preview1", "fd_write"),
+        ];
+        let module = WasmModule::parse(&module(&imports, &plugin_exports())).unwrap();
+        assert!(module.compatibility_problems().is_empty());
+    }
+
+    #[test]
+    fn finds_missing_exports() {
+        let exports = [("memory", 2), ("load", 0), ("render", 0), ("update", 2)];
+        let module = WasmModule::parse(&module(&[], &exports)).unwrap();
+        assert_eq!(
+            module.compatibility_problems(),
+            vec!["it doesn't export _start, update, pipe, plugin_version (is it a binary crate that calls register_plugin!?)".to_owned()]
+        );
+    }
+
+    #[test]
+    fn finds_imports_zellij_doesnt_provide() {
+        let imports = [("env", "sqlite3_open"), ("zellij", "host_open_file")];
+        let module = WasmModule::parse(&module(&imports, &plugin_exports())).unwrap();
+        assert_eq!(
+            module.compatibility_problems(),
+            vec!["it imports env::sqlite3_open, zellij::host_open_file, which zellij doesn't provide (is a dependency native only?)".to_owned()]
+        );
+    }
+
+    #[test]
+    fn a_truncated_module_is_an_error() {
+        let bytes = module(&[("zellij", "host_run_plugin_command")], &plugin_exports());
+        assert_eq!(
+            WasmModule::parse(&bytes[..bytes.len() - 1]),
+            Err("it's truncated".to_owned())
+        );
+        assert_eq!(WasmModule::parse(b"\0as"), Err("it's truncated".to_owned()));
+        // the size of the last section is cut in the middle of its LEB128 encoding
+        assert_eq!(
+            WasmModule::parse(b"\0asm\x01\0\0\0\x07\x80"),
+            Err("it's truncated".to_owned())
+        );
+    }
+
+    #[test]
+    fn a_malformed_module_is_an_error() {
+        assert_eq!(
+            WasmModule::parse(b"\x7fELF\x02\x01\x01\0"),
+            Err("it isn't a wasm module".to_owned())
+        );
+        assert_eq!(
+            WasmModule::parse(b"\0asm\x0d\0\x01\0"),
+            Err("it isn't a version 1 wasm module (eg. it's a component)".to_owned())
+        );
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        bytes.extend(section(EXPORT_SECTION, vec![1, 1, b'x', 9, 0]));
+        assert_eq!(
+            WasmModule::parse(&bytes),
+            Err("unknown external kind 9".to_owned())
+        );
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        bytes.extend(section(EXPORT_SECTION, vec![1, 1, 0xff, 0, 0]));
+        assert_eq!(
+            WasmModule::parse(&bytes),
+            Err("it has a name that isn't UTF-8".to_owned())
+        );
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        bytes.extend(&[EXPORT_SECTION, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+        assert_eq!(
+            WasmModule::parse(&bytes),
+            Err("it has an integer that's too large".to_owned())
+        );
+    }
+}
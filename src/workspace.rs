@@ -0,0 +1,449 @@
+use zellij_tile::prelude::*;
+
+use crate::cargo_workspace::CargoProject;
+use crate::hooks::{HookConfig, HookKind, Hooks};
+use crate::host::{HostEffect, RecordingHost};
+use crate::run_and_reload::{BuildConfig, RunAndReload};
+use crate::update_workspace::UpdateWorkspace;
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// the events that drive the workspace (binding the reload shortcut, compiling and reloading the
+// developed plugin), as data so that they can be recorded and replayed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DevEvent {
+    Loaded {
+        own_plugin_id: u32,
+        reload_shortcut: Option<String>,
+        #[serde(default)]
+        bind_in_locked_mode: bool,
+        #[serde(default = "close_with_plugin_by_default")]
+        close_with_plugin: bool,
+        #[serde(default)]
+        build_config: BuildConfig,
+        #[serde(default)]
+        hook_config: HookConfig,
+    },
+    CwdChanged(PathBuf),
+    CargoProjectDetected {
+        cargo_project: CargoProject,
+        selected_members: Vec<String>,
+    },
+    MembersSelected(Vec<String>),
+    PermissionsGranted,
+    TabUpdate(Vec<TabInfo>),
+    PaneUpdate(PaneManifest),
+    ModeUpdate {
+        base_mode: Option<InputMode>,
+        session_name: Option<String>,
+        // None if zellij didn't send the keybinds
+        #[serde(default)]
+        recompile_bindings: Option<Vec<(InputMode, KeyWithModifier)>>,
+    },
+    RecompileRequested,
+    ReloadRequested,
+    CompilationPaneOpened(u32),
+    CompilationPaneExited {
+        terminal_pane_id: u32,
+        exit_code: Option<i32>,
+    },
+    QuietBuildFinished {
+        exit_code: Option<i32>,
+    },
+    // the succeeded build's warnings and wasm were checked, and whether they allow reloading it
+    ReloadChecked {
+        reload: bool,
+    },
+    QuietBuildsToggled,
+    HookPaneOpened {
+        hook: HookKind,
+        terminal_pane_id: u32,
+    },
+    HookPaneExited {
+        terminal_pane_id: u32,
+        exit_code: Option<i32>,
+    },
+    PaneClosed(PaneId),
+}
+
+#[derive(Debug)]
+pub struct Workspace {
+    run_and_reload: RunAndReload<RecordingHost>,
+    update_workspace: UpdateWorkspace<RecordingHost>,
+    hooks: Hooks<RecordingHost>,
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        let reload_shortcut = KeyWithModifier::new(BareKey::Char('r'))
+            .with_ctrl_modifier()
+            .with_shift_modifier();
+        Workspace {
+            run_and_reload: Default::default(),
+            update_workspace: UpdateWorkspace::with_host(reload_shortcut, Default::default()),
+            hooks: Default::default(),
+        }
+    }
+}
+
+impl Workspace {
+    // applies the event to the workspace and returns the host calls it results in, without
+    // making them
+    pub fn reduce(&mut self, event: DevEvent) -> Vec<HostEffect> {
+        match event {
+            DevEvent::Loaded {
+                own_plugin_id,
+                reload_shortcut,
+                bind_in_locked_mode,
+                close_with_plugin,
+                build_config,
+                hook_config,
+            } => {
+                self.run_and_reload.update_build_config(build_config);
+                self.hooks.update_config(hook_config);
+                self.update_workspace
+                    .update_close_with_plugin(close_with_plugin);
+                self.update_workspace
+                    .update_bind_in_locked_mode(bind_in_locked_mode);
+                if let Some(reload_shortcut) = reload_shortcut {
+                    self.update_workspace
+                        .update_reload_shortcut(&reload_shortcut);
+                }
+                self.update_workspace.update_own_plugin_id(own_plugin_id);
+            }
+            DevEvent::CwdChanged(cwd) => {
+                self.run_and_reload.update_cwd(cwd);
+            }
+            DevEvent::CargoProjectDetected {
+                cargo_project,
+                selected_members,
+            } => {
+                self.run_and_reload
+                    .update_cargo_project(cargo_project, &selected_members);
+            }
+            DevEvent::MembersSelected(selected_members) => {
+                self.run_and_reload
+                    .update_selected_members(&selected_members);
+            }
+            DevEvent::PermissionsGranted => {
+                self.update_workspace.rename_own_pane();
+            }
+            DevEvent::TabUpdate(tab_infos) => {
+                self.update_workspace.update_tab_infos(tab_infos);
+                self.update_workspace
+                    .bind_key_if_not_bound_and_tab_is_focused();
+            }
+            DevEvent::PaneUpdate(pane_manifest) => {
+                let target_directory = self.run_and_reload.get_target_directory();
+                let artifact_names = self.run_and_reload.get_artifact_names();
+                self.update_workspace.update_pane_manifest(pane_manifest);
+                self.update_workspace
+                    .bind_key_if_not_bound_and_tab_is_focused();
+                self.update_workspace.update_plugin_pane_ids(|plugin_url| {
+                    is_developed_plugin_url(
+                        plugin_url,
+                        target_directory.as_deref(),
+                        &artifact_names,
+                    )
+                });
+                self.update_workspace
+                    .rename_plugin_pane_if_needed(artifact_name);
+            }
+            DevEvent::ModeUpdate {
+                base_mode,
+                session_name,
+                recompile_bindings,
+            } => {
+                if let Some(session_name) = session_name {
+                    self.update_workspace.update_session_name(session_name);
+                }
+                if let Some(recompile_bindings) = recompile_bindings {
+                    self.update_workspace
+                        .update_recompile_bindings(&recompile_bindings);
+                }
+                if let Some(base_mode) = base_mode {
+                    self.update_workspace.update_base_mode(base_mode);
+                }
+                self.update_workspace
+                    .bind_key_if_not_bound_and_tab_is_focused();
+            }
+            DevEvent::RecompileRequested => {
+                if self.hooks.has_pre_build() {
+                    self.hooks.run_pre_build(
+                        self.run_and_reload.get_cwd(),
+                        self.run_and_reload.get_quiet_builds(),
+                    );
+                } else {
+                    self.run_and_reload.run_compilation();
+                }
+            }
+            DevEvent::ReloadRequested => {
+                self.run_and_reload.start_or_reload_plugin();
+                self.run_post_reload_hook();
+            }
+            DevEvent::CompilationPaneOpened(terminal_pane_id) => {
+                self.run_and_reload
+                    .update_compilation_pane_id(terminal_pane_id);
+            }
+            DevEvent::CompilationPaneExited {
+                terminal_pane_id,
+                exit_code,
+            } => {
+                if self
+                    .run_and_reload
+                    .command_pane_exited(exit_code, terminal_pane_id)
+                {
+                    self.run_post_reload_hook();
+                }
+            }
+            DevEvent::QuietBuildFinished { exit_code } => {
+                if self.run_and_reload.quiet_build_finished(exit_code) {
+                    self.run_post_reload_hook();
+                }
+            }
+            DevEvent::ReloadChecked { reload } => {
+                if self.run_and_reload.reload_checked(reload) {
+                    self.run_post_reload_hook();
+                }
+            }
+            DevEvent::QuietBuildsToggled => {
+                self.run_and_reload.toggle_quiet_builds();
+            }
+            DevEvent::HookPaneOpened {
+                hook,
+                terminal_pane_id,
+            } => {
+                self.hooks.update_pane_id(hook, terminal_pane_id);
+            }
+            DevEvent::HookPaneExited {
+                terminal_pane_id,
+                exit_code,
+            } => {
+                // a failing pre-build hook aborts the build
+                if let Some((HookKind::PreBuild, true)) =
+                    self.hooks.pane_exited(terminal_pane_id, exit_code)
+                {
+                    self.run_and_reload.run_compilation();
+                }
+            }
+            DevEvent::PaneClosed(pane_id) => {
+                self.run_and_reload.pane_closed(pane_id);
+                self.update_workspace.pane_closed(pane_id);
+                self.hooks.pane_closed(pane_id);
+            }
+        }
+        let mut effects = self.run_and_reload.host_mut().take_effects();
+        effects.append(&mut self.update_workspace.host_mut().take_effects());
+        effects.append(&mut self.hooks.host_mut().take_effects());
+        effects
+    }
+    fn run_post_reload_hook(&mut self) {
+        let plugin_urls: Vec<String> = self
+            .run_and_reload
+            .get_plugin_paths()
+            .iter()
+            .map(|plugin_path| format!("file:{}", plugin_path.display()))
+            .collect();
+        if !plugin_urls.is_empty() {
+            self.hooks
+                .run_post_reload(self.run_and_reload.get_cwd(), &plugin_urls);
+        }
+    }
+    pub fn run_and_reload(&self) -> &RunAndReload<RecordingHost> {
+        &self.run_and_reload
+    }
+    pub fn update_workspace(&self) -> &UpdateWorkspace<RecordingHost> {
+        &self.update_workspace
+    }
+    pub fn hooks(&self) -> &Hooks<RecordingHost> {
+        &self.hooks
+    }
+    // the first of the plugins we build, if we build several workspace members
+    pub fn plugin_url(&self) -> Option<String> {
+        self.run_and_reload
+            .get_plugin_paths()
+            .first()
+            .map(|plugin_path| format!("file:{}", plugin_path.display()))
+    }
+    pub fn plugin_name(&self) -> Option<String> {
+        self.run_and_reload.get_artifact_names().first().cloned()
+    }
+}
+
+fn close_with_plugin_by_default() -> bool {
+    true
+}
+
+fn plugin_path(plugin_url: &str) -> &Path {
+    let plugin_path = plugin_url.strip_prefix("file:").unwrap_or(plugin_url);
+    Path::new(plugin_path.split('?').next().unwrap_or(plugin_path))
+}
+
+// the name of the wasm file, which is how we name the plugin's panes
+fn artifact_name(plugin_url: &str) -> Option<String> {
+    plugin_path(plugin_url)
+        .file_stem()
+        .and_then(|f| f.to_str())
+        .map(|f| f.to_owned())
+}
+
+// matches the plugins' wasm files in the target directory regardless of the build profile (eg.
+// target/wasm32-wasip1/release/my-plugin.wasm) and of any query zellij adds to the url
+fn is_developed_plugin_url(
+    plugin_url: &str,
+    target_directory: Option<&Path>,
+    artifact_names: &[String],
+) -> bool {
+    let target_directory = match target_directory {
+        Some(target_directory) => target_directory,
+        None => return false,
+    };
+    let plugin_path = plugin_path(plugin_url);
+    plugin_path.starts_with(target_directory)
+        && plugin_path.extension().and_then(|e| e.to_str()) == Some("wasm")
+        && artifact_name(plugin_url)
+            .map(|a| artifact_names.contains(&a))
+            .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_plugin_in_any_profile() {
+        let target_directory = Some(Path::new("/projects/my-plugin/target"));
+        let artifact_names = vec!["my-plugin".to_owned()];
+        let matches = |url| is_developed_plugin_url(url, target_directory, &artifact_names);
+        assert!(matches(
+            "file:/projects/my-plugin/target/wasm32-wasip1/debug/my-plugin.wasm"
+        ));
+        assert!(matches(
+            "file:/projects/my-plugin/target/wasm32-wasi/release/my-plugin.wasm?config=1"
+        ));
+        assert!(!matches(
+            "file:/projects/other/target/wasm32-wasip1/debug/my-plugin.wasm"
+        ));
+        assert!(!matches(
+            "file:/projects/my-plugin/target/wasm32-wasip1/debug/other.wasm"
+        ));
+        assert!(!matches("zellij:tab-bar"));
+        assert!(!is_developed_plugin_url(
+            "file:/projects/my-plugin/target/wasm32-wasip1/debug/my-plugin.wasm",
+            None,
+            &artifact_names
+        ));
+    }
+
+    #[test]
+    fn pre_build_hook_gates_the_build_and_post_reload_hook_follows_the_reload() {
+        let mut workspace = Workspace::default();
+        workspace.reduce(DevEvent::Loaded {
+            own_plugin_id: 1,
+            reload_shortcut: None,
+            bind_in_locked_mode: false,
+            close_with_plugin: true,
+            build_config: BuildConfig {
+                stream_progress: false,
+                ..Default::default()
+            },
+            hook_config: HookConfig {
+                pre_build_command: Some("buf generate".to_owned()),
+                post_reload_pipe: Some("init".to_owned()),
+                ..Default::default()
+            },
+        });
+        workspace.reduce(DevEvent::CwdChanged(PathBuf::from("/projects/my-plugin")));
+        let effects = workspace.reduce(DevEvent::RecompileRequested);
+        assert!(matches!(
+            &effects[0],
+            HostEffect::OpenCommandPaneFloating { command, .. } if command == "sh"
+        ));
+        workspace.reduce(DevEvent::HookPaneOpened {
+            hook: HookKind::PreBuild,
+            terminal_pane_id: 4,
+        });
+        let effects = workspace.reduce(DevEvent::HookPaneExited {
+            terminal_pane_id: 4,
+            exit_code: Some(1),
+        });
+        assert!(effects.is_empty());
+        workspace.reduce(DevEvent::RecompileRequested);
+        let effects = workspace.reduce(DevEvent::HookPaneExited {
+            terminal_pane_id: 4,
+            exit_code: Some(0),
+        });
+        assert!(effects.iter().any(|effect| matches!(
+            effect,
+            HostEffect::OpenCommandPaneFloating { command, .. } if command == "cargo"
+        )));
+        workspace.reduce(DevEvent::CompilationPaneOpened(5));
+        let effects = workspace.reduce(DevEvent::CompilationPaneExited {
+            terminal_pane_id: 5,
+            exit_code: Some(0),
+        });
+        assert_eq!(
+            effects.last(),
+            Some(&HostEffect::PipeMessageToPlugin {
+                plugin_url: "file:/projects/my-plugin/target/wasm32-wasip1/debug/my-plugin.wasm"
+                    .to_owned(),
+                name: "init".to_owned(),
+                payload: None,
+                args: Default::default(),
+                plugin_config: Default::default(),
+                pane_title: None,
+            })
+        );
+    }
+
+    #[test]
+    fn a_quiet_build_runs_its_pre_build_hook_in_the_background() {
+        let mut workspace = Workspace::default();
+        workspace.reduce(DevEvent::Loaded {
+            own_plugin_id: 1,
+            reload_shortcut: None,
+            bind_in_locked_mode: false,
+            close_with_plugin: true,
+            build_config: BuildConfig {
+                quiet: true,
+                stream_progress: false,
+                ..Default::default()
+            },
+            hook_config: HookConfig {
+                pre_build_command: Some("buf generate".to_owned()),
+                ..Default::default()
+            },
+        });
+        workspace.reduce(DevEvent::CwdChanged(PathBuf::from("/projects/my-plugin")));
+        let effects = workspace.reduce(DevEvent::RecompileRequested);
+        assert!(matches!(
+            effects.as_slice(),
+            [HostEffect::OpenCommandPaneBackground { command, .. }] if command == "sh"
+        ));
+        workspace.reduce(DevEvent::HookPaneOpened {
+            hook: HookKind::PreBuild,
+            terminal_pane_id: 4,
+        });
+        // the pane of a failed hook is shown, so that we know why nothing was built
+        let effects = workspace.reduce(DevEvent::HookPaneExited {
+            terminal_pane_id: 4,
+            exit_code: Some(1),
+        });
+        assert_eq!(
+            effects,
+            vec![HostEffect::ShowPaneWithId(PaneId::Terminal(4), true)]
+        );
+        let effects = workspace.reduce(DevEvent::RecompileRequested);
+        assert_eq!(effects, vec![HostEffect::RerunCommandPane(4)]);
+        let effects = workspace.reduce(DevEvent::HookPaneExited {
+            terminal_pane_id: 4,
+            exit_code: Some(0),
+        });
+        assert!(effects.iter().any(|effect| matches!(
+            effect,
+            HostEffect::RunCommand { command, .. } if command[0] == "cargo"
+        )));
+        assert!(!effects.contains(&HostEffect::HideSelf));
+    }
+}